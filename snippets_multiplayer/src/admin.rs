@@ -0,0 +1,531 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+
+use crate::{
+    admission::SharedBanList,
+    client_requests::{EntityCheckData, GroundItemData, SpriteData, SpriteIds},
+    game::{common::Vector2F, world::EntityId},
+    rooms::{RoomId, RoomRegistry, SharedRoomRegistry},
+    session_registry::SharedSessionRegistry,
+    world_handle::WorldHandle
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum AdminServerError {
+    #[error("IoError, reason='{0}'")]
+    IoError(#[from] tokio::io::Error),
+
+    #[error("Admin token must not be empty")]
+    EmptyToken,
+
+    #[error("Failed to shutdown admin server")]
+    ShutdownError,
+
+    #[error("Could not join task, reason='{0}'")]
+    TaskJoinError(#[from] tokio::task::JoinError),
+}
+
+fn default_room_id() -> RoomId {
+    RoomRegistry::MAIN_ROOM_ID
+}
+
+/// First request of every connection must be `Auth`
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum AdminRequest {
+    Auth {
+        token: String
+    },
+    ListSessions,
+    Kick {
+        #[serde(default = "default_room_id")]
+        room_id: RoomId,
+        player_id: EntityId,
+        #[serde(default)]
+        reason: String
+    },
+    SpawnNpc {
+        #[serde(default = "default_room_id")]
+        room_id: RoomId,
+        name: String,
+        position: Vector2F
+    },
+    /// Players can only be kicked
+    RemoveNpc {
+        #[serde(default = "default_room_id")]
+        room_id: RoomId,
+        entity_id: EntityId
+    },
+    Teleport {
+        #[serde(default = "default_room_id")]
+        room_id: RoomId,
+        entity_id: EntityId,
+        position: Vector2F
+    },
+    SetTickRate {
+        #[serde(default = "default_room_id")]
+        room_id: RoomId,
+        ticks_per_second: u32
+    },
+    DumpWorld {
+        #[serde(default = "default_room_id")]
+        room_id: RoomId
+    },
+    /// Read ban list file again and kick connected players which are banned now
+    ReloadBans,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SessionData {
+    pub room_id: RoomId,
+    pub player_id: EntityId,
+    pub address: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum AdminResponse {
+    Authenticated,
+    Unauthorized,
+    Sessions {
+        sessions: Vec<SessionData>
+    },
+    Kicked {
+        player_id: EntityId
+    },
+    NpcSpawned {
+        entity_id: EntityId
+    },
+    NpcRemoved {
+        entity_id: EntityId
+    },
+    Teleported {
+        entity_id: EntityId
+    },
+    TickRateChanged {
+        tick_interval_ms: f32
+    },
+    WorldDump {
+        room_id: RoomId,
+        tick_interval_ms: f32,
+        players: usize,
+        entities: Vec<EntityCheckData>,
+        /// Names of sprite ids used by `entities`
+        sprites: Vec<SpriteData>,
+        items: Vec<GroundItemData>,
+    },
+    BansReloaded {
+        banned: usize,
+        kicked: usize
+    },
+    BadRequest {
+        err: String
+    },
+    CommandFailed {
+        err: String
+    },
+}
+
+pub struct AdminServerHandler {
+    connection_task_handler: tokio::task::JoinHandle<()>,
+    shutdown_sender: tokio::sync::oneshot::Sender<()>,
+}
+
+/// Local console for inspecting and changing the running server
+pub struct AdminServer {
+    listener: tokio::net::TcpListener,
+    token: Arc<str>,
+}
+
+impl AdminServer {
+    pub const NPC_SIZE: Vector2F = Vector2F { x: 4.8, y: 4.8 };
+
+    pub async fn bind<A: tokio::net::ToSocketAddrs>(addr: A, token: &str) -> Result<Self, AdminServerError> {
+        if token.is_empty() {
+            return Err(AdminServerError::EmptyToken);
+        }
+
+        Ok(Self {
+            listener: tokio::net::TcpListener::bind(addr).await?,
+            token: token.into(),
+        })
+    }
+
+    pub fn get_local_address(&self) -> Result<std::net::SocketAddr, std::io::Error> {
+        self.listener.local_addr()
+    }
+
+    pub async fn run(self, rooms: SharedRoomRegistry, ban_list: SharedBanList) -> Result<AdminServerHandler, AdminServerError> {
+        let (shutdown_sender, mut shutdown_receiver) = tokio::sync::oneshot::channel();
+
+        let connection_task_handler = tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = &mut shutdown_receiver => {
+                        log::debug!("Admin server received shut down signal...");
+                        break;
+                    },
+                    incomming_connection = self.listener.accept() => {
+                        if let Ok((socket, address)) = incomming_connection {
+                            log::info!("Admin connected from {address:?}");
+                            tokio::spawn(process_admin_connection(socket, self.token.clone(), rooms.clone(), ban_list.clone()));
+                        }
+                    },
+                }
+            }
+        });
+
+        Ok(AdminServerHandler {
+            connection_task_handler,
+            shutdown_sender,
+        })
+    }
+}
+
+impl AdminServerHandler {
+    pub async fn shutdown(self) -> Result<(), AdminServerError> {
+        self.shutdown_sender.send(()).map_err(|_| AdminServerError::ShutdownError)?;
+        self.connection_task_handler.await?;
+        Ok(())
+    }
+}
+
+/// Compare without early exit, so token cannot be guessed by timing
+fn is_token_valid(expected: &str, given: &str) -> bool {
+    expected.len() == given.len() && expected.bytes().zip(given.bytes()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+async fn process_admin_connection(mut socket: tokio::net::TcpStream, token: Arc<str>, rooms: SharedRoomRegistry, ban_list: SharedBanList) {
+    let (reader, mut writer) = socket.split();
+    let mut lines = tokio::io::BufReader::new(reader).lines();
+    let mut authenticated = false;
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        let response = match serde_json::from_str::<AdminRequest>(line.trim()) {
+            Ok(AdminRequest::Auth { token: given_token }) => {
+                authenticated = is_token_valid(&token, &given_token);
+                if authenticated { AdminResponse::Authenticated } else { AdminResponse::Unauthorized }
+            },
+            Ok(_) if !authenticated => AdminResponse::Unauthorized,
+            Ok(request) => route_admin_request(request, &rooms, &ban_list).await,
+            Err(e) => AdminResponse::BadRequest { err: e.to_string() },
+        };
+
+        let mut response_str = serde_json::to_string(&response).expect("Could not serialize admin response");
+        response_str.push('\n');
+        if let Err(e) = writer.write_all(response_str.as_bytes()).await {
+            log::error!("Admin could not send response, reason: {e}");
+            break;
+        }
+
+        if matches!(response, AdminResponse::Unauthorized) {
+            log::warn!("Admin authentication failed, closing connection");
+            break;
+        }
+    }
+}
+
+fn room_state(rooms: &SharedRoomRegistry, room_id: RoomId) -> Result<(WorldHandle, SharedSessionRegistry), String> {
+    let rooms_guard = rooms.lock().map_err(|e| e.to_string())?;
+    let room = rooms_guard.get(room_id).map_err(|e| e.to_string())?;
+    Ok((room.world.clone(), room.sessions.clone()))
+}
+
+pub async fn route_admin_request(request: AdminRequest, rooms: &SharedRoomRegistry, ban_list: &SharedBanList) -> AdminResponse {
+    execute_admin_request(request, rooms, ban_list).await
+        .unwrap_or_else(|err| AdminResponse::CommandFailed { err })
+}
+
+async fn execute_admin_request(request: AdminRequest, rooms: &SharedRoomRegistry, ban_list: &SharedBanList) -> Result<AdminResponse, String> {
+    match request {
+        AdminRequest::Auth { .. } => Ok(AdminResponse::Authenticated),
+        AdminRequest::ListSessions => list_sessions(rooms),
+        AdminRequest::Kick { room_id, player_id, reason } => {
+            let (_, sessions) = room_state(rooms, room_id)?;
+            let sessions_guard = sessions.lock().map_err(|e| e.to_string())?;
+            let reason = if reason.is_empty() { "Kicked by admin".to_string() } else { reason };
+            if sessions_guard.kick(player_id, reason) {
+                log::warn!("Admin kicked player {player_id} from room {room_id}");
+                Ok(AdminResponse::Kicked { player_id })
+            } else {
+                Err(format!("Player {player_id} is not connected to room {room_id}"))
+            }
+        },
+        AdminRequest::SpawnNpc { room_id, name, position } => {
+            let (world, _) = room_state(rooms, room_id)?;
+            let entity_id = world.execute(move |world| world.create_entity_npc(name, position, AdminServer::NPC_SIZE))
+                .await
+                .map_err(|e| e.to_string())?
+                .map_err(|e| format!("{e:?}"))?;
+            Ok(AdminResponse::NpcSpawned { entity_id })
+        },
+        AdminRequest::RemoveNpc { room_id, entity_id } => {
+            let (world, _) = room_state(rooms, room_id)?;
+            world.execute(move |world| {
+                match world.get_entity_by_id(entity_id) {
+                    Some(entity) if entity.is_player() => Err(format!("Entity {entity_id} is a player, kick instead")),
+                    Some(_) => world.remove_entity(entity_id).map_err(|e| format!("{e:?}")),
+                    None => Err(format!("Entity {entity_id} does not exist")),
+                }
+            }).await.map_err(|e| e.to_string())??;
+            Ok(AdminResponse::NpcRemoved { entity_id })
+        },
+        AdminRequest::Teleport { room_id, entity_id, position } => {
+            let (world, _) = room_state(rooms, room_id)?;
+            world.execute(move |world| world.try_teleport_entity(entity_id, position))
+                .await
+                .map_err(|e| e.to_string())?
+                .map_err(|e| format!("{e:?}"))?;
+            Ok(AdminResponse::Teleported { entity_id })
+        },
+        AdminRequest::SetTickRate { room_id, ticks_per_second } => {
+            let rooms_guard = rooms.lock().map_err(|e| e.to_string())?;
+            let room = rooms_guard.get(room_id).map_err(|e| e.to_string())?;
+            let tick_interval = room.set_tick_rate(ticks_per_second).map_err(|e| e.to_string())?;
+            Ok(AdminResponse::TickRateChanged { tick_interval_ms: tick_interval.as_secs_f32() * 1000.0 })
+        },
+        AdminRequest::DumpWorld { room_id } => dump_world(rooms, room_id),
+        AdminRequest::ReloadBans => reload_bans(rooms, ban_list),
+    }
+}
+
+fn reload_bans(rooms: &SharedRoomRegistry, ban_list: &SharedBanList) -> Result<AdminResponse, String> {
+    let mut ban_list_guard = ban_list.lock().map_err(|e| e.to_string())?;
+    let banned = ban_list_guard.reload().map_err(|e| e.to_string())?;
+    log::warn!("Admin reloaded ban list, {banned} addresses banned");
+
+    let rooms_sessions: Vec<SharedSessionRegistry> = rooms.lock()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .map(|room| room.sessions.clone())
+        .collect();
+
+    let mut kicked = 0;
+    for room_sessions in rooms_sessions {
+        let sessions_guard = room_sessions.lock().map_err(|e| e.to_string())?;
+        let banned_players: Vec<EntityId> = sessions_guard.iter()
+            .filter(|(_, session)| ban_list_guard.is_banned(&session.address.ip()))
+            .map(|(player_id, _)| player_id)
+            .collect();
+        for player_id in banned_players {
+            if sessions_guard.kick(player_id, "Banned".to_string()) {
+                kicked += 1;
+            }
+        }
+    }
+
+    Ok(AdminResponse::BansReloaded { banned, kicked })
+}
+
+fn list_sessions(rooms: &SharedRoomRegistry) -> Result<AdminResponse, String> {
+    let rooms_sessions: Vec<(RoomId, SharedSessionRegistry)> = rooms.lock()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .map(|room| (room.id, room.sessions.clone()))
+        .collect();
+
+    let mut sessions = vec![];
+    for (room_id, room_sessions) in rooms_sessions {
+        let sessions_guard = room_sessions.lock().map_err(|e| e.to_string())?;
+        sessions.extend(sessions_guard.iter().map(|(player_id, session)| SessionData {
+            room_id,
+            player_id,
+            address: session.address.to_string(),
+        }));
+    }
+    sessions.sort_by_key(|session| (session.room_id, session.player_id));
+
+    Ok(AdminResponse::Sessions { sessions })
+}
+
+fn dump_world(rooms: &SharedRoomRegistry, room_id: RoomId) -> Result<AdminResponse, String> {
+    let tick_interval = rooms.lock()
+        .map_err(|e| e.to_string())?
+        .get(room_id)
+        .map_err(|e| e.to_string())?
+        .tick_interval();
+    let (world, sessions) = room_state(rooms, room_id)?;
+
+    let snapshot = world.snapshot();
+    let players = sessions.lock().map_err(|e| e.to_string())?.len();
+    let mut sprite_ids = SpriteIds::default();
+
+    Ok(AdminResponse::WorldDump {
+        room_id,
+        tick_interval_ms: tick_interval.as_secs_f32() * 1000.0,
+        players,
+        entities: EntityCheckData::vec_from_iter(snapshot.iter_entities(), &mut sprite_ids),
+        sprites: sprite_ids.take_new(),
+        items: GroundItemData::vec_from_iter(snapshot.iter_ground_items(), snapshot.get_item_definitions()),
+    })
+}
+
+#[test]
+fn test_admin_token_comparison() {
+    assert!(is_token_valid("secret", "secret"));
+    assert!(!is_token_valid("secret", "secreT"));
+    assert!(!is_token_valid("secret", "secret1"));
+    assert!(!is_token_valid("secret", ""));
+}
+
+#[tokio::test]
+async fn test_admin_requires_token() {
+    let rooms = RoomRegistry::new(Default::default()).into_shared();
+    let admin_server = AdminServer::bind("127.0.0.1:0", "secret").await.unwrap();
+    let admin_address = admin_server.get_local_address().unwrap();
+    let admin_handler = admin_server.run(rooms.clone(), crate::admission::BanList::new().into_shared()).await.unwrap();
+
+    let (reader, mut writer) = tokio::net::TcpStream::connect(admin_address).await.unwrap().into_split();
+    let mut lines = tokio::io::BufReader::new(reader).lines();
+    writer.write_all(b"{\"type\":\"ListSessions\"}\n").await.unwrap();
+    let response: AdminResponse = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+    assert!(matches!(response, AdminResponse::Unauthorized));
+    assert!(lines.next_line().await.unwrap().is_none(), "Connection should be closed");
+
+    let (reader, mut writer) = tokio::net::TcpStream::connect(admin_address).await.unwrap().into_split();
+    let mut lines = tokio::io::BufReader::new(reader).lines();
+    for (request, expected) in [
+        ("{\"type\":\"Auth\",\"token\":\"secret\"}\n", "Authenticated"),
+        ("{\"type\":\"SpawnNpc\",\"name\":\"Tuna\",\"position\":{\"x\":10.0,\"y\":0.0}}\n", "NpcSpawned"),
+        ("{\"type\":\"SetTickRate\",\"ticks_per_second\":10}\n", "TickRateChanged"),
+    ] {
+        writer.write_all(request.as_bytes()).await.unwrap();
+        let response = lines.next_line().await.unwrap().unwrap();
+        assert!(response.contains(expected), "Got '{response}'");
+    }
+
+    let main_room_world = rooms.lock().unwrap().get(RoomRegistry::MAIN_ROOM_ID).unwrap().world.clone();
+    let names = main_room_world.execute(|world| world.iter_entities().map(|e| e.name.clone()).collect::<Vec<_>>()).await.unwrap();
+    assert_eq!(names, vec!["Tuna".to_string()]);
+
+    admin_handler.shutdown().await.unwrap();
+    let rooms_to_stop = rooms.lock().unwrap().drain();
+    for room in rooms_to_stop {
+        room.shutdown().await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_admin_kicks_connected_player() {
+    use crate::{client_requests::{ClientRequest, ClientResponse}, multiplayer_server::MultiplayerServer};
+
+    let server = MultiplayerServer::bind_any_local().await.unwrap();
+    let server_address = server.get_local_address().unwrap();
+    let server_handler = server.run().await.unwrap();
+
+    let (reader, mut writer) = tokio::net::TcpStream::connect(server_address).await.unwrap().into_split();
+    let mut lines = tokio::io::BufReader::new(reader).lines();
+    writer.write_all(format!("{}\n", serde_json::to_string(&ClientRequest::GetId).unwrap()).as_bytes()).await.unwrap();
+    let ClientResponse::GetId { id: player_id } = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap() else {
+        panic!("Expected id");
+    };
+
+    let AdminResponse::Sessions { sessions } = route_admin_request(AdminRequest::ListSessions, &server_handler.rooms, &server_handler.admission.ban_list).await else {
+        panic!("Expected sessions");
+    };
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].player_id, player_id);
+
+    let response = route_admin_request(AdminRequest::Kick { 
+        room_id: RoomRegistry::MAIN_ROOM_ID, 
+        player_id, 
+        reason: "Bye".to_string() 
+    }, &server_handler.rooms, &server_handler.admission.ban_list).await;
+    assert!(matches!(response, AdminResponse::Kicked { .. }));
+
+    let kicked = tokio::time::timeout(std::time::Duration::from_secs(1), lines.next_line()).await.unwrap().unwrap().unwrap();
+    assert!(matches!(serde_json::from_str(&kicked).unwrap(), ClientResponse::Kicked { reason } if reason == "Bye"));
+    assert!(lines.next_line().await.unwrap().is_none(), "Connection should be closed");
+    // Removal is queued before connection is closed
+    assert!(server_handler.world.execute(move |world| world.get_entity_by_id(player_id).is_none()).await.unwrap());
+
+    let response = route_admin_request(AdminRequest::RemoveNpc { room_id: 7, entity_id: 0 }, &server_handler.rooms, &server_handler.admission.ban_list).await;
+    assert!(matches!(response, AdminResponse::CommandFailed { .. }));
+
+    drop((lines, writer));
+    server_handler.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_admin_kicks_player_with_full_push_queue() {
+    use crate::{client_requests::{ClientRequest, ClientResponse}, multiplayer_server::MultiplayerServer};
+
+    let server = MultiplayerServer::bind_any_local().await.unwrap();
+    let server_address = server.get_local_address().unwrap();
+    let server_handler = server.run().await.unwrap();
+
+    let (reader, mut writer) = tokio::net::TcpStream::connect(server_address).await.unwrap().into_split();
+    let mut lines = tokio::io::BufReader::new(reader).lines();
+    writer.write_all(format!("{}\n", serde_json::to_string(&ClientRequest::GetId).unwrap()).as_bytes()).await.unwrap();
+    let ClientResponse::GetId { id: player_id } = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap() else {
+        panic!("Expected id");
+    };
+
+    // Client does not read, pushes pile up until the queue is full
+    {
+        let sessions_guard = server_handler.sessions.lock().unwrap();
+        while sessions_guard.push(player_id, ClientResponse::Queued { position: 1 }) {}
+    }
+
+    let response = route_admin_request(AdminRequest::Kick { 
+        room_id: RoomRegistry::MAIN_ROOM_ID, 
+        player_id, 
+        reason: "Bye".to_string() 
+    }, &server_handler.rooms, &server_handler.admission.ban_list).await;
+    assert!(matches!(response, AdminResponse::Kicked { .. }));
+
+    // Queued pushes may arrive first, kick ends the connection
+    let mut last = None;
+    while let Some(line) = tokio::time::timeout(std::time::Duration::from_secs(1), lines.next_line()).await.unwrap().unwrap() {
+        last = Some(line);
+    }
+    assert!(matches!(serde_json::from_str(&last.unwrap()).unwrap(), ClientResponse::Kicked { reason } if reason == "Bye"));
+
+    drop((lines, writer));
+    server_handler.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_admin_reloaded_bans_kick_connected_player() {
+    use crate::{admission::BanList, client_requests::{ClientRequest, ClientResponse}, multiplayer_server::MultiplayerServer};
+
+    let path = std::env::temp_dir().join(format!("snippets_admin_bans_{}.toml", std::process::id()));
+    std::fs::write(&path, "ips = []\n").unwrap();
+    let server = MultiplayerServer::bind_any_local().await.unwrap()
+        .with_ban_list(BanList::load(&path).unwrap())
+        .with_max_players(1)
+        .with_queue_size(1);
+    let server_address = server.get_local_address().unwrap();
+    let server_handler = server.run().await.unwrap();
+
+    let (reader, mut writer) = tokio::net::TcpStream::connect(server_address).await.unwrap().into_split();
+    let mut lines = tokio::io::BufReader::new(reader).lines();
+    writer.write_all(format!("{}\n", serde_json::to_string(&ClientRequest::GetId).unwrap()).as_bytes()).await.unwrap();
+    assert!(matches!(serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap(), ClientResponse::GetId { .. }));
+
+    let queued = tokio::net::TcpStream::connect(server_address).await.unwrap();
+    let mut queued_lines = tokio::io::BufReader::new(queued).lines();
+    assert!(matches!(serde_json::from_str(&queued_lines.next_line().await.unwrap().unwrap()).unwrap(), ClientResponse::Queued { position: 1 }));
+
+    std::fs::write(&path, "ips = [\"127.0.0.1\"]\n").unwrap();
+    let response = route_admin_request(AdminRequest::ReloadBans, &server_handler.rooms, &server_handler.admission.ban_list).await;
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(response, AdminResponse::BansReloaded { banned: 1, kicked: 1 }));
+
+    let kicked = tokio::time::timeout(std::time::Duration::from_secs(1), lines.next_line()).await.unwrap().unwrap().unwrap();
+    assert!(matches!(serde_json::from_str(&kicked).unwrap(), ClientResponse::Kicked { .. }));
+    assert!(lines.next_line().await.unwrap().is_none(), "Connection should be closed");
+
+    // Client waiting in queue gets the freed place, but is banned now too
+    let rejected = tokio::time::timeout(std::time::Duration::from_secs(1), queued_lines.next_line()).await.unwrap().unwrap().unwrap();
+    assert!(matches!(
+        serde_json::from_str(&rejected).unwrap(),
+        ClientResponse::ConnectionRejected { reason: crate::admission::RejectionReason::Banned }
+    ));
+    assert!(queued_lines.next_line().await.unwrap().is_none(), "Connection should be closed");
+    assert_eq!(server_handler.admission.connection_queue.active(), 0);
+
+    server_handler.shutdown().await.unwrap();
+}
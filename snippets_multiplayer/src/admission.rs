@@ -0,0 +1,376 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    net::IpAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex}
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::multiplayer_server::ShutdownNotice;
+
+pub type SharedBanList = Arc<Mutex<BanList>>;
+pub type SharedConnectionQueue = Arc<ConnectionQueue>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BanListError {
+    #[error("IoError, reason='{0}'")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Could not parse ban list, reason='{0}'")]
+    ParseError(#[from] toml::de::Error),
+
+    #[error("Ban list was not loaded from file")]
+    NoFile,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BanListFile {
+    #[serde(default)]
+    ips: Vec<IpAddr>,
+}
+
+/// Addresses which cannot connect, checked before player is spawned
+#[derive(Debug, Default)]
+pub struct BanList {
+    path: Option<PathBuf>,
+    ips: HashSet<IpAddr>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RejectionReason {
+    ServerFull,
+    Banned,
+}
+
+#[derive(Debug)]
+struct WaitingEntry {
+    ticket_id: u64,
+    position_sender: tokio::sync::watch::Sender<usize>,
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    active: usize,
+    waiting: VecDeque<WaitingEntry>,
+    next_ticket_id: u64,
+    /// Sent to connections dropped from the closed queue
+    shutdown_notice: Option<ShutdownNotice>,
+}
+
+/// Limits number of players, connections above limit wait in FIFO queue
+#[derive(Debug)]
+pub struct ConnectionQueue {
+    max_players: Option<usize>,
+    queue_size: usize,
+    state: Mutex<QueueState>,
+}
+
+/// Held by session for its whole lifetime, frees place for next in queue on drop
+#[derive(Debug)]
+pub struct PlayerSlot {
+    queue: SharedConnectionQueue,
+}
+
+/// Place in queue, leaves queue on drop
+#[derive(Debug)]
+pub struct QueueTicket {
+    ticket_id: u64,
+    position_receiver: tokio::sync::watch::Receiver<usize>,
+    queue: Option<SharedConnectionQueue>,
+}
+
+#[derive(Debug)]
+pub enum QueueEntry {
+    Admitted(PlayerSlot),
+    Queued(QueueTicket),
+    Rejected,
+}
+
+/// Everything checked before client enters the game
+#[derive(Debug, Clone)]
+pub struct Admission {
+    pub ban_list: SharedBanList,
+    pub connection_queue: SharedConnectionQueue,
+}
+
+impl BanList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_toml_str(toml: &str) -> Result<Self, BanListError> {
+        let file: BanListFile = toml::from_str(toml)?;
+        Ok(Self {
+            path: None,
+            ips: file.ips.into_iter().collect(),
+        })
+    }
+
+    /// Remembers path, so list can be reloaded
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, BanListError> {
+        let toml = std::fs::read_to_string(path.as_ref())?;
+        let mut ban_list = Self::from_toml_str(&toml)?;
+        ban_list.path = Some(path.as_ref().to_path_buf());
+        Ok(ban_list)
+    }
+
+    /// On error previous list stays in use. Returns number of banned addresses.
+    pub fn reload(&mut self) -> Result<usize, BanListError> {
+        let path = self.path.clone().ok_or(BanListError::NoFile)?;
+        *self = Self::load(path)?;
+        Ok(self.len())
+    }
+
+    pub fn into_shared(self) -> SharedBanList {
+        Arc::new(Mutex::new(self))
+    }
+
+    pub fn is_banned(&self, ip: &IpAddr) -> bool {
+        self.ips.contains(ip)
+    }
+
+    pub fn len(&self) -> usize {
+        self.ips.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ips.is_empty()
+    }
+}
+
+impl ConnectionQueue {
+    /// Without limit every connection is admitted
+    pub fn new(max_players: Option<usize>, queue_size: usize) -> Self {
+        Self {
+            max_players,
+            queue_size,
+            state: Mutex::new(QueueState::default()),
+        }
+    }
+
+    pub fn into_shared(self) -> SharedConnectionQueue {
+        Arc::new(self)
+    }
+
+    fn lock_state(&self) -> std::sync::MutexGuard<'_, QueueState> {
+        self.state.lock().expect("Connection queue mutex should not be poisoned")
+    }
+
+    pub fn join(self: &Arc<Self>) -> QueueEntry {
+        let mut state = self.lock_state();
+        let has_free_slot = self.max_players.is_none_or(|max_players| state.active < max_players);
+
+        if has_free_slot && state.waiting.is_empty() {
+            state.active += 1;
+            return QueueEntry::Admitted(PlayerSlot { queue: self.clone() });
+        }
+        if state.waiting.len() >= self.queue_size {
+            return QueueEntry::Rejected;
+        }
+
+        let ticket_id = state.next_ticket_id;
+        state.next_ticket_id += 1;
+        let (position_sender, position_receiver) = tokio::sync::watch::channel(state.waiting.len() + 1);
+        state.waiting.push_back(WaitingEntry {
+            ticket_id,
+            position_sender,
+        });
+
+        QueueEntry::Queued(QueueTicket {
+            ticket_id,
+            position_receiver,
+            queue: Some(self.clone()),
+        })
+    }
+
+    pub fn max_players(&self) -> Option<usize> {
+        self.max_players
+    }
+
+    pub fn active(&self) -> usize {
+        self.lock_state().active
+    }
+
+    pub fn waiting(&self) -> usize {
+        self.lock_state().waiting.len()
+    }
+
+    /// Waiting connections are dropped, their sessions send the notice and end without entering the game
+    pub fn close(&self, notice: ShutdownNotice) -> usize {
+        let mut state = self.lock_state();
+        state.shutdown_notice = Some(notice);
+        let dropped = state.waiting.len();
+        state.waiting.clear();
+        dropped
+    }
+
+    fn release(&self) {
+        let mut state = self.lock_state();
+        state.active -= 1;
+        if let Some(entry) = state.waiting.pop_front() {
+            state.active += 1;
+            entry.position_sender.send_replace(0);
+        }
+        Self::update_positions(&state);
+    }
+
+    /// Returns false if ticket was admitted already
+    fn leave_queue(&self, ticket_id: u64) -> bool {
+        let mut state = self.lock_state();
+        let Some(idx) = state.waiting.iter().position(|entry| entry.ticket_id == ticket_id) else {
+            return false;
+        };
+        state.waiting.remove(idx);
+        Self::update_positions(&state);
+        true
+    }
+
+    fn update_positions(state: &QueueState) {
+        for (idx, entry) in state.waiting.iter().enumerate() {
+            entry.position_sender.send_if_modified(|position| {
+                let changed = *position != idx + 1;
+                *position = idx + 1;
+                changed
+            });
+        }
+    }
+}
+
+impl Drop for PlayerSlot {
+    fn drop(&mut self) {
+        self.queue.release();
+    }
+}
+
+impl QueueTicket {
+    /// Starts from 1, 0 means admitted
+    pub fn position(&self) -> usize {
+        *self.position_receiver.borrow()
+    }
+
+    /// Resolves when position changes
+    pub async fn changed(&mut self) -> Result<usize, tokio::sync::watch::error::RecvError> {
+        self.position_receiver.changed().await?;
+        Ok(*self.position_receiver.borrow_and_update())
+    }
+
+    /// Set once queue was closed
+    pub fn shutdown_notice(&self) -> Option<ShutdownNotice> {
+        self.queue.as_ref()?.lock_state().shutdown_notice.clone()
+    }
+
+    /// Succeeds once ticket reaches front of the queue and place is freed
+    pub fn into_slot(mut self) -> Result<PlayerSlot, Self> {
+        if self.position() != 0 {
+            return Err(self);
+        }
+        let queue = self.queue.take().expect("Ticket should hold queue until converted");
+        Ok(PlayerSlot { queue })
+    }
+}
+
+impl Drop for QueueTicket {
+    fn drop(&mut self) {
+        if let Some(queue) = self.queue.take() {
+            // Admitted but never used, give place to the next one. Not admitted if queue was closed.
+            if !queue.leave_queue(self.ticket_id) && self.position() == 0 {
+                queue.release();
+            }
+        }
+    }
+}
+
+impl Admission {
+    pub fn new(ban_list: BanList, connection_queue: ConnectionQueue) -> Self {
+        Self {
+            ban_list: ban_list.into_shared(),
+            connection_queue: connection_queue.into_shared(),
+        }
+    }
+
+    pub fn is_banned(&self, ip: &IpAddr) -> bool {
+        self.ban_list.lock().is_ok_and(|ban_list| ban_list.is_banned(ip))
+    }
+}
+
+impl Default for Admission {
+    fn default() -> Self {
+        Self::new(BanList::new(), ConnectionQueue::new(None, 0))
+    }
+}
+
+#[test]
+fn test_admission_ban_list_reload() {
+    let path = std::env::temp_dir().join(format!("snippets_bans_{}.toml", std::process::id()));
+    std::fs::write(&path, "ips = [\"10.0.0.7\"]\n").unwrap();
+    let mut ban_list = BanList::load(&path).unwrap();
+    assert!(ban_list.is_banned(&"10.0.0.7".parse().unwrap()));
+    assert!(!ban_list.is_banned(&"10.0.0.8".parse().unwrap()));
+
+    std::fs::write(&path, "ips = [\"10.0.0.8\", \"::1\"]\n").unwrap();
+    assert_eq!(ban_list.reload().unwrap(), 2);
+    assert!(!ban_list.is_banned(&"10.0.0.7".parse().unwrap()));
+    assert!(ban_list.is_banned(&"::1".parse().unwrap()));
+
+    // Broken file keeps previous list
+    std::fs::write(&path, "ips = [\"not an ip\"]\n").unwrap();
+    assert!(ban_list.reload().is_err());
+    assert_eq!(ban_list.len(), 2);
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(matches!(BanList::new().reload(), Err(BanListError::NoFile)));
+}
+
+#[test]
+fn test_admission_queue_positions() {
+    let queue = ConnectionQueue::new(Some(1), 2).into_shared();
+
+    let QueueEntry::Admitted(first) = queue.join() else { panic!("Expected admitted") };
+    let QueueEntry::Queued(second) = queue.join() else { panic!("Expected queued") };
+    let QueueEntry::Queued(third) = queue.join() else { panic!("Expected queued") };
+    assert!(matches!(queue.join(), QueueEntry::Rejected));
+    assert_eq!((second.position(), third.position()), (1, 2));
+
+    // Leaving queue moves others forward
+    drop(second);
+    assert_eq!(third.position(), 1);
+
+    let third = third.into_slot().unwrap_err();
+    drop(first);
+    assert_eq!(third.position(), 0);
+    let third = third.into_slot().unwrap();
+    assert_eq!((queue.active(), queue.waiting()), (1, 0));
+
+    drop(third);
+    assert_eq!(queue.active(), 0);
+}
+
+#[test]
+fn test_admission_unused_admitted_ticket_frees_place() {
+    let queue = ConnectionQueue::new(Some(1), 1).into_shared();
+    let QueueEntry::Admitted(first) = queue.join() else { panic!("Expected admitted") };
+    let QueueEntry::Queued(second) = queue.join() else { panic!("Expected queued") };
+
+    drop(first);
+    assert_eq!(queue.active(), 1);
+    drop(second);
+    assert_eq!(queue.active(), 0);
+}
+
+#[tokio::test]
+async fn test_admission_closed_queue_drops_waiting() {
+    let queue = ConnectionQueue::new(Some(1), 1).into_shared();
+    let QueueEntry::Admitted(first) = queue.join() else { panic!("Expected admitted") };
+    let QueueEntry::Queued(mut second) = queue.join() else { panic!("Expected queued") };
+
+    assert!(second.shutdown_notice().is_none());
+    assert_eq!(queue.close(ShutdownNotice::default()), 1);
+    assert!(second.changed().await.is_err());
+    assert_eq!(second.shutdown_notice().unwrap().reason, ShutdownNotice::default().reason);
+    drop(second);
+    assert_eq!(queue.active(), 1);
+    drop(first);
+    assert_eq!(queue.active(), 0);
+}
@@ -1,25 +1,13 @@
-use snippets_multiplayer::TEST_SERVER_ADRESS;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use snippets_multiplayer::{
+    client_requests::{ChatChannel, ClientRequest},
+    multiplayer_client::request_response,
+    TEST_SERVER_ADRESS
+};
 
 use tokio::net::TcpStream;
 use std::net::SocketAddr;
 
 
-async fn client_do_request_await_response(
-    req: &str,
-    buf_reader: &mut tokio::io::BufReader<tokio::net::tcp::ReadHalf<'_>>,
-    write: &mut tokio::net::tcp::WriteHalf<'_>,
-) -> String {
-    let mut buf_string = String::new();
-
-    write.write_all(req.as_bytes()).await.unwrap();
-    write.write_all(b"\n").await.unwrap();
-    write.flush().await.unwrap();
-
-    buf_reader.read_line(&mut buf_string).await.unwrap();
-    buf_string.trim().to_string()
-}
-
 #[tokio::main]
 async fn main() {
     env_logger::builder()
@@ -39,18 +27,22 @@ async fn main() {
     let mut buf_reader = tokio::io::BufReader::new(read_half);
 
     let requests = [
-        String::from("{\"type\":\"Healthcheck\"}"),
-        String::from("{\"type\":\"GetId\"}"),
-        String::from("{\"type\":\"WorldCheck\"}"),
+        ClientRequest::Healthcheck,
+        ClientRequest::GetId,
+        ClientRequest::WorldCheck,
+        ClientRequest::Chat { channel: ChatChannel::Global, text: "Hello everyone!".to_string() },
     ];
 
     for request in requests {
-        let response = client_do_request_await_response(
+        let response = request_response(
             &request,
             &mut buf_reader,
             &mut write_half,
+            |pushed| log::info!("Server pushed '{}'", serde_json::to_string(&pushed).unwrap()),
         ).await;
 
-        println!("'{request}' -> '{response}'");
+        let request = serde_json::to_string(&request).unwrap();
+        let response = response.map(|response| serde_json::to_string(&response).unwrap());
+        println!("'{request}' -> '{}'", response.unwrap_or_default());
     }
-}
\ No newline at end of file
+}
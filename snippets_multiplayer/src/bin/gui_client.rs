@@ -1,21 +1,21 @@
+use clap::Parser;
 use snippets_multiplayer::{
-    client_requests::{ClientRequest, ClientResponse, MoveDirection}, 
-    game::common::Vector2F, 
+    client_requests::{ChatChannel, ClientRequest, ClientResponse}, 
+    game::common::Vector2F,
+    input::{InputAction, InputMap},
+    multiplayer_client::request_response,
     rendering::{
-        renderer::State, AppData, EntityView
+        renderer::State, sprites::SpriteSheet, AppData, Renderer
     }, TEST_SERVER_ADRESS
 };
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
-use wgpu::naga::proc::NameKey;
-
-use std::{sync::{Arc, Mutex}, time::Duration};
+use std::{path::PathBuf, sync::{Arc, Mutex}, time::{Duration, Instant}};
 
 use winit::{
-    application::ApplicationHandler, event::{ElementState, WindowEvent}, event_loop::{
+    application::ApplicationHandler, event::{ElementState, MouseButton, WindowEvent}, event_loop::{
         ActiveEventLoop, 
         ControlFlow, 
         EventLoop
-    }, keyboard::Key, window::{
+    }, keyboard::{Key, NamedKey}, window::{
         Window, 
         WindowId
     }
@@ -25,9 +25,35 @@ use winit::{
 struct App {
     state: Option<State>,
     data: Arc<Mutex<AppData>>,
-    client_handler: Option<GuiClientHandle>
+    client_handler: Option<GuiClientHandle>,
+    last_frame: Option<Instant>,
+    /// In pixels from top-left corner of window, None when outside
+    cursor_position: Option<Vector2F>,
+    /// Where left button was pressed, while it is held
+    press_position: Option<Vector2F>,
+    /// Press turned into camera pan, so release does not select
+    is_dragging: bool,
+    input: InputMap,
+    /// Set before event loop starts, window is created with them
+    sprites: Option<SpriteSheet>,
+}
+
+#[derive(Debug, clap::Parser)]
+#[command(about = "Multiplayer snippets GUI client")]
+struct GuiArgs {
+    /// Path to TOML key bindings, defaults are used if not set
+    #[arg(long)]
+    input: Option<PathBuf>,
+    /// Path to sprite metadata JSON, embedded sprites are used if it can not be loaded
+    #[arg(long, default_value = "assets/sprites.json")]
+    sprites: PathBuf,
 }
 
+/// Weight of the newest frame in smoothed FPS
+const FPS_SMOOTHING: f32 = 0.1;
+/// Cursor has to move this many pixels with button held to start panning
+const DRAG_THRESHOLD: f32 = 4.0;
+
 impl ApplicationHandler for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         // Create window object
@@ -37,7 +63,8 @@ impl ApplicationHandler for App {
                 .unwrap(),
         );
 
-        let state = pollster::block_on(State::new(window.clone()));
+        let sprites = self.sprites.clone().expect("Sprites should be loaded before event loop starts");
+        let state = pollster::block_on(State::new(window.clone(), sprites));
         self.state = Some(state);
 
         window.request_redraw();
@@ -45,7 +72,6 @@ impl ApplicationHandler for App {
 
     fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
         let state = self.state.as_mut().unwrap();
-        let app_data = self.data.clone();
         match event {
             WindowEvent::CloseRequested => {
                 println!("The close button was pressed; stopping");
@@ -57,7 +83,19 @@ impl ApplicationHandler for App {
                 });
             }
             WindowEvent::RedrawRequested => {
-                if let Ok(app_data_guard) = self.data.lock() {
+                let now = Instant::now();
+                let frame_time = self.last_frame.replace(now).map(|last_frame| now - last_frame);
+                // Held movement key
+                if let Some(action) = frame_time.and_then(|frame_time| self.input.update(frame_time)) {
+                    apply_action(action, &self.data, self.client_handler.as_ref());
+                }
+                if let Ok(mut app_data_guard) = self.data.lock() {
+                    if let Some(frame_time) = frame_time.filter(|frame_time| !frame_time.is_zero()) {
+                        app_data_guard.animation_time += frame_time;
+                        app_data_guard.camera.update(frame_time);
+                        let fps = 1.0 / frame_time.as_secs_f32();
+                        app_data_guard.fps += (fps - app_data_guard.fps) * FPS_SMOOTHING;
+                    }
                     state.render(&app_data_guard)
                 };
 
@@ -67,71 +105,164 @@ impl ApplicationHandler for App {
             WindowEvent::Resized(size) => {
                 // Reconfigures the size of the surface. We do not re-render
                 // here as this event is always followed up by redraw request.
-                state.resize(size);
+                state.resize(size.width, size.height);
             },
             WindowEvent::MouseWheel { 
                 device_id: _, 
                 delta, 
                 phase: _ 
             } => {
-                let scroll_sentivity: f32 = 0.1;
-                match delta {
-                    winit::event::MouseScrollDelta::LineDelta(_, y) => {
-                        // y is +-1
-                        if let Ok(mut app_data_guard) = self.data.lock() {
-                            app_data_guard.scale *= (1.0 + scroll_sentivity).powf(y);
+                if let Ok(mut app_data_guard) = self.data.lock() {
+                    match delta {
+                        // Mouse wheel, y is +-1 per notch
+                        winit::event::MouseScrollDelta::LineDelta(_, y) => app_data_guard.camera.zoom_lines(y),
+                        // Touchpad
+                        winit::event::MouseScrollDelta::PixelDelta(position) => app_data_guard.camera.zoom_pixels(position.y as f32),
+                    }
+                }
+            },
+            WindowEvent::CursorMoved { device_id: _, position } => {
+                let position = Vector2F::new(position.x as f32, position.y as f32);
+                // Pan starts once cursor is far enough from press, then follows every move
+                let pan_from = if self.is_dragging {
+                    self.cursor_position
+                } else {
+                    self.press_position.filter(|press_position| (position - *press_position).length() > DRAG_THRESHOLD)
+                };
+                if let (Some(pan_from), Ok(mut app_data_guard)) = (pan_from, self.data.lock()) {
+                    let size = state.get_window().inner_size();
+                    app_data_guard.camera.pan(position - pan_from, (size.width, size.height));
+                    self.is_dragging = true;
+                }
+                self.cursor_position = Some(position);
+            },
+            WindowEvent::CursorLeft { device_id: _ } => {
+                self.cursor_position = None;
+            },
+            WindowEvent::MouseInput { device_id: _, state: ElementState::Pressed, button: MouseButton::Left } => {
+                self.press_position = self.cursor_position;
+                self.is_dragging = false;
+            },
+            WindowEvent::MouseInput { device_id: _, state: ElementState::Released, button: MouseButton::Left } => {
+                // Click without drag selects, click on empty ground clears selection
+                let size = state.get_window().inner_size();
+                let is_click = self.press_position.take().is_some() && !std::mem::take(&mut self.is_dragging);
+                if let (true, Some(cursor_position), Ok(mut app_data_guard)) = (is_click, self.cursor_position, self.data.lock()) {
+                    let world_position = app_data_guard.screen_to_world(cursor_position, (size.width, size.height));
+                    app_data_guard.selected = app_data_guard.entity_at(world_position);
+                }
+            },
+            WindowEvent::KeyboardInput { device_id: _, event, is_synthetic: _ } => {
+                let key = key_name(&event.logical_key);
+                match event.state {
+                    ElementState::Pressed => {
+                        if let Some(action) = self.input.key_down(&key, event.text.as_deref()) {
+                            apply_action(action, &self.data, self.client_handler.as_ref());
                         }
                     },
-                    winit::event::MouseScrollDelta::PixelDelta(_physical_position) => todo!(),
+                    ElementState::Released => self.input.key_up(&key),
+                }
+                if let Ok(mut app_data_guard) = self.data.lock() {
+                    app_data_guard.chat_draft = self.input.chat_draft().map(str::to_string);
                 }
             },
-            WindowEvent::KeyboardInput { device_id: _, event, is_synthetic: _ } => {
-                if event.state == ElementState::Released {
-                    match event.logical_key {
-                        Key::Named(winit::keyboard::NamedKey::ArrowUp) => {
-                            self.client_handler.as_ref().unwrap().move_headless(MoveDirection::Up);
-                        },
-                        Key::Named(winit::keyboard::NamedKey::ArrowRight) => {
-                            self.client_handler.as_ref().unwrap().move_headless(MoveDirection::Right);
-                        },
-                        Key::Named(winit::keyboard::NamedKey::ArrowDown) => {
-                            self.client_handler.as_ref().unwrap().move_headless(MoveDirection::Down);
-                        },
-                        Key::Named(winit::keyboard::NamedKey::ArrowLeft) => {
-                            self.client_handler.as_ref().unwrap().move_headless(MoveDirection::Left);
-                        },
-                        _ => {}
-                    }
-                }    
-            }
+            // Releases would not arrive, keys would stay held
+            WindowEvent::Focused(false) => self.input.release_all(),
             _ => (),
         }
     }
 }
 
+/// Name used in key bindings, e.g. "w" or "ArrowUp". Keys without name are never bound.
+fn key_name(key: &Key) -> String {
+    match key {
+        Key::Named(named) => named_key_name(*named).to_string(),
+        Key::Character(character) => character.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Names written in `assets/input.toml`, independent of winit debug output
+fn named_key_name(key: NamedKey) -> &'static str {
+    match key {
+        NamedKey::Enter => "Enter",
+        NamedKey::Escape => "Escape",
+        NamedKey::Backspace => "Backspace",
+        NamedKey::Tab => "Tab",
+        NamedKey::Space => "Space",
+        NamedKey::ArrowUp => "ArrowUp",
+        NamedKey::ArrowDown => "ArrowDown",
+        NamedKey::ArrowLeft => "ArrowLeft",
+        NamedKey::ArrowRight => "ArrowRight",
+        NamedKey::Home => "Home",
+        NamedKey::End => "End",
+        NamedKey::PageUp => "PageUp",
+        NamedKey::PageDown => "PageDown",
+        NamedKey::Insert => "Insert",
+        NamedKey::Delete => "Delete",
+        NamedKey::Shift => "Shift",
+        NamedKey::Control => "Control",
+        NamedKey::Alt => "Alt",
+        NamedKey::F1 => "F1",
+        NamedKey::F2 => "F2",
+        NamedKey::F3 => "F3",
+        NamedKey::F4 => "F4",
+        NamedKey::F5 => "F5",
+        NamedKey::F6 => "F6",
+        NamedKey::F7 => "F7",
+        NamedKey::F8 => "F8",
+        NamedKey::F9 => "F9",
+        NamedKey::F10 => "F10",
+        NamedKey::F11 => "F11",
+        NamedKey::F12 => "F12",
+        _ => "",
+    }
+}
+
+/// Server actions are sent as requests, others change the view
+fn apply_action(action: InputAction, app_data: &Mutex<AppData>, client_handler: Option<&GuiClientHandle>) {
+    if let Some(request) = action.to_request() {
+        if let Some(client_handler) = client_handler {
+            client_handler.send_request(request);
+        }
+        return;
+    }
+
+    if let Ok(mut app_data_guard) = app_data.lock() {
+        match action {
+            InputAction::ZoomIn => app_data_guard.camera.zoom_lines(1.0),
+            InputAction::ZoomOut => app_data_guard.camera.zoom_lines(-1.0),
+            InputAction::ToggleDebugOverlay => app_data_guard.hide_debug_overlay = !app_data_guard.hide_debug_overlay,
+            InputAction::Recenter => app_data_guard.camera.recenter(),
+            InputAction::Move(_) | InputAction::SendChat(_) => {},
+        }
+    }
+}
+
 struct GuiClient {
     socket: tokio::net::TcpStream
 }
 
 struct GuiClientHandle {
     task_handle: tokio::task::JoinHandle<()>,
-    app_data: Arc<Mutex<AppData>>,
-    contol_signals_tx: std::sync::mpsc::Sender<MoveDirection>
+    contol_signals_tx: std::sync::mpsc::Sender<ClientRequest>
 }
 
-async fn client_do_request_await_response(
-    req: &str,
-    buf_reader: &mut tokio::io::BufReader<tokio::net::tcp::ReadHalf<'_>>,
-    write: &mut tokio::net::tcp::WriteHalf<'_>,
-) -> String {
-    let mut buf_string = String::new();
-
-    write.write_all(req.as_bytes()).await.unwrap();
-    write.write_all(b"\n").await.unwrap();
-    write.flush().await.unwrap();
-
-    buf_reader.read_line(&mut buf_string).await.unwrap();
-    buf_string.trim().to_string()
+/// Chat goes to the log on screen, other pushed messages are only logged
+fn show_pushed(app_data: &Mutex<AppData>, pushed: ClientResponse) {
+    match pushed {
+        ClientResponse::ChatMessage { channel, from_name, text, .. } => {
+            let channel = match channel {
+                ChatChannel::Global => "Global",
+                ChatChannel::Proximity => "Proximity",
+                ChatChannel::Whisper { .. } => "Whisper",
+            };
+            if let Ok(mut app_data_guard) = app_data.lock() {
+                app_data_guard.push_chat(format!("[{channel}] {from_name}: {text}"));
+            }
+        },
+        pushed => log::info!("Server pushed '{}'", serde_json::to_string(&pushed).unwrap_or_default()),
+    }
 }
 
 impl GuiClient {
@@ -147,70 +278,47 @@ impl GuiClient {
     }
 
     async fn run(mut self, app_data: Arc<Mutex<AppData>>) -> GuiClientHandle {
-        let app_data_cloned = app_data.clone();
         let (contol_signals_tx, contol_signals_rx) = std::sync::mpsc::channel();
 
         let task_handle = tokio::task::spawn(async move {
             let (read_half, mut write_half) = self.socket.split();
             let mut buf_reader = tokio::io::BufReader::new(read_half);
 
+            let on_push = |pushed| show_pushed(&app_data, pushed);
+
             // store player id
-            let player_id = {
-                let response = client_do_request_await_response(
-                    "{\"type\":\"GetId\"}",
-                    &mut buf_reader,
-                    &mut write_half
-                ).await;
-
-                if let Ok(ClientResponse::GetId { id }) = serde_json::from_str(&response) {
-                    id
-                } else {
-                    panic!("PlayerGetID parse failed")
-                }
+            let player_id = match request_response(&ClientRequest::GetId, &mut buf_reader, &mut write_half, on_push).await {
+                Some(ClientResponse::GetId { id }) => id,
+                _ => panic!("PlayerGetID parse failed"),
             };
+            if let Ok(mut app_data_guard) = app_data.lock() {
+                app_data_guard.player_id = Some(player_id);
+            }
 
             loop {
-                let response = client_do_request_await_response(
-                    "{\"type\":\"WorldCheck\"}",
-                    &mut buf_reader,
-                    &mut write_half
-                ).await;
-                log::trace!("Client got response '{response}'.");
-
-                if let ClientResponse::WorldCheck { entities } = serde_json::from_str(&response).unwrap() {
+                let request_time = Instant::now();
+                let Some(response) = request_response(&ClientRequest::WorldCheck, &mut buf_reader, &mut write_half, on_push).await else {
+                    log::info!("Server closed connection");
+                    break;
+                };
+                let ping = request_time.elapsed();
+
+                if let ClientResponse::WorldCheck { entities, sprites, items, chunks, unloaded_chunks, .. } = response {
                     // Update shared data
                     if let Ok(mut app_data_guard) = app_data.lock() {
-                        app_data_guard.entities.clear();
-                        for entiy in entities {
-                            if entiy.id == player_id {
-                                app_data_guard.camera_position = entiy.position;
-                            }
-
-                            let color = [
-                                entiy.color[0] as f32 / 255.0,
-                                entiy.color[1] as f32 / 255.0,
-                                entiy.color[2] as f32 / 255.0
-                            ];
-
-                            app_data_guard.entities.push(EntityView { 
-                                position: entiy.position, 
-                                size: entiy.size, 
-                                color
-                            });
-                            
-                        }
+                        app_data_guard.update_sprites(sprites);
+                        app_data_guard.update_world(entities, items);
+                        app_data_guard.update_terrain(chunks, unloaded_chunks);
+                        app_data_guard.ping = Some(ping);
                     }
                 }
 
                 // Poll for control signals
-                if let Ok(move_dir) = contol_signals_rx.try_recv() {
-                    let request = serde_json::to_string(&ClientRequest::Move{dir: move_dir}).unwrap();
-                    let response = client_do_request_await_response(
-                        &request,
-                        &mut buf_reader,
-                        &mut write_half
-                    ).await;
-                    log::debug!("Client got response '{response}'.");
+                while let Ok(request) = contol_signals_rx.try_recv() {
+                    let response = request_response(&request, &mut buf_reader, &mut write_half, on_push).await;
+                    if let Some(response) = response {
+                        log::debug!("Client got response '{}'.", serde_json::to_string(&response).unwrap_or_default());
+                    }
                 }
                 
 
@@ -221,7 +329,6 @@ impl GuiClient {
 
         GuiClientHandle {
             task_handle,
-            app_data: app_data_cloned,
             contol_signals_tx
         }
     }
@@ -232,8 +339,8 @@ impl GuiClientHandle {
         self.task_handle.await
     }
 
-    fn move_headless(&self, direction: MoveDirection) {
-        self.contol_signals_tx.send(direction).unwrap();
+    fn send_request(&self, request: ClientRequest) {
+        self.contol_signals_tx.send(request).unwrap();
     }
 }
 
@@ -263,11 +370,28 @@ async fn main() {
     // the background.
     // event_loop.set_control_flow(ControlFlow::Wait);
 
+    let args = GuiArgs::parse();
+    let input = match &args.input {
+        Some(path) => InputMap::load(path),
+        None => Ok(InputMap::default()),
+    };
+    let input = match input {
+        Ok(input) => input,
+        Err(e) => {
+            eprintln!("Could not load key bindings, reason {e}");
+            std::process::exit(1);
+        }
+    };
+
+    let sprites = SpriteSheet::load(&args.sprites).unwrap_or_else(|e| {
+        log::warn!("Could not load sprites from {}, using embedded ones, reason {e}", args.sprites.display());
+        SpriteSheet::new().expect("Embedded sprites should load")
+    });
+
     let mut app = App {
-        data: Arc::new(Mutex::new(AppData {
-            scale: 0.5,
-            ..Default::default()
-        })),
+        data: Arc::new(Mutex::new(AppData::default())),
+        input,
+        sprites: Some(sprites),
         ..Default::default()
     };
     
@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+use clap::Parser;
+use snippets_multiplayer::{
+    loadtest::{run_load_test, BehaviourProfile, LoadTestConfig}, 
+    TEST_SERVER_ADRESS
+};
+
+/// Simulated players connected to running server
+#[derive(Debug, Parser)]
+#[command(about = "Load test of multiplayer snippets server")]
+struct LoadTestArgs {
+    #[arg(long, default_value = TEST_SERVER_ADRESS)]
+    address: std::net::SocketAddr,
+
+    #[arg(short, long, default_value_t = 100)]
+    players: usize,
+
+    /// Seconds of load after all players connected
+    #[arg(short, long, default_value_t = 10)]
+    duration: u64,
+
+    #[arg(long, value_enum, default_value_t = BehaviourProfile::Wanderer)]
+    profile: BehaviourProfile,
+
+    /// Seconds over which players connect
+    #[arg(long, default_value_t = 2)]
+    ramp_up: u64,
+}
+
+#[tokio::main]
+async fn main() {
+    env_logger::builder()
+        .filter_level(log::LevelFilter::Warn)
+        .format_timestamp_millis()
+        .format_file(false)
+        .format_line_number(true)
+        .init();
+
+    let args = LoadTestArgs::parse();
+    println!("Running {} {:?} players against {} for {}s...", args.players, args.profile, args.address, args.duration);
+
+    let report = run_load_test(LoadTestConfig {
+        address: args.address,
+        players: args.players,
+        duration: Duration::from_secs(args.duration),
+        profile: args.profile,
+        ramp_up: Duration::from_secs(args.ramp_up),
+    }).await;
+    println!("{report}");
+}
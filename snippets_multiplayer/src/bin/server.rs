@@ -1,8 +1,25 @@
-use snippets_multiplayer::{game::common::Vector2F, multiplayer_server::MultiplayerServer, TEST_SERVER_ADRESS};
+use clap::Parser;
+use snippets_multiplayer::{
+    admin::AdminServer, 
+    config::ServerArgs, 
+    metrics::MetricsServer, 
+    multiplayer_server::MultiplayerServer
+};
+
+/// Admin console is started only if token is provided
+const ADMIN_TOKEN_ENV: &str = "SNIPPETS_ADMIN_TOKEN";
 
 fn main() {
+    let config = match ServerArgs::parse().into_config() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Config error: {e}");
+            std::process::exit(2);
+        }
+    };
+
     env_logger::builder()
-        .filter_level(log::LevelFilter::Warn)
+        .filter_level(config.log_level)
         .format_timestamp_millis()
         .format_file(false)
         .format_line_number(true)
@@ -10,33 +27,39 @@ fn main() {
 
     let rt = tokio::runtime::Runtime::new().unwrap();
     rt.block_on(async {
-        let server = MultiplayerServer::bind(TEST_SERVER_ADRESS).await.unwrap();
+        let server = match MultiplayerServer::bind_with_config(&config).await {
+            Ok(server) => server,
+            Err(e) => {
+                log::error!("Server could not start, reason {e}");
+                std::process::exit(1);
+            }
+        };
         log::info!("MP-server, address:{:?}",  server.get_local_address().unwrap());
         
         let server_handler = server.run().await.unwrap();
-    
-        {
-            let mut world = server_handler.world.lock().unwrap();
-            world.create_entity_npc("Tuna", Vector2F::new(5.0, 10.0), Vector2F::new(4.8, 4.8));
-            world.create_entity_npc("Starlette", Vector2F::new(-5.0, 0.0), Vector2F::new(4.8, 4.8));
-            world.create_entity_npc("Bucket", Vector2F::new(5.0, -5.0), Vector2F::new(4.8, 4.8));
-            world.create_entity_npc("Sugar", Vector2F::new(5.0, 0.0), Vector2F::new(4.8, 4.8));
-            world.create_entity_npc("Tapioka", Vector2F::new(10.0, 5.0), Vector2F::new(4.8, 4.8));
-
-            for ix in -9..9 {
-                if (-2..3).contains(&ix) {
-                    continue;
-                }
-                for iy in -5..5 {
-                    if (-2..3).contains(&iy) {
-                        continue;
-                    }
-                    let x = (ix * 5) as f32;
-                    let y = (iy * 5) as f32;
-                    world.create_entity_npc("Bot", Vector2F::new(x, y), Vector2F::new(4.8, 4.8));
+
+        let metrics_handler = if config.features.metrics {
+            let metrics_server = MetricsServer::bind(config.metrics_address).await.unwrap();
+            log::info!("Metrics, address:{:?}", metrics_server.get_local_address().unwrap());
+            Some(metrics_server.run(server_handler.metrics.clone()).await.unwrap())
+        } else {
+            None
+        };
+
+        let admin_handler = match std::env::var(ADMIN_TOKEN_ENV) {
+            Ok(token) if config.features.admin => {
+                let admin_server = AdminServer::bind(config.admin_address, &token).await.unwrap();
+                log::info!("Admin console, address:{:?}", admin_server.get_local_address().unwrap());
+                Some(admin_server.run(server_handler.rooms.clone(), server_handler.admission.ban_list.clone()).await.unwrap())
+            },
+            Ok(_) => None,
+            Err(_) => {
+                if config.features.admin {
+                    log::warn!("{ADMIN_TOKEN_ENV} not set, admin console disabled");
                 }
-            }
-        }
+                None
+            },
+        };
 
         let (ctrlc_sender, ctrlc_receiver) = tokio::sync::oneshot::channel();
         let mut ctrlc_sender = Some(ctrlc_sender);
@@ -48,6 +71,12 @@ fn main() {
         }).expect("Error setting Ctrl-C handler");
 
         ctrlc_receiver.await.unwrap();
+        if let Some(admin_handler) = admin_handler {
+            admin_handler.shutdown().await.unwrap();
+        }
+        if let Some(metrics_handler) = metrics_handler {
+            metrics_handler.shutdown().await.unwrap();
+        }
         server_handler.shutdown().await.unwrap();
     })
-}
\ No newline at end of file
+}
@@ -0,0 +1,166 @@
+use snippets_multiplayer::{
+    client_requests::{ClientRequest, ClientResponse, MoveDirection}, 
+    game::common::Vector2F,
+    multiplayer_client::request_response,
+    rendering::{
+        camera::Camera, terminal::{EntityListWidget, WorldWidget}, AppData
+    }, TEST_SERVER_ADRESS
+};
+use std::{sync::{Arc, Mutex}, time::{Duration, Instant}};
+
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind}, 
+    layout::{Constraint, Layout}, 
+    widgets::Block, 
+    DefaultTerminal
+};
+
+/// Zoom change per key press
+const SCALE_STEP: f32 = 1.25;
+
+fn log_pushed(pushed: ClientResponse) {
+    log::info!("Server pushed '{}'", serde_json::to_string(&pushed).unwrap_or_default());
+}
+
+/// Keeps `AppData` up to date and sends moves, until connection is closed
+async fn run_client(
+    socket: tokio::net::TcpStream,
+    app_data: Arc<Mutex<AppData>>,
+    mut move_receiver: tokio::sync::mpsc::UnboundedReceiver<MoveDirection>
+) {
+    let (read_half, mut write_half) = socket.into_split();
+    let mut buf_reader = tokio::io::BufReader::new(read_half);
+
+    let Some(ClientResponse::GetId { id }) = request_response(&ClientRequest::GetId, &mut buf_reader, &mut write_half, log_pushed).await else {
+        log::error!("Could not get player id");
+        return;
+    };
+    if let Ok(mut app_data_guard) = app_data.lock() {
+        app_data_guard.player_id = Some(id);
+    }
+
+    loop {
+        match request_response(&ClientRequest::WorldCheck, &mut buf_reader, &mut write_half, log_pushed).await {
+            Some(ClientResponse::WorldCheck { entities, sprites, items, chunks, unloaded_chunks, .. }) => {
+                if let Ok(mut app_data_guard) = app_data.lock() {
+                    app_data_guard.update_sprites(sprites);
+                    app_data_guard.update_world(entities, items);
+                    app_data_guard.update_terrain(chunks, unloaded_chunks);
+                }
+            },
+            Some(_) => {},
+            None => break,
+        }
+
+        while let Ok(dir) = move_receiver.try_recv() {
+            if request_response(&ClientRequest::Move { dir }, &mut buf_reader, &mut write_half, log_pushed).await.is_none() {
+                return;
+            }
+        }
+
+        tokio::time::sleep(Duration::from_millis(32)).await;
+    }
+}
+
+fn draw(terminal: &mut DefaultTerminal, app_data: &AppData, connected: bool) -> std::io::Result<()> {
+    terminal.draw(|frame| {
+        let [world_area, list_area] = Layout::horizontal([Constraint::Min(20), Constraint::Length(32)]).areas(frame.area());
+        let status = if connected { " World, arrows move, +/- zoom, q quits " } else { " Disconnected, q quits " };
+        let world_block = Block::bordered().title(status);
+        frame.render_widget(WorldWidget::new(app_data), world_block.inner(world_area));
+        frame.render_widget(world_block, world_area);
+        frame.render_widget(EntityListWidget::new(app_data), list_area);
+    })?;
+    Ok(())
+}
+
+/// Blocking, runs until user quits
+fn run_ui(
+    mut terminal: DefaultTerminal,
+    app_data: Arc<Mutex<AppData>>,
+    move_sender: tokio::sync::mpsc::UnboundedSender<MoveDirection>
+) -> std::io::Result<()> {
+    let mut last_frame = Instant::now();
+    loop {
+        if let Ok(mut app_data_guard) = app_data.lock() {
+            app_data_guard.camera.update(last_frame.elapsed());
+            last_frame = Instant::now();
+            draw(&mut terminal, &app_data_guard, !move_sender.is_closed())?;
+        }
+
+        if !event::poll(Duration::from_millis(32))? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        let dir = match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Up => Some(MoveDirection::Up),
+            KeyCode::Down => Some(MoveDirection::Down),
+            KeyCode::Left => Some(MoveDirection::Left),
+            KeyCode::Right => Some(MoveDirection::Right),
+            KeyCode::Char('+') | KeyCode::Char('=') | KeyCode::Char('-') => {
+                if let Ok(mut app_data_guard) = app_data.lock() {
+                    let step = if key.code == KeyCode::Char('-') { 1.0 / SCALE_STEP } else { SCALE_STEP };
+                    app_data_guard.camera.zoom(step);
+                }
+                None
+            },
+            _ => None,
+        };
+        if let Some(dir) = dir {
+            let _ = move_sender.send(dir);
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    // Terminal UI owns the screen, stderr output would break it, so logs go to file
+    let log_path = std::env::temp_dir().join("snippets_tui_client.log");
+    let log_file = match std::fs::File::create(&log_path) {
+        Ok(log_file) => log_file,
+        Err(e) => {
+            eprintln!("Could not create log file {log_path:?}, reason {e}");
+            std::process::exit(1);
+        }
+    };
+    env_logger::builder()
+        .filter_level(log::LevelFilter::Info)
+        .format_timestamp_millis()
+        .format_file(false)
+        .format_line_number(true)
+        .target(env_logger::Target::Pipe(Box::new(log_file)))
+        .init();
+
+    let address = std::env::args().nth(1).unwrap_or(TEST_SERVER_ADRESS.to_string());
+    let socket = match tokio::net::TcpStream::connect(&address).await {
+        Ok(socket) => socket,
+        Err(e) => {
+            eprintln!("Could not connect to {address}, reason {e}");
+            std::process::exit(1);
+        }
+    };
+
+    let app_data = Arc::new(Mutex::new(AppData {
+        camera: Camera::new(Vector2F::zero(), 0.03),
+        ..Default::default()
+    }));
+    let (move_sender, move_receiver) = tokio::sync::mpsc::unbounded_channel();
+    let client_handle = tokio::spawn(run_client(socket, app_data.clone(), move_receiver));
+
+    let terminal = ratatui::init();
+    let result = tokio::task::block_in_place(|| run_ui(terminal, app_data, move_sender));
+    ratatui::restore();
+
+    client_handle.abort();
+    if let Err(e) = result {
+        eprintln!("Terminal error, reason {e}");
+    }
+    println!("Logs written to {log_path:?}");
+}
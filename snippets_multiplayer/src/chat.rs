@@ -0,0 +1,196 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant}
+};
+
+use crate::{
+    client_requests::{ChatChannel, ClientResponse},
+    game::world::{EntityId, World},
+    session_registry::SessionRegistry
+};
+
+pub const MAX_CHAT_MESSAGE_LENGTH: usize = 200;
+pub const PROXIMITY_CHAT_RADIUS: f32 = 10.0 * World::TILE_SIZE_SIDE;
+
+const FLOOD_MAX_MESSAGES: usize = 5;
+const FLOOD_WINDOW: Duration = Duration::from_secs(5);
+const PROFANITIES: [&str; 4] = ["damn", "hell", "crap", "idiot"];
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum ChatError {
+    #[error("Message is empty")]
+    EmptyMessage,
+
+    #[error("Message exceeds {MAX_CHAT_MESSAGE_LENGTH} characters")]
+    MessageTooLong,
+
+    #[error("Too many messages, slow down")]
+    Flooding,
+
+    #[error("Player '{0}' is not online")]
+    RecipientNotFound(String),
+}
+
+/// Allows at most `FLOOD_MAX_MESSAGES` in sliding `FLOOD_WINDOW`
+#[derive(Debug, Default)]
+pub struct FloodGuard {
+    sent_at: VecDeque<Instant>,
+}
+
+impl FloodGuard {
+    pub fn try_send(&mut self, now: Instant) -> Result<(), ChatError> {
+        while self.sent_at.front().is_some_and(|sent| now.duration_since(*sent) >= FLOOD_WINDOW) {
+            self.sent_at.pop_front();
+        }
+
+        if self.sent_at.len() >= FLOOD_MAX_MESSAGES {
+            Err(ChatError::Flooding)
+        } else {
+            self.sent_at.push_back(now);
+            Ok(())
+        }
+    }
+}
+
+/// Trim, check length and mask profanities with asterisks
+pub fn sanitize_message(text: &str) -> Result<String, ChatError> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(ChatError::EmptyMessage);
+    }
+    if text.chars().count() > MAX_CHAT_MESSAGE_LENGTH {
+        return Err(ChatError::MessageTooLong);
+    }
+
+    let sanitized = text
+        .split(' ')
+        .map(|word| {
+            let bare_word = word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase();
+            if PROFANITIES.contains(&bare_word.as_str()) {
+                word.chars().map(|c| if c.is_alphanumeric() { '*' } else { c }).collect()
+            } else {
+                word.to_string()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    Ok(sanitized)
+}
+
+/// Deliver message to players matching channel, sender gets count of recipients
+pub fn handle_chat(
+    sender_id: EntityId,
+    channel: ChatChannel,
+    text: &str,
+    world: &World,
+    sessions: &mut SessionRegistry,
+) -> Result<usize, ChatError> {
+    let text = sanitize_message(text)?;
+
+    if let Some(session) = sessions.get_mut(sender_id) {
+        session.chat_flood_guard.try_send(Instant::now())?;
+    }
+
+    let Some(sender) = world.get_entity_by_id(sender_id) else {
+        return Ok(0);
+    };
+
+    let recipients: Vec<EntityId> = match &channel {
+        ChatChannel::Global => {
+            sessions.player_ids()
+                .filter(|id| *id != sender_id)
+                .collect()
+        },
+        ChatChannel::Proximity => {
+            sessions.player_ids()
+                .filter(|id| *id != sender_id)
+                .filter(|id| world.get_entity_by_id(*id)
+                    .is_some_and(|e| (e.position - sender.position).length() <= PROXIMITY_CHAT_RADIUS)
+                )
+                .collect()
+        },
+        ChatChannel::Whisper { to } => {
+            let recipient_id = world.iter_entities()
+                .find(|e| e.is_player() && e.name == *to)
+                .map(|e| e.id)
+                .filter(|id| sessions.player_ids().any(|session_id| session_id == *id))
+                .ok_or_else(|| ChatError::RecipientNotFound(to.clone()))?;
+            vec![recipient_id]
+        },
+    };
+
+    let delivered = recipients.iter()
+        .filter(|id| sessions.push(**id, ClientResponse::ChatMessage {
+            channel: channel.clone(),
+            from_id: sender_id,
+            from_name: sender.name.clone(),
+            text: text.clone()
+        }))
+        .count();
+
+    Ok(delivered)
+}
+
+#[test]
+fn test_chat_sanitize_length_limits() {
+    assert_eq!(sanitize_message("   "), Err(ChatError::EmptyMessage));
+    assert_eq!(sanitize_message(&"a".repeat(MAX_CHAT_MESSAGE_LENGTH + 1)), Err(ChatError::MessageTooLong));
+    assert_eq!(sanitize_message(&"a".repeat(MAX_CHAT_MESSAGE_LENGTH)), Ok("a".repeat(MAX_CHAT_MESSAGE_LENGTH)));
+    assert_eq!(sanitize_message("  hi there "), Ok("hi there".to_string()));
+}
+
+#[test]
+fn test_chat_sanitize_masks_profanities() {
+    assert_eq!(sanitize_message("you Idiot!"), Ok("you *****!".to_string()));
+    assert_eq!(sanitize_message("hello world"), Ok("hello world".to_string()));
+}
+
+#[test]
+fn test_chat_flood_guard() {
+    let mut guard = FloodGuard::default();
+    let start = Instant::now();
+    for _ in 0..FLOOD_MAX_MESSAGES {
+        assert_eq!(guard.try_send(start), Ok(()));
+    }
+    assert_eq!(guard.try_send(start), Err(ChatError::Flooding));
+    assert_eq!(guard.try_send(start + FLOOD_WINDOW), Ok(()));
+}
+
+#[test]
+fn test_chat_channels_recipients() {
+    use crate::game::common::Vector2F;
+
+    let mut world = World::new();
+    let size = Vector2F::new(4.8, 4.8);
+    let sender_id = world.create_entity_player("Alice", Vector2F::new(0.0, 0.0), size).unwrap();
+    let near_id = world.create_entity_player("Bob", Vector2F::new(10.0, 0.0), size).unwrap();
+    let far_id = world.create_entity_player("Carol", Vector2F::new(500.0, 0.0), size).unwrap();
+
+    let mut sessions = SessionRegistry::new();
+    let mut receivers = vec![];
+    for id in [sender_id, near_id, far_id] {
+        let (channels, session_receivers) = crate::session_registry::SessionChannels::new();
+        sessions.register(id, "127.0.0.1:0".parse().unwrap(), channels);
+        receivers.push(session_receivers.pushed);
+    }
+
+    assert_eq!(handle_chat(sender_id, ChatChannel::Global, "hi all", &world, &mut sessions), Ok(2));
+    assert_eq!(handle_chat(sender_id, ChatChannel::Proximity, "hi near", &world, &mut sessions), Ok(1));
+    assert_eq!(handle_chat(sender_id, ChatChannel::Whisper { to: "Carol".to_string() }, "psst", &world, &mut sessions), Ok(1));
+    assert_eq!(
+        handle_chat(sender_id, ChatChannel::Whisper { to: "Dave".to_string() }, "psst", &world, &mut sessions),
+        Err(ChatError::RecipientNotFound("Dave".to_string()))
+    );
+
+    assert!(receivers[0].try_recv().is_err());
+    assert_eq!(receivers[1].len(), 2);
+    assert_eq!(receivers[2].len(), 2);
+    match receivers[2].try_recv().unwrap() {
+        ClientResponse::ChatMessage { from_name, text, .. } => {
+            assert_eq!(from_name, "Alice");
+            assert_eq!(text, "hi all");
+        },
+        _ => panic!("Expected chat message"),
+    }
+}
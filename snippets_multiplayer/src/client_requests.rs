@@ -1,23 +1,43 @@
-use std::sync::{Arc, Mutex};
+use std::collections::HashMap;
 
 use serde::{
     Deserialize, 
     Serialize
 };
 
-use crate::game::{
-    common::Vector2F, 
-    world::{Entity, EntityId, World}
+use crate::{
+    admission::RejectionReason, 
+    game::{
+        chunk::{Chunk, ChunkCoord, Terrain}, 
+        common::Vector2F, 
+        item::{GroundItem, ItemDefinitions, ItemId, ItemStack}, 
+        world::{Entity, EntityId, World, WorldError}
+    }, 
+    interest::InterestArea, 
+    multiplayer_client::SessionContext, 
+    rooms::{RoomId, RoomInfo, RoomRegistry}, 
+    world_handle::WorldHandle
 };
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MoveDirection {
     Up,
+    #[default]
     Down,
     Left,
     Right,
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum ChatChannel {
+    Global,
+    Proximity,
+    Whisper {
+        to: String
+    },
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum ClientRequest {
@@ -27,9 +47,37 @@ pub enum ClientRequest {
     Move {
         dir: MoveDirection
     },
+    Attack {
+        target: EntityId
+    },
+    InventoryCheck,
+    PickUp,
+    Drop {
+        item_id: ItemId,
+        count: u32
+    },
+    Use {
+        item_id: ItemId
+    },
+    Chat {
+        channel: ChatChannel,
+        text: String
+    },
+    SetInterest {
+        area: InterestArea
+    },
+    ListRooms,
+    CreateRoom {
+        name: String
+    },
+    JoinRoom {
+        room_id: RoomId
+    },
+    /// Go back to the main room
+    LeaveRoom,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EntityCheckData {
     pub position: Vector2F,
     pub size: Vector2F,
@@ -37,6 +85,67 @@ pub struct EntityCheckData {
     pub id: EntityId,
     pub name: String,
     pub is_npc: bool,
+    pub health: u32,
+    pub max_health: u32,
+    /// Name is sent once in `sprites` of the response which used the id first
+    #[serde(default)]
+    pub sprite: Option<SpriteId>,
+    /// Direction of movement in progress
+    #[serde(default)]
+    pub facing: Option<MoveDirection>,
+}
+
+pub type SpriteId = u16;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpriteData {
+    pub id: SpriteId,
+    pub name: String,
+}
+
+/// Assigns ids to sprite names, so each name is sent only once
+#[derive(Debug, Default)]
+pub struct SpriteIds {
+    ids: HashMap<String, SpriteId>,
+    /// Assigned since last `take_new`
+    new: Vec<SpriteData>,
+}
+
+impl SpriteIds {
+    /// None when all ids are used, entity is drawn without sprite then
+    pub fn id(&mut self, name: &str) -> Option<SpriteId> {
+        if let Some(id) = self.ids.get(name) {
+            return Some(*id);
+        }
+        let id = SpriteId::try_from(self.ids.len()).ok()?;
+        self.ids.insert(name.to_string(), id);
+        self.new.push(SpriteData { id, name: name.to_string() });
+        Some(id)
+    }
+
+    pub fn take_new(&mut self) -> Vec<SpriteData> {
+        std::mem::take(&mut self.new)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ItemData {
+    pub item_id: ItemId,
+    pub name: String,
+    pub count: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GroundItemData {
+    pub position: Vector2F,
+    pub item: ItemData,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ChunkData {
+    pub coord: ChunkCoord,
+    /// Row by row, `Chunk::SIZE_TILES` squared
+    pub terrain: Vec<Terrain>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -46,7 +155,20 @@ pub enum ClientResponse {
         id: EntityId
     },
     WorldCheck {
-        entities: Vec<EntityCheckData>
+        entities: Vec<EntityCheckData>,
+        /// Sprite names used by entities for the first time in this session
+        #[serde(default)]
+        sprites: Vec<SpriteData>,
+        #[serde(default)]
+        items: Vec<GroundItemData>,
+        /// Entities which left area of interest since previous check
+        #[serde(default)]
+        despawned: Vec<EntityId>,
+        /// Terrain of chunks approached since previous check
+        #[serde(default)]
+        chunks: Vec<ChunkData>,
+        #[serde(default)]
+        unloaded_chunks: Vec<ChunkCoord>,
     },
     Healthcheck {
         msg: String
@@ -60,12 +182,122 @@ pub enum ClientResponse {
     Move {
         started: bool
     },
+    Attack {
+        hit: bool,
+        damage: u32,
+        target_killed: bool,
+    },
+    Inventory {
+        items: Vec<ItemData>
+    },
+    ItemActionFailed {
+        err: String
+    },
+    Chat {
+        delivered: usize
+    },
+    ChatRejected {
+        reason: String
+    },
+    SetInterest {
+        area: InterestArea
+    },
+    Rooms {
+        rooms: Vec<RoomInfo>
+    },
+    RoomCreated {
+        room_id: RoomId
+    },
+    RoomJoined {
+        room_id: RoomId,
+        player_id: EntityId
+    },
+    LobbyError {
+        err: String
+    },
+    /// Pushed by server without request
+    ChatMessage {
+        channel: ChatChannel,
+        from_id: EntityId,
+        from_name: String,
+        text: String
+    },
+    /// Pushed by server, connection is closed right after
+    Kicked {
+        reason: String
+    },
+    /// Pushed by server, connection is closed right after
+    ServerShutdown {
+        reason: String,
+        /// Seconds after which server is expected to be back
+        reconnect_after: Option<u64>
+    },
+    /// Pushed while waiting for free place, 1 is the first in queue, 0 when entering the game
+    Queued {
+        position: usize
+    },
+    /// Pushed instead of entering the game, connection is closed right after
+    ConnectionRejected {
+        reason: RejectionReason
+    },
+}
+
+impl ClientRequest {
+    /// Variant name, used as metrics label
+    pub fn name(&self) -> &'static str {
+        match self {
+            ClientRequest::GetId => "GetId",
+            ClientRequest::WorldCheck => "WorldCheck",
+            ClientRequest::Healthcheck => "Healthcheck",
+            ClientRequest::Move { .. } => "Move",
+            ClientRequest::Attack { .. } => "Attack",
+            ClientRequest::InventoryCheck => "InventoryCheck",
+            ClientRequest::PickUp => "PickUp",
+            ClientRequest::Drop { .. } => "Drop",
+            ClientRequest::Use { .. } => "Use",
+            ClientRequest::Chat { .. } => "Chat",
+            ClientRequest::SetInterest { .. } => "SetInterest",
+            ClientRequest::ListRooms => "ListRooms",
+            ClientRequest::CreateRoom { .. } => "CreateRoom",
+            ClientRequest::JoinRoom { .. } => "JoinRoom",
+            ClientRequest::LeaveRoom => "LeaveRoom",
+        }
+    }
 }
 
+impl MoveDirection {
+    /// Dominant axis of the vector, horizontal on tie
+    pub fn from_vector(vector: Vector2F) -> Self {
+        if vector.y.abs() > vector.x.abs() {
+            if vector.y > 0.0 { MoveDirection::Up } else { MoveDirection::Down }
+        } else if vector.x < 0.0 {
+            MoveDirection::Left
+        } else {
+            MoveDirection::Right
+        }
+    }
+}
+
+impl ClientResponse {
+    /// Pushed messages can arrive between request and its response
+    pub fn is_push(&self) -> bool {
+        matches!(self, 
+            ClientResponse::ChatMessage { .. } | 
+            ClientResponse::Kicked { .. } | 
+            ClientResponse::ServerShutdown { .. } | 
+            ClientResponse::Queued { .. } | 
+            ClientResponse::ConnectionRejected { .. }
+        )
+    }
 
+    /// Session ends after sending this message
+    pub fn closes_connection(&self) -> bool {
+        matches!(self, ClientResponse::Kicked { .. } | ClientResponse::ServerShutdown { .. } | ClientResponse::ConnectionRejected { .. })
+    }
+}
 
 impl EntityCheckData {
-    fn vec_from_iter<'a, I: Iterator<Item = &'a Entity>>(iter: I) -> Vec<Self> {
+    pub(crate) fn vec_from_iter<'a, I: Iterator<Item = &'a Entity>>(iter: I, sprite_ids: &mut SpriteIds) -> Vec<Self> {
         iter.map(|e| {
             EntityCheckData {
                 name: e.name.clone(),
@@ -73,24 +305,124 @@ impl EntityCheckData {
                 color: e.color,
                 position: e.position,
                 is_npc: !e.is_player(),
-                size: e.size
+                size: e.size,
+                health: e.health(),
+                max_health: e.max_health(),
+                sprite: sprite_ids.id(&e.sprite),
+                facing: e.movement_direction().map(MoveDirection::from_vector),
+            }
+        })
+        .collect()
+    }
+}
+
+impl ItemData {
+    fn from_stack(stack: &ItemStack, definitions: &ItemDefinitions) -> Self {
+        ItemData {
+            item_id: stack.item_id,
+            name: definitions.get(stack.item_id).map_or_else(|| "Unknown".to_string(), |def| def.name.clone()),
+            count: stack.count
+        }
+    }
+}
+
+impl From<&Chunk> for ChunkData {
+    fn from(chunk: &Chunk) -> Self {
+        ChunkData {
+            coord: chunk.coord,
+            terrain: chunk.terrain().to_vec()
+        }
+    }
+}
+
+impl GroundItemData {
+    pub(crate) fn vec_from_iter<'a, I: Iterator<Item = &'a GroundItem>>(iter: I, definitions: &ItemDefinitions) -> Vec<Self> {
+        iter.map(|item| {
+            GroundItemData {
+                position: item.position,
+                item: ItemData::from_stack(&item.stack, definitions)
             }
         })
         .collect()
     }
 }
 
-pub fn route_request(player_id: EntityId, request_str: &str, world: Arc<Mutex<World>>) -> String {
-    let response: ClientResponse = match serde_json::from_str::<ClientRequest>(request_str) {
+fn inventory_response(player_id: EntityId, world: &World) -> ClientResponse {
+    match world.get_entity_by_id(player_id) {
+        Some(player) => ClientResponse::Inventory { 
+            items: player.inventory().iter()
+                .map(|stack| ItemData::from_stack(stack, world.get_item_definitions()))
+                .collect()
+        },
+        None => ClientResponse::OtherError { err: format!("Player {player_id} does not exist") },
+    }
+}
+
+async fn join_room_response(session_context: &mut SessionContext, room_id: RoomId) -> ClientResponse {
+    match session_context.join_room(room_id).await {
+        Ok(()) => ClientResponse::RoomJoined { 
+            room_id: session_context.room_id, 
+            player_id: session_context.player_id 
+        },
+        Err(e) => ClientResponse::LobbyError { err: e.to_string() },
+    }
+}
+
+/// Execute item action and respond with updated inventory
+async fn item_action_response<T, F>(player_id: EntityId, world: &WorldHandle, action: F) -> ClientResponse 
+where 
+    F: FnOnce(&mut World) -> Result<T, WorldError> + Send + 'static
+{
+    let result = world.execute(move |world| {
+        match action(world) {
+            Ok(_) => inventory_response(player_id, world),
+            Err(e) => ClientResponse::ItemActionFailed { err: format!("{e:?}") },
+        }
+    }).await;
+
+    result.unwrap_or_else(|e| ClientResponse::OtherError { err: e.to_string() })
+}
+
+/// Writes are executed by the world tick task, reads use snapshot of the last tick
+pub async fn route_request(session_context: &mut SessionContext, request_str: &str) -> String {
+    let player_id = session_context.player_id;
+    let world = session_context.world.clone();
+    let sessions = session_context.sessions.clone();
+
+    let request = serde_json::from_str::<ClientRequest>(request_str);
+    session_context.metrics.requests_total.inc(request.as_ref().map_or("Invalid", ClientRequest::name));
+
+    let response: ClientResponse = match request {
         Ok(req) => match req {
             ClientRequest::GetId => {
                 ClientResponse::GetId { id: player_id }
             },
             ClientRequest::WorldCheck => {
-                match world.lock() {
-                    Ok(world_guard) => {
-                        ClientResponse::WorldCheck { 
-                            entities: EntityCheckData::vec_from_iter(world_guard.iter_entities())
+                let snapshot = world.snapshot();
+                match sessions.lock() {
+                    Ok(mut sessions_guard) => {
+                        match sessions_guard.get_mut(player_id) {
+                            Some(session) => {
+                                let (visible, despawned) = session.interest.update(player_id, &snapshot);
+                                let (chunks, unloaded_chunks) = session.interest.update_chunks(player_id, &snapshot);
+                                let visible_rect = match snapshot.get_entity_by_id(player_id) {
+                                    Some(player) => session.interest.area.bounding_rect(&(player.position + player.size * 0.5)),
+                                    None => session.interest.area.bounding_rect(&Vector2F::zero()),
+                                };
+                                let items = snapshot.query_ground_items_in_area(&visible_rect);
+
+                                let entities = EntityCheckData::vec_from_iter(visible.into_iter(), &mut session.interest.sprite_ids);
+
+                                ClientResponse::WorldCheck { 
+                                    entities,
+                                    sprites: session.interest.sprite_ids.take_new(),
+                                    items: GroundItemData::vec_from_iter(items, snapshot.get_item_definitions()),
+                                    despawned,
+                                    chunks: chunks.into_iter().map(ChunkData::from).collect(),
+                                    unloaded_chunks
+                                }
+                            },
+                            None => ClientResponse::OtherError { err: format!("No session for player {player_id}") },
                         }
                     },
                     Err(e) => {
@@ -99,52 +431,140 @@ pub fn route_request(player_id: EntityId, request_str: &str, world: Arc<Mutex<Wo
                 }
             },
             ClientRequest::Healthcheck => {
-                match world.lock() {
-                    Ok(world_guard) => {
-                        let players_count = world_guard.iter_entities().filter(|e| e.is_player()).count();
-                        ClientResponse::Healthcheck { msg: format!("Hello from server! Players active {players_count}.") }
+                let players_count = world.snapshot().iter_entities().filter(|e| e.is_player()).count();
+                ClientResponse::Healthcheck { msg: format!("Hello from server! Players active {players_count}.") }
+            },
+            ClientRequest::Move{dir} => {
+                let was_moved = world.execute(move |world| {
+                    let Some(player) = world.get_entity_by_id(player_id) else {
+                        return false;
+                    };
+                    if player.is_moving() {
+                        // can move only after not moving
+                        return false;
+                    }
+                    let next_player_pos = player.position + match dir {
+                        MoveDirection::Up => Vector2F::new(0.0, 1.0),
+                        MoveDirection::Down => Vector2F::new(0.0, -1.0),
+                        MoveDirection::Left => Vector2F::new(-1.0, 0.0),
+                        MoveDirection::Right => Vector2F::new(1.0, 0.0),
+                    } * World::TILE_SIZE_SIDE;
+
+                    world.try_start_move_entity_to(player_id, next_player_pos).is_ok()
+                }).await;
+
+                ClientResponse::Move {
+                    started: was_moved.unwrap_or(false)
+                }
+            },
+            ClientRequest::Attack { target } => {
+                let result = world.execute(move |world| world.try_attack_entity(player_id, target)).await;
+                match result {
+                    Ok(Ok(outcome)) => ClientResponse::Attack { 
+                        hit: true, 
+                        damage: outcome.damage, 
+                        target_killed: outcome.target_killed 
+                    },
+                    Ok(Err(e)) => {
+                        log::debug!("Player {player_id} could not attack {target}, reason {e:?}");
+                        ClientResponse::Attack { 
+                            hit: false, 
+                            damage: 0, 
+                            target_killed: false 
+                        }
                     },
                     Err(e) => {
                         ClientResponse::OtherError { err: e.to_string() }
                     }
                 }
             },
-            ClientRequest::Move{dir} => {
-                let was_moved = match world.lock() {
-                    Ok(mut world_guard) => {
-                        let (player_pos, player_moving) = {
-                            let player = world_guard.get_entity_by_id(player_id).unwrap();
-                            (player.position, player.is_moving())
-                        };
-                        if player_moving {
-                            // can move only after not moving
-                            false
-                        } else {
-                            let next_player_pos = player_pos + match dir {
-                                MoveDirection::Up => Vector2F::new(0.0, 1.0),
-                                MoveDirection::Down => Vector2F::new(0.0, -1.0),
-                                MoveDirection::Left => Vector2F::new(-1.0, 0.0),
-                                MoveDirection::Right => Vector2F::new(1.0, 0.0),
-                            } * 5.0;
-                            
-                            if world_guard.is_tile_occupied(&next_player_pos) {
-                                false
-                            } else {
-                                world_guard.try_start_move_entity_to(player_id, next_player_pos).is_ok()
-                            }
+            ClientRequest::InventoryCheck => {
+                // Read on the tick task, so items just picked up or used are already reflected
+                match world.execute(move |w| inventory_response(player_id, w)).await {
+                    Ok(response) => response,
+                    Err(e) => ClientResponse::OtherError { err: e.to_string() },
+                }
+            },
+            ClientRequest::PickUp => {
+                item_action_response(player_id, &world, move |w| w.try_pick_up_items(player_id)).await
+            },
+            ClientRequest::Drop { item_id, count } => {
+                item_action_response(player_id, &world, move |w| w.try_drop_item(player_id, item_id, count)).await
+            },
+            ClientRequest::Use { item_id } => {
+                item_action_response(player_id, &world, move |w| w.try_use_item(player_id, item_id)).await
+            },
+            ClientRequest::Chat { channel, text } => {
+                let snapshot = world.snapshot();
+                match sessions.lock() {
+                    Ok(mut sessions_guard) => {
+                        match crate::chat::handle_chat(player_id, channel, &text, &snapshot, &mut sessions_guard) {
+                            Ok(delivered) => ClientResponse::Chat { delivered },
+                            Err(e) => ClientResponse::ChatRejected { reason: e.to_string() },
                         }
+                    },
+                    Err(e) => {
+                        ClientResponse::OtherError { err: e.to_string() }
                     }
-                    Err(_) => {
-                        false
+                }
+            },
+            ClientRequest::SetInterest { area } => {
+                match sessions.lock() {
+                    Ok(mut sessions_guard) => match sessions_guard.get_mut(player_id) {
+                        Some(session) => {
+                            session.interest.area = area.clamped();
+                            ClientResponse::SetInterest { area: session.interest.area }
+                        },
+                        None => ClientResponse::OtherError { err: format!("No session for player {player_id}") },
+                    },
+                    Err(e) => {
+                        ClientResponse::OtherError { err: e.to_string() }
                     }
-                };
-                ClientResponse::Move {
-                    started: was_moved
                 }
-            }
+            },
+            ClientRequest::ListRooms => {
+                match session_context.rooms.lock() {
+                    Ok(rooms_guard) => ClientResponse::Rooms { rooms: rooms_guard.list() },
+                    Err(e) => {
+                        ClientResponse::OtherError { err: e.to_string() }
+                    }
+                }
+            },
+            ClientRequest::CreateRoom { name } => {
+                match session_context.rooms.lock() {
+                    Ok(mut rooms_guard) => match rooms_guard.create_room(&name) {
+                        Ok(room_id) => ClientResponse::RoomCreated { room_id },
+                        Err(e) => ClientResponse::LobbyError { err: e.to_string() },
+                    },
+                    Err(e) => {
+                        ClientResponse::OtherError { err: e.to_string() }
+                    }
+                }
+            },
+            ClientRequest::JoinRoom { room_id } => {
+                join_room_response(session_context, room_id).await
+            },
+            ClientRequest::LeaveRoom => {
+                join_room_response(session_context, RoomRegistry::MAIN_ROOM_ID).await
+            },
         },
         Err(e) => ClientResponse::BadRequest { err: format!("request={request_str}, reason={e}") },
     };
 
     serde_json::to_string(&response).expect("Could not serialize response")
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_sprite_ids_are_assigned_once() {
+    let mut sprite_ids = SpriteIds::default();
+    assert_eq!(sprite_ids.id("player"), Some(0));
+    assert_eq!(sprite_ids.id("npc"), Some(1));
+    assert_eq!(sprite_ids.id("player"), Some(0));
+
+    let new: Vec<_> = sprite_ids.take_new().into_iter().map(|sprite| (sprite.id, sprite.name)).collect();
+    assert_eq!(new, [(0, "player".to_string()), (1, "npc".to_string())]);
+
+    // Names already sent are not sent again
+    assert_eq!(sprite_ids.id("npc"), Some(1));
+    assert!(sprite_ids.take_new().is_empty());
+}
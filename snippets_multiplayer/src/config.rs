@@ -0,0 +1,323 @@
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::Duration
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    admission::{BanList, BanListError},
+    game::{
+        map::{MapDefinition, MapError},
+        world::WorldSettings
+    },
+    rooms::Room
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("Could not read config file '{path}', reason='{source}'")]
+    IoError {
+        path: PathBuf,
+        source: std::io::Error
+    },
+
+    /// Path is not known when parsing from string
+    #[error("Could not parse config{}, reason='{source}'", .path.as_ref().map(|path| format!(" '{}'", path.display())).unwrap_or_default())]
+    ParseError {
+        path: Option<PathBuf>,
+        source: toml::de::Error
+    },
+
+    #[error("Invalid value of '{field}', reason='{reason}'")]
+    InvalidValue {
+        field: &'static str,
+        reason: String
+    },
+
+    #[error("Could not load map '{path}', reason='{source}'")]
+    MapError {
+        path: PathBuf,
+        source: MapError
+    },
+
+    #[error("Could not load ban list '{path}', reason='{source}'")]
+    BanListError {
+        path: PathBuf,
+        source: BanListError
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeatureToggles {
+    /// Admin console also requires token to be provided
+    pub admin: bool,
+    pub metrics: bool,
+}
+
+/// Server settings, read from TOML file and overridden by CLI flags
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub address: SocketAddr,
+    pub admin_address: SocketAddr,
+    pub metrics_address: SocketAddr,
+    /// Ticks per second of every room
+    pub tick_rate: Option<u32>,
+    /// Unlimited if not set
+    pub max_players: Option<usize>,
+    /// Connections waiting for free place when server is full
+    pub queue_size: usize,
+    /// TOML file with banned addresses, can be reloaded from admin console
+    pub ban_list: Option<PathBuf>,
+    /// Embedded map is used if not set
+    pub map_file: Option<PathBuf>,
+    pub log_level: log::LevelFilter,
+    /// State is saved on shutdown and restored on start, not persisted if not set
+    pub persistence_path: Option<PathBuf>,
+    pub world: WorldSettings,
+    pub features: FeatureToggles,
+}
+
+/// Flags take precedence over config file
+#[derive(Debug, Default, clap::Parser)]
+#[command(about = "Multiplayer snippets server")]
+pub struct ServerArgs {
+    /// Path to TOML config file
+    #[arg(short, long)]
+    pub config: Option<PathBuf>,
+
+    #[arg(long)]
+    pub address: Option<SocketAddr>,
+
+    #[arg(long)]
+    pub tick_rate: Option<u32>,
+
+    /// Path to JSON map file
+    #[arg(long)]
+    pub map: Option<PathBuf>,
+
+    #[arg(long)]
+    pub max_players: Option<usize>,
+
+    #[arg(long)]
+    pub queue_size: Option<usize>,
+
+    /// Path to TOML ban list
+    #[arg(long)]
+    pub ban_list: Option<PathBuf>,
+
+    #[arg(long)]
+    pub log_level: Option<log::LevelFilter>,
+
+    #[arg(long)]
+    pub persistence_path: Option<PathBuf>,
+
+    #[arg(long)]
+    pub no_admin: bool,
+
+    #[arg(long)]
+    pub no_metrics: bool,
+}
+
+impl Default for FeatureToggles {
+    fn default() -> Self {
+        Self {
+            admin: true,
+            metrics: true,
+        }
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            address: crate::TEST_SERVER_ADRESS.parse().expect("Default address should be valid"),
+            admin_address: crate::ADMIN_SERVER_ADRESS.parse().expect("Default address should be valid"),
+            metrics_address: crate::METRICS_SERVER_ADRESS.parse().expect("Default address should be valid"),
+            tick_rate: None,
+            max_players: None,
+            queue_size: 0,
+            ban_list: None,
+            map_file: None,
+            log_level: log::LevelFilter::Warn,
+            persistence_path: None,
+            world: WorldSettings::default(),
+            features: FeatureToggles::default(),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Missing fields take default values
+    pub fn from_toml_str(toml: &str) -> Result<Self, ConfigError> {
+        let config: Self = toml::from_str(toml).map_err(|source| ConfigError::ParseError { path: None, source })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let toml = std::fs::read_to_string(path).map_err(|source| ConfigError::IoError { path: path.to_path_buf(), source })?;
+        let config: Self = toml::from_str(&toml).map_err(|source| ConfigError::ParseError { path: Some(path.to_path_buf()), source })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if let Some(tick_rate) = self.tick_rate {
+            Room::tick_interval_from_rate(tick_rate)
+                .map_err(|e| ConfigError::InvalidValue { field: "tick_rate", reason: e.to_string() })?;
+        }
+        if self.max_players == Some(0) {
+            return Err(ConfigError::InvalidValue { field: "max_players", reason: "Must be at least 1".to_string() });
+        }
+        let speeds = [
+            ("world.player_movement_speed", self.world.player_movement_speed),
+            ("world.npc_movement_speed", self.world.npc_movement_speed),
+        ];
+        // Movement step larger than tile would skip collision checks
+        for (field, speed) in speeds {
+            if !(speed > 0.0 && speed <= crate::game::world::World::TILE_SIZE_SIDE) {
+                return Err(ConfigError::InvalidValue { field, reason: format!("Must be in (0, {}]", crate::game::world::World::TILE_SIZE_SIDE) });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn tick_interval(&self) -> Duration {
+        self.tick_rate
+            .and_then(|tick_rate| Room::tick_interval_from_rate(tick_rate).ok())
+            .unwrap_or(Room::DEFAULT_TICK_INTERVAL)
+    }
+
+    pub fn load_map(&self) -> Result<MapDefinition, ConfigError> {
+        match &self.map_file {
+            Some(path) => MapDefinition::load(path).map_err(|source| ConfigError::MapError { path: path.clone(), source }),
+            None => Ok(MapDefinition::default()),
+        }
+    }
+
+    /// Empty list if not set
+    pub fn load_ban_list(&self) -> Result<BanList, ConfigError> {
+        match &self.ban_list {
+            Some(path) => BanList::load(path).map_err(|source| ConfigError::BanListError { path: path.clone(), source }),
+            None => Ok(BanList::new()),
+        }
+    }
+}
+
+impl ServerArgs {
+    /// Reads config file if given and applies flags on top of it
+    pub fn into_config(self) -> Result<ServerConfig, ConfigError> {
+        let mut config = match &self.config {
+            Some(path) => ServerConfig::load(path)?,
+            None => ServerConfig::default(),
+        };
+
+        if let Some(address) = self.address {
+            config.address = address;
+        }
+        if let Some(tick_rate) = self.tick_rate {
+            config.tick_rate = Some(tick_rate);
+        }
+        if let Some(map) = self.map {
+            config.map_file = Some(map);
+        }
+        if let Some(max_players) = self.max_players {
+            config.max_players = Some(max_players);
+        }
+        if let Some(queue_size) = self.queue_size {
+            config.queue_size = queue_size;
+        }
+        if let Some(ban_list) = self.ban_list {
+            config.ban_list = Some(ban_list);
+        }
+        if let Some(log_level) = self.log_level {
+            config.log_level = log_level;
+        }
+        if let Some(persistence_path) = self.persistence_path {
+            config.persistence_path = Some(persistence_path);
+        }
+        config.features.admin &= !self.no_admin;
+        config.features.metrics &= !self.no_metrics;
+
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+#[test]
+fn test_config_from_toml() {
+    let config = ServerConfig::from_toml_str(r#"
+        address = "0.0.0.0:5000"
+        tick_rate = 20
+        log_level = "debug"
+
+        [world]
+        npc_movement_speed = 0.5
+
+        [features]
+        admin = false
+    "#).unwrap();
+
+    assert_eq!(config.address, "0.0.0.0:5000".parse().unwrap());
+    assert_eq!(config.tick_interval(), Duration::from_millis(50));
+    assert_eq!(config.log_level, log::LevelFilter::Debug);
+    assert_eq!(config.world.npc_movement_speed, 0.5);
+    assert_eq!(config.world.player_movement_speed, WorldSettings::default().player_movement_speed);
+    assert!(!config.features.admin);
+    assert!(config.features.metrics);
+    assert_eq!(config.max_players, None);
+}
+
+#[test]
+fn test_config_errors_are_reported() {
+    let error = ServerConfig::from_toml_str("adress = \"0.0.0.0:5000\"").unwrap_err();
+    assert!(error.to_string().contains("unknown field `adress`"), "{error}");
+
+    let error = ServerConfig::from_toml_str("tick_rate = 0").unwrap_err();
+    assert!(matches!(error, ConfigError::InvalidValue { field: "tick_rate", .. }), "{error}");
+
+    let error = ServerConfig::from_toml_str("max_players = 0").unwrap_err();
+    assert!(matches!(error, ConfigError::InvalidValue { field: "max_players", .. }), "{error}");
+
+    let error = ServerConfig::load("does/not/exist.toml").unwrap_err();
+    assert!(error.to_string().contains("does/not/exist.toml"), "{error}");
+
+    let path = std::env::temp_dir().join(format!("snippets_broken_config_{}.toml", std::process::id()));
+    std::fs::write(&path, "tick_rate = \"fast\"\n").unwrap();
+    let error = ServerConfig::load(&path).unwrap_err();
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(&error, ConfigError::ParseError { path: Some(error_path), .. } if *error_path == path), "{error}");
+    assert!(error.to_string().contains(path.to_str().unwrap()), "{error}");
+}
+
+#[test]
+fn test_config_cli_overrides_file() {
+    use clap::Parser;
+
+    let path = std::env::temp_dir().join(format!("snippets_config_{}.toml", std::process::id()));
+    std::fs::write(&path, "tick_rate = 20\nmax_players = 8\n").unwrap();
+    let args = ServerArgs::try_parse_from([
+        "server", "--config", path.to_str().unwrap(), "--tick-rate", "50", "--no-metrics", "--log-level", "info"
+    ]).unwrap();
+    let config = args.into_config();
+    std::fs::remove_file(&path).unwrap();
+    let config = config.unwrap();
+
+    assert_eq!(config.tick_rate, Some(50));
+    assert_eq!(config.max_players, Some(8));
+    assert_eq!(config.log_level, log::LevelFilter::Info);
+    assert!(!config.features.metrics);
+    assert!(config.features.admin);
+}
+
+#[test]
+fn test_config_example_file_is_valid() {
+    let config = ServerConfig::from_toml_str(include_str!("../assets/server.toml")).unwrap();
+    assert_eq!(config.max_players, Some(64));
+    assert!(config.load_map().is_ok());
+}
@@ -0,0 +1,207 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    common::{Rect2F, Vector2F, Vector2I},
+    world::World
+};
+
+pub type ChunkCoord = Vector2I;
+
+/// Cosmetic ground type, does not affect movement
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Terrain {
+    Grass,
+    Sand,
+    Stone,
+    Water,
+}
+
+/// Square of `Chunk::SIZE_TILES` x `Chunk::SIZE_TILES` tiles, stored row by row
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    pub coord: ChunkCoord,
+    terrain: Vec<Terrain>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ChunkEvent {
+    Loaded(ChunkCoord),
+    Unloaded(ChunkCoord),
+}
+
+/// Chunks around players, only those are simulated
+#[derive(Debug, Default, Clone)]
+pub struct ChunkMap {
+    /// Terrain never changes, so world snapshots share chunks
+    chunks: HashMap<ChunkCoord, Arc<Chunk>>,
+    events: Vec<ChunkEvent>,
+}
+
+impl Chunk {
+    pub const SIZE_TILES: i32 = World::TILE_SIZE_SIDE as i32;
+    pub const SIZE: f32 = Self::SIZE_TILES as f32 * World::TILE_SIZE_SIDE;
+
+    /// Terrain is derived from coordinates only, so every server generates the same map
+    pub fn generate(coord: ChunkCoord) -> Self {
+        let terrain = (0..Self::SIZE_TILES)
+            .flat_map(|y| (0..Self::SIZE_TILES).map(move |x| (x, y)))
+            .map(|(x, y)| Self::terrain_for_tile(coord.x * Self::SIZE_TILES + x, coord.y * Self::SIZE_TILES + y))
+            .collect();
+
+        Self {
+            coord,
+            terrain,
+        }
+    }
+
+    fn terrain_for_tile(x: i32, y: i32) -> Terrain {
+        // Cheap integer hash, good enough for cosmetics
+        let mut hash = (x as u32).wrapping_mul(0x9E37_79B1) ^ (y as u32).wrapping_mul(0x85EB_CA77);
+        hash ^= hash >> 15;
+        hash = hash.wrapping_mul(0x2C1B_3C6D);
+        hash ^= hash >> 12;
+
+        match hash % 16 {
+            0 => Terrain::Water,
+            1..=2 => Terrain::Stone,
+            3..=4 => Terrain::Sand,
+            _ => Terrain::Grass,
+        }
+    }
+
+    pub fn coord_of(position: &Vector2F) -> ChunkCoord {
+        ChunkCoord::new(
+            (position.x / Self::SIZE).floor() as i32,
+            (position.y / Self::SIZE).floor() as i32
+        )
+    }
+
+    pub fn area(coord: ChunkCoord) -> Rect2F {
+        Rect2F::new(coord.x as f32 * Self::SIZE, coord.y as f32 * Self::SIZE, Self::SIZE, Self::SIZE)
+    }
+
+    /// Coords of all chunks touched by area
+    pub fn coords_in_area(area: &Rect2F) -> impl Iterator<Item = ChunkCoord> {
+        let first = Self::coord_of(&area.pos);
+        let last = Self::coord_of(&(area.pos + area.size));
+        (first.y..=last.y).flat_map(move |y| (first.x..=last.x).map(move |x| ChunkCoord::new(x, y)))
+    }
+
+    /// Tile coordinates local to chunk
+    pub fn terrain_at(&self, x: i32, y: i32) -> Option<Terrain> {
+        if !(0..Self::SIZE_TILES).contains(&x) || !(0..Self::SIZE_TILES).contains(&y) {
+            return None;
+        }
+        self.terrain.get((y * Self::SIZE_TILES + x) as usize).copied()
+    }
+
+    pub fn terrain(&self) -> &[Terrain] {
+        &self.terrain
+    }
+}
+
+impl ChunkMap {
+    /// Chunks within this distance (Chebyshev) of any player chunk stay loaded
+    pub const ACTIVE_RADIUS: i32 = 3;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn chunks_around(center: ChunkCoord) -> impl Iterator<Item = ChunkCoord> {
+        (-Self::ACTIVE_RADIUS..=Self::ACTIVE_RADIUS)
+            .flat_map(move |dy| (-Self::ACTIVE_RADIUS..=Self::ACTIVE_RADIUS).map(move |dx| ChunkCoord::new(center.x + dx, center.y + dy)))
+    }
+
+    /// Load chunks around centers and unload all others, emitting events for changes
+    pub fn update_active(&mut self, centers: impl IntoIterator<Item = ChunkCoord>) {
+        let active: HashSet<ChunkCoord> = centers.into_iter()
+            .flat_map(Self::chunks_around)
+            .collect();
+
+        let mut unloaded: Vec<ChunkCoord> = self.chunks.keys()
+            .filter(|coord| !active.contains(coord))
+            .copied()
+            .collect();
+        unloaded.sort();
+        for coord in unloaded {
+            self.chunks.remove(&coord);
+            self.events.push(ChunkEvent::Unloaded(coord));
+        }
+
+        let mut loaded: Vec<ChunkCoord> = active.into_iter()
+            .filter(|coord| !self.chunks.contains_key(coord))
+            .collect();
+        loaded.sort();
+        for coord in loaded {
+            self.chunks.insert(coord, Arc::new(Chunk::generate(coord)));
+            self.events.push(ChunkEvent::Loaded(coord));
+        }
+    }
+
+    pub fn is_loaded(&self, coord: ChunkCoord) -> bool {
+        self.chunks.contains_key(&coord)
+    }
+
+    pub fn get(&self, coord: ChunkCoord) -> Option<&Chunk> {
+        self.chunks.get(&coord).map(Arc::as_ref)
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Events since last drain, in order of occurrence
+    pub fn drain_events(&mut self) -> Vec<ChunkEvent> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+#[test]
+fn test_chunk_coord_of_position() {
+    assert_eq!(Chunk::coord_of(&Vector2F::new(0.0, 0.0)), ChunkCoord::new(0, 0));
+    assert_eq!(Chunk::coord_of(&Vector2F::new(Chunk::SIZE - 0.1, Chunk::SIZE)), ChunkCoord::new(0, 1));
+    assert_eq!(Chunk::coord_of(&Vector2F::new(-0.1, -Chunk::SIZE - 0.1)), ChunkCoord::new(-1, -2));
+}
+
+#[test]
+fn test_chunk_generation_is_deterministic() {
+    let chunk = Chunk::generate(ChunkCoord::new(3, -7));
+    assert_eq!(chunk, Chunk::generate(ChunkCoord::new(3, -7)));
+    assert_eq!(chunk.terrain().len(), (Chunk::SIZE_TILES * Chunk::SIZE_TILES) as usize);
+    assert!(chunk.terrain_at(Chunk::SIZE_TILES - 1, 0).is_some());
+    assert!(chunk.terrain_at(Chunk::SIZE_TILES, 0).is_none());
+}
+
+#[test]
+fn test_chunk_map_load_and_unload_events() {
+    let mut chunks = ChunkMap::new();
+    let side = 2 * ChunkMap::ACTIVE_RADIUS + 1;
+
+    chunks.update_active([ChunkCoord::new(0, 0)]);
+    let events = chunks.drain_events();
+    assert_eq!(events.len(), (side * side) as usize);
+    assert!(events.iter().all(|event| matches!(event, ChunkEvent::Loaded(_))));
+    assert!(chunks.is_loaded(ChunkCoord::new(ChunkMap::ACTIVE_RADIUS, -ChunkMap::ACTIVE_RADIUS)));
+    assert!(chunks.drain_events().is_empty());
+
+    // Moving by one chunk swaps a single column
+    chunks.update_active([ChunkCoord::new(1, 0)]);
+    let events = chunks.drain_events();
+    assert_eq!(events.len(), 2 * side as usize);
+    assert!(events.contains(&ChunkEvent::Unloaded(ChunkCoord::new(-ChunkMap::ACTIVE_RADIUS, 0))));
+    assert!(events.contains(&ChunkEvent::Loaded(ChunkCoord::new(ChunkMap::ACTIVE_RADIUS + 1, 0))));
+
+    chunks.update_active([]);
+    assert!(chunks.is_empty());
+    assert_eq!(chunks.drain_events().len(), (side * side) as usize);
+}
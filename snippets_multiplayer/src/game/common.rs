@@ -174,6 +174,39 @@ where
             && point.x < self.pos.x + self.size.x
             && point.y < self.pos.y + self.size.y
     }
+
+    /// Rects are half-open like in `contains`, so rects only touching by edge do not intersect.
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.pos.x < other.pos.x + other.size.x
+            && other.pos.x < self.pos.x + self.size.x
+            && self.pos.y < other.pos.y + other.size.y
+            && other.pos.y < self.pos.y + self.size.y
+    }
+}
+
+impl<T> Rect2X<T> 
+where 
+    T: PartialOrd + std::ops::Add<Output = T> + std::ops::Sub<Output = T> + Copy
+{
+    /// Returns overlapping part of both rects or None if they do not intersect.
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        fn max<T: PartialOrd>(a: T, b: T) -> T { if a > b { a } else { b } }
+        fn min<T: PartialOrd>(a: T, b: T) -> T { if a < b { a } else { b } }
+
+        if !self.intersects(other) {
+            return None;
+        }
+
+        let left = max(self.pos.x, other.pos.x);
+        let bottom = max(self.pos.y, other.pos.y);
+        let right = min(self.pos.x + self.size.x, other.pos.x + other.size.x);
+        let top = min(self.pos.y + self.size.y, other.pos.y + other.size.y);
+
+        Some(Self {
+            pos: Vector2X { x: left, y: bottom },
+            size: Vector2X { x: right - left, y: top - bottom },
+        })
+    }
 }
 
 #[test]
@@ -260,4 +293,26 @@ fn test_rect_containing() {
     assert!(!rect.contains(&p3_not_inside));
     assert!(!rect.contains(&p4_not_inside));
     assert!(rect.contains(&p5_inside));
+}
+
+#[test]
+fn test_rect_intersects() {
+    let rect = Rect2F::new(0.0, 0.0, 5.0, 5.0);
+
+    assert!(rect.intersects(&Rect2F::new(4.0, 4.0, 2.0, 2.0)));
+    assert!(rect.intersects(&Rect2F::new(-1.0, -1.0, 10.0, 10.0)));
+    assert!(rect.intersects(&Rect2F::new(1.0, 1.0, 1.0, 1.0)));
+    assert!(!rect.intersects(&Rect2F::new(5.0, 0.0, 5.0, 5.0)));
+    assert!(!rect.intersects(&Rect2F::new(0.0, -5.0, 5.0, 5.0)));
+    assert!(!rect.intersects(&Rect2F::new(10.0, 10.0, 1.0, 1.0)));
+}
+
+#[test]
+fn test_rect_intersection() {
+    let r1 = Rect2I::new(0, 0, 10, 5);
+    let r2 = Rect2I::new(5, 2, 10, 10);
+
+    assert_eq!(r1.intersection(&r2), Some(Rect2I::new(5, 2, 5, 3)));
+    assert_eq!(r2.intersection(&r1), Some(Rect2I::new(5, 2, 5, 3)));
+    assert_eq!(r1.intersection(&Rect2I::new(10, 0, 1, 1)), None);
 }
\ No newline at end of file
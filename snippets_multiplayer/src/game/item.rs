@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::common::Vector2F;
+
+pub type ItemId = u32;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ItemDefinitionsError {
+    #[error("Could not parse item definitions, reason='{0}'")]
+    ParseError(#[from] serde_json::Error),
+
+    #[error("Item id={0} defined more than once")]
+    DuplicatedItem(ItemId),
+
+    #[error("Loot refers to unknown item id={0}")]
+    UnknownLootItem(ItemId),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ItemEffect {
+    Heal {
+        amount: u32
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemDefinition {
+    pub id: ItemId,
+    pub name: String,
+    pub max_stack: u32,
+    #[serde(default)]
+    pub effect: Option<ItemEffect>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LootEntry {
+    pub item_id: ItemId,
+    pub chance: f32,
+    pub count: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct ItemDefinitionsFile {
+    items: Vec<ItemDefinition>,
+    #[serde(default)]
+    npc_loot: Vec<LootEntry>,
+}
+
+/// Items known to the world, embedded in the binary
+#[derive(Debug)]
+pub struct ItemDefinitions {
+    items: HashMap<ItemId, ItemDefinition>,
+    npc_loot: Vec<LootEntry>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ItemStack {
+    pub item_id: ItemId,
+    pub count: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroundItem {
+    pub position: Vector2F,
+    pub stack: ItemStack,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Inventory {
+    slots: Vec<ItemStack>,
+    capacity: usize,
+}
+
+impl ItemDefinitions {
+    const DEFAULT_DEFINITIONS: &str = include_str!("../../assets/items.json");
+
+    pub fn from_json_str(json: &str) -> Result<Self, ItemDefinitionsError> {
+        let file: ItemDefinitionsFile = serde_json::from_str(json)?;
+
+        let mut items = HashMap::new();
+        for item in file.items {
+            let item_id = item.id;
+            if items.insert(item_id, item).is_some() {
+                return Err(ItemDefinitionsError::DuplicatedItem(item_id));
+            }
+        }
+
+        if let Some(entry) = file.npc_loot.iter().find(|entry| !items.contains_key(&entry.item_id)) {
+            return Err(ItemDefinitionsError::UnknownLootItem(entry.item_id));
+        }
+
+        Ok(Self {
+            items,
+            npc_loot: file.npc_loot
+        })
+    }
+
+    pub fn get(&self, item_id: ItemId) -> Option<&ItemDefinition> {
+        self.items.get(&item_id)
+    }
+
+    /// Random drop from NPC loot table
+    pub fn roll_npc_loot(&self) -> Vec<ItemStack> {
+        self.npc_loot.iter()
+            .filter(|entry| rand::random::<f32>() < entry.chance)
+            .map(|entry| ItemStack { item_id: entry.item_id, count: entry.count })
+            .collect()
+    }
+}
+
+impl Default for ItemDefinitions {
+    fn default() -> Self {
+        Self::from_json_str(Self::DEFAULT_DEFINITIONS).expect("Embedded item definitions should be valid")
+    }
+}
+
+impl Inventory {
+    pub const DEFAULT_CAPACITY: usize = 16;
+
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            slots: vec![],
+            capacity
+        }
+    }
+
+    pub fn count(&self, item_id: ItemId) -> u32 {
+        self.slots.iter()
+            .filter(|slot| slot.item_id == item_id)
+            .map(|slot| slot.count)
+            .sum()
+    }
+
+    /// Stack items up to `max_stack` and use free slots for the rest.
+    /// Returns count of items that did not fit.
+    pub fn add(&mut self, stack: ItemStack, definitions: &ItemDefinitions) -> u32 {
+        let max_stack = definitions.get(stack.item_id).map_or(1, |def| def.max_stack.max(1));
+        let mut remaining = stack.count;
+
+        for slot in self.slots.iter_mut().filter(|slot| slot.item_id == stack.item_id) {
+            let added = remaining.min(max_stack.saturating_sub(slot.count));
+            slot.count += added;
+            remaining -= added;
+        }
+
+        while remaining > 0 && self.slots.len() < self.capacity {
+            let added = remaining.min(max_stack);
+            self.slots.push(ItemStack { item_id: stack.item_id, count: added });
+            remaining -= added;
+        }
+
+        remaining
+    }
+
+    /// Remove exactly `count` items or nothing if there is not enough of them
+    pub fn remove(&mut self, item_id: ItemId, count: u32) -> bool {
+        if count == 0 || self.count(item_id) < count {
+            return false;
+        }
+
+        let mut remaining = count;
+        for slot in self.slots.iter_mut().rev().filter(|slot| slot.item_id == item_id) {
+            let removed = remaining.min(slot.count);
+            slot.count -= removed;
+            remaining -= removed;
+        }
+        self.slots.retain(|slot| slot.count > 0);
+        true
+    }
+
+    pub fn take_all(&mut self) -> Vec<ItemStack> {
+        std::mem::take(&mut self.slots)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ItemStack> {
+        self.slots.iter()
+    }
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY)
+    }
+}
+
+#[test]
+fn test_item_definitions_embedded_are_valid() {
+    let definitions = ItemDefinitions::default();
+    assert!(definitions.get(0).is_some());
+    assert!(definitions.get(1).unwrap().effect.is_some());
+}
+
+#[test]
+fn test_item_definitions_reject_invalid_data() {
+    let duplicated = r#"{"items": [{"id": 1, "name": "A", "max_stack": 1}, {"id": 1, "name": "B", "max_stack": 1}]}"#;
+    assert!(matches!(ItemDefinitions::from_json_str(duplicated), Err(ItemDefinitionsError::DuplicatedItem(1))));
+
+    let unknown_loot = r#"{"items": [], "npc_loot": [{"item_id": 7, "chance": 1.0, "count": 1}]}"#;
+    assert!(matches!(ItemDefinitions::from_json_str(unknown_loot), Err(ItemDefinitionsError::UnknownLootItem(7))));
+
+    assert!(matches!(ItemDefinitions::from_json_str("{"), Err(ItemDefinitionsError::ParseError(_))));
+}
+
+#[test]
+fn test_inventory_stacking() {
+    let definitions = ItemDefinitions::from_json_str(
+        r#"{"items": [{"id": 0, "name": "Coin", "max_stack": 10}]}"#
+    ).unwrap();
+    let mut inventory = Inventory::new(2);
+
+    assert_eq!(inventory.add(ItemStack { item_id: 0, count: 7 }, &definitions), 0);
+    assert_eq!(inventory.add(ItemStack { item_id: 0, count: 7 }, &definitions), 0);
+    assert_eq!(inventory.iter().count(), 2);
+    assert_eq!(inventory.count(0), 14);
+
+    // Only 6 more fit into 2 slots
+    assert_eq!(inventory.add(ItemStack { item_id: 0, count: 10 }, &definitions), 4);
+    assert_eq!(inventory.count(0), 20);
+}
+
+#[test]
+fn test_inventory_remove() {
+    let definitions = ItemDefinitions::default();
+    let mut inventory = Inventory::default();
+    inventory.add(ItemStack { item_id: 2, count: 3 }, &definitions);
+
+    assert!(!inventory.remove(2, 4));
+    assert!(inventory.remove(2, 2));
+    assert_eq!(inventory.count(2), 1);
+    assert!(inventory.remove(2, 1));
+    assert_eq!(inventory.iter().count(), 0);
+}
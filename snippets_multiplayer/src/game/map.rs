@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    common::Vector2F,
+    world::{EntityId, World, WorldError}
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum MapError {
+    #[error("IoError, reason='{0}'")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Could not parse map, reason='{0}'")]
+    ParseError(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NpcSpawn {
+    pub name: String,
+    pub position: Vector2F,
+    /// Default NPC sprite if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sprite: Option<String>,
+}
+
+/// Initial layout of the world, loaded from data file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MapDefinition {
+    pub npcs: Vec<NpcSpawn>,
+}
+
+impl MapDefinition {
+    const DEFAULT_MAP: &str = include_str!("../../assets/map.json");
+    const NPC_SIZE: Vector2F = Vector2F { x: 4.8, y: 4.8 };
+
+    pub fn from_json_str(json: &str) -> Result<Self, MapError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn load<P: AsRef<std::path::Path>>(path: P) -> Result<Self, MapError> {
+        let json = std::fs::read_to_string(path)?;
+        Self::from_json_str(&json)
+    }
+
+    pub fn spawn(&self, world: &mut World) -> Result<Vec<EntityId>, WorldError> {
+        self.npcs.iter()
+            .map(|npc| {
+                let entity_id = world.create_entity_npc(&npc.name, npc.position, Self::NPC_SIZE)?;
+                if let (Some(sprite), Some(entity)) = (&npc.sprite, world.get_entity_by_id_mut(entity_id)) {
+                    entity.sprite = sprite.clone();
+                }
+                Ok(entity_id)
+            })
+            .collect()
+    }
+}
+
+impl Default for MapDefinition {
+    fn default() -> Self {
+        Self::from_json_str(Self::DEFAULT_MAP).expect("Embedded map should be valid")
+    }
+}
+
+#[test]
+fn test_map_spawns_npcs() {
+    let map = MapDefinition::from_json_str(r#"{ "npcs": [
+        { "name": "Tuna", "position": { "x": 5.0, "y": 10.0 } },
+        { "name": "Rat", "position": { "x": 20.0, "y": 10.0 }, "sprite": "rat" }
+    ] }"#).unwrap();
+    let mut world = World::new();
+    let spawned = map.spawn(&mut world).unwrap();
+
+    assert_eq!(spawned.len(), 2);
+    let npc = world.get_entity_by_id(spawned[0]).unwrap();
+    assert_eq!(npc.name, "Tuna");
+    assert_eq!(npc.position, Vector2F::new(5.0, 10.0));
+    assert_eq!(npc.sprite, "npc");
+    assert_eq!(world.get_entity_by_id(spawned[1]).unwrap().sprite, "rat");
+    assert!(!MapDefinition::default().npcs.is_empty());
+}
@@ -1,2 +1,7 @@
 pub mod world;
-pub mod common;
\ No newline at end of file
+pub mod common;
+pub mod reservation;
+pub mod item;
+pub mod spatial;
+pub mod chunk;
+pub mod map;
\ No newline at end of file
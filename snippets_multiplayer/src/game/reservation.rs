@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+
+use super::{
+    common::{Rect2F, Vector2F, Vector2I},
+    world::{EntityId, World}
+};
+
+/// Tiles claimed by entities. Entity owns tiles it stands on and,
+/// while moving, tiles of its destination. Claiming is all-or-nothing,
+/// so two entities can never hold the same tile.
+#[derive(Debug, Default, Clone)]
+pub struct TileReservations {
+    tiles: HashMap<Vector2I, EntityId>,
+    /// Tiles of each entity, so releasing does not scan the whole world
+    owned: HashMap<EntityId, Vec<Vector2I>>,
+}
+
+impl TileReservations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Indices of all grid tiles touched by area
+    pub fn tiles_covered(area: &Rect2F) -> impl Iterator<Item = Vector2I> {
+        let first_x = (area.pos.x / World::TILE_SIZE_SIDE).floor() as i32;
+        let first_y = (area.pos.y / World::TILE_SIZE_SIDE).floor() as i32;
+        let last_x = ((area.pos.x + area.size.x) / World::TILE_SIZE_SIDE).ceil() as i32 - 1;
+        let last_y = ((area.pos.y + area.size.y) / World::TILE_SIZE_SIDE).ceil() as i32 - 1;
+
+        (first_x..=last_x).flat_map(move |x| (first_y..=last_y).map(move |y| Vector2I::new(x, y)))
+    }
+
+    pub fn owner(&self, tile: &Vector2I) -> Option<EntityId> {
+        self.tiles.get(tile).copied()
+    }
+
+    pub fn is_reserved_by_other(&self, area: &Rect2F, entity_id: Option<EntityId>) -> bool {
+        Self::tiles_covered(area).any(|tile| {
+            self.owner(&tile).is_some_and(|owner| Some(owner) != entity_id)
+        })
+    }
+
+    /// Claim every tile of area or nothing if any of them belongs to other entity
+    pub fn try_reserve(&mut self, entity_id: EntityId, area: &Rect2F) -> bool {
+        if self.is_reserved_by_other(area, Some(entity_id)) {
+            return false;
+        }
+
+        for tile in Self::tiles_covered(area) {
+            if self.tiles.insert(tile, entity_id).is_none() {
+                self.owned.entry(entity_id).or_default().push(tile);
+            }
+        }
+        true
+    }
+
+    /// Keep only tiles of area, release everything else owned by entity
+    pub fn retain_only(&mut self, entity_id: EntityId, area: &Rect2F) {
+        self.release_all(entity_id);
+        let was_reserved = self.try_reserve(entity_id, area);
+        debug_assert!(was_reserved, "Retained area should be owned by entity");
+    }
+
+    pub fn release_all(&mut self, entity_id: EntityId) {
+        for tile in self.owned.remove(&entity_id).unwrap_or_default() {
+            self.tiles.remove(&tile);
+        }
+    }
+
+    /// Search rings of tiles around position for the closest one, where area of `size` is not reserved
+    pub fn find_free_position_near(&self, position: &Vector2F, size: &Vector2F, search_range: i32) -> Option<Vector2F> {
+        for ring in 0..=search_range {
+            for dx in -ring..=ring {
+                for dy in -ring..=ring {
+                    if dx.abs() != ring && dy.abs() != ring {
+                        continue;
+                    }
+
+                    let candidate = *position + Vector2F::new(dx as f32, dy as f32) * World::TILE_SIZE_SIDE;
+                    let area = Rect2F::new(candidate.x, candidate.y, size.x, size.y);
+                    if !self.is_reserved_by_other(&area, None) {
+                        return Some(candidate);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Vector2I, &EntityId)> {
+        self.tiles.iter()
+    }
+}
+
+#[test]
+fn test_tiles_covered_by_single_tile_area() {
+    let area = Rect2F::new(5.0, 10.0, 4.8, 4.8);
+    let tiles: Vec<_> = TileReservations::tiles_covered(&area).collect();
+    assert_eq!(tiles, vec![Vector2I::new(1, 2)]);
+
+    let area = Rect2F::new(-5.0, -5.0, World::TILE_SIZE_SIDE, World::TILE_SIZE_SIDE);
+    let tiles: Vec<_> = TileReservations::tiles_covered(&area).collect();
+    assert_eq!(tiles, vec![Vector2I::new(-1, -1)]);
+}
+
+#[test]
+fn test_tiles_covered_by_multi_tile_area() {
+    let area = Rect2F::new(0.0, 0.0, 9.8, 9.8);
+    let mut tiles: Vec<_> = TileReservations::tiles_covered(&area).collect();
+    tiles.sort();
+    assert_eq!(tiles, vec![
+        Vector2I::new(0, 0),
+        Vector2I::new(0, 1),
+        Vector2I::new(1, 0),
+        Vector2I::new(1, 1)
+    ]);
+}
+
+#[test]
+fn test_reservation_is_all_or_nothing() {
+    let mut reservations = TileReservations::new();
+    assert!(reservations.try_reserve(0, &Rect2F::new(5.0, 0.0, 4.8, 4.8)));
+
+    // Overlaps tile [1,0] owned by entity 0
+    assert!(!reservations.try_reserve(1, &Rect2F::new(0.0, 0.0, 9.8, 4.8)));
+    assert_eq!(reservations.owner(&Vector2I::new(0, 0)), None);
+
+    // Entity can extend own reservation
+    assert!(reservations.try_reserve(0, &Rect2F::new(0.0, 0.0, 9.8, 4.8)));
+    assert_eq!(reservations.owner(&Vector2I::new(0, 0)), Some(0));
+}
+
+#[test]
+fn test_reservation_release() {
+    let mut reservations = TileReservations::new();
+    assert!(reservations.try_reserve(0, &Rect2F::new(0.0, 0.0, 9.8, 4.8)));
+
+    reservations.retain_only(0, &Rect2F::new(5.0, 0.0, 4.8, 4.8));
+    assert_eq!(reservations.owner(&Vector2I::new(0, 0)), None);
+    assert_eq!(reservations.owner(&Vector2I::new(1, 0)), Some(0));
+
+    // Tiles of other entities stay reserved
+    assert!(reservations.try_reserve(1, &Rect2F::new(10.0, 0.0, 4.8, 4.8)));
+    reservations.release_all(0);
+    assert_eq!(reservations.iter().map(|(_, owner)| *owner).collect::<Vec<_>>(), vec![1]);
+    reservations.release_all(1);
+    assert_eq!(reservations.iter().count(), 0);
+    assert!(reservations.owned.is_empty());
+}
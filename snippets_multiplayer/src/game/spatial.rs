@@ -0,0 +1,109 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    common::{Rect2F, Rect2I, Vector2I},
+    world::EntityId
+};
+
+/// Uniform grid of buckets, entity is stored in every cell its bounding box touches
+#[derive(Debug, Clone)]
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<Vector2I, HashSet<EntityId>>,
+    entity_cells: HashMap<EntityId, Rect2I>,
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            entity_cells: HashMap::new(),
+        }
+    }
+
+    /// Range of cells touched by area, size is inclusive count of cells
+    fn cells_range(&self, area: &Rect2F) -> Rect2I {
+        let first_x = (area.pos.x / self.cell_size).floor() as i32;
+        let first_y = (area.pos.y / self.cell_size).floor() as i32;
+        let last_x = ((area.pos.x + area.size.x) / self.cell_size).floor() as i32;
+        let last_y = ((area.pos.y + area.size.y) / self.cell_size).floor() as i32;
+        Rect2I::new(first_x, first_y, last_x - first_x + 1, last_y - first_y + 1)
+    }
+
+    fn iter_cells(range: Rect2I) -> impl Iterator<Item = Vector2I> {
+        (range.pos.x..range.pos.x + range.size.x)
+            .flat_map(move |x| (range.pos.y..range.pos.y + range.size.y).map(move |y| Vector2I::new(x, y)))
+    }
+
+    /// Insert or move entity, cheap if it stays in the same cells
+    pub fn update(&mut self, entity_id: EntityId, bounding_box: &Rect2F) {
+        let new_range = self.cells_range(bounding_box);
+        if self.entity_cells.get(&entity_id) == Some(&new_range) {
+            return;
+        }
+
+        self.remove(entity_id);
+        for cell in Self::iter_cells(new_range) {
+            self.cells.entry(cell).or_default().insert(entity_id);
+        }
+        self.entity_cells.insert(entity_id, new_range);
+    }
+
+    pub fn remove(&mut self, entity_id: EntityId) {
+        if let Some(range) = self.entity_cells.remove(&entity_id) {
+            for cell in Self::iter_cells(range) {
+                if let Some(bucket) = self.cells.get_mut(&cell) {
+                    bucket.remove(&entity_id);
+                    if bucket.is_empty() {
+                        self.cells.remove(&cell);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Candidates from cells touched by area, may contain entities just outside of it
+    pub fn query(&self, area: &Rect2F) -> HashSet<EntityId> {
+        Self::iter_cells(self.cells_range(area))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .collect()
+    }
+}
+
+#[test]
+fn test_spatial_grid_query() {
+    let mut grid = SpatialGrid::new(10.0);
+    grid.update(0, &Rect2F::new(1.0, 1.0, 4.8, 4.8));
+    grid.update(1, &Rect2F::new(55.0, 55.0, 4.8, 4.8));
+    grid.update(2, &Rect2F::new(-15.0, 0.0, 4.8, 4.8));
+
+    assert_eq!(grid.query(&Rect2F::new(0.0, 0.0, 10.0, 10.0)), HashSet::from([0]));
+    assert_eq!(grid.query(&Rect2F::new(-20.0, -20.0, 100.0, 100.0)), HashSet::from([0, 1, 2]));
+    assert!(grid.query(&Rect2F::new(100.0, 100.0, 10.0, 10.0)).is_empty());
+}
+
+#[test]
+fn test_spatial_grid_update_and_remove() {
+    let mut grid = SpatialGrid::new(10.0);
+    grid.update(0, &Rect2F::new(1.0, 1.0, 4.8, 4.8));
+    grid.update(0, &Rect2F::new(31.0, 1.0, 4.8, 4.8));
+
+    assert!(grid.query(&Rect2F::new(0.0, 0.0, 9.0, 9.0)).is_empty());
+    assert_eq!(grid.query(&Rect2F::new(30.0, 0.0, 9.0, 9.0)), HashSet::from([0]));
+
+    grid.remove(0);
+    assert!(grid.query(&Rect2F::new(30.0, 0.0, 9.0, 9.0)).is_empty());
+    assert!(grid.cells.is_empty());
+}
+
+#[test]
+fn test_spatial_grid_big_entity_in_many_cells() {
+    let mut grid = SpatialGrid::new(10.0);
+    grid.update(0, &Rect2F::new(5.0, 5.0, 20.0, 4.0));
+
+    assert_eq!(grid.query(&Rect2F::new(21.0, 6.0, 1.0, 1.0)), HashSet::from([0]));
+    assert_eq!(grid.entity_cells[&0], Rect2I::new(0, 0, 3, 1));
+}
@@ -1,40 +1,78 @@
-use super::common::{Rect2F, Rect2X, Vector2F};
+use std::{collections::HashMap, sync::Arc};
+
+use super::{
+    chunk::{Chunk, ChunkCoord, ChunkEvent, ChunkMap},
+    common::{Rect2F, Vector2F},
+    item::{GroundItem, Inventory, ItemDefinitions, ItemEffect, ItemId, ItemStack},
+    reservation::TileReservations,
+    spatial::SpatialGrid
+};
 use rand::seq::IndexedRandom;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum WorldError {
     EntityNotExist,
     EntityCannotMoveThere,
+    EntityIsDead,
+    TargetOutOfRange,
+    AttackOnCooldown,
+    ItemNotInInventory,
+    ItemNotUsable,
+    NothingToPickUp,
+    InventoryFull,
+    NoFreeSpace,
 }
 
-#[derive(Debug)]
+/// Clones are published as read-only snapshots after every tick.
+/// Item definitions and chunk terrain are shared, entities and indices are copied.
+#[derive(Debug, Clone)]
 pub struct World {
     new_entity_id: EntityId,
     entities: Vec<Entity>,
+    entity_indices: HashMap<EntityId, usize>,
+    spatial_index: SpatialGrid,
+    reservations: TileReservations,
+    item_definitions: Arc<ItemDefinitions>,
+    /// Bucketed by chunk, so area queries do not scan all items
+    ground_items: HashMap<ChunkCoord, Vec<GroundItem>>,
+    chunks: ChunkMap,
+    settings: WorldSettings,
 }
 
-#[derive(Debug, PartialEq)]
+/// Tunables which can be set by server configuration
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorldSettings {
+    pub player_movement_speed: f32,
+    pub npc_movement_speed: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum EntityState {
     Idle,
     Moving {
         from_position: Vector2F,
         destination: Vector2F,
     },
+    Dead {
+        respawn_counter: u32,
+    },
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct NpcController {
     spawnpoint: Vector2F,
     roaming_range: Option<f32>,
     change_destination_counter: u32,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PlayerController {
-    dummy: u8,
+    spawnpoint: Vector2F,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum EntityController {
     Npc(NpcController),
     Player(PlayerController),
@@ -42,29 +80,64 @@ pub enum EntityController {
 
 pub type EntityId = u32;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct EntityStats {
     movement_speed: f32,
+    health: u32,
+    max_health: u32,
+    attack: u32,
+    defense: u32,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct AttackOutcome {
+    pub damage: u32,
+    pub target_killed: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Entity {
     pub id: u32,
     pub name: String,
     pub position: Vector2F,
     pub color: [u8; 3],
     pub size: Vector2F,
+    /// Name of sprite drawn by clients
+    pub sprite: String,
     state: EntityState,
     stats: EntityStats,
     controller: EntityController,
+    attack_cooldown_counter: u32,
+    inventory: Inventory,
 }
 
 const PLAYER_MOVEMENT_SPEED: f32 = 0.9;
 const NPC_MOVEMENT_SPEED: f32 = 0.3;
 const NPC_DIRECTION_SELECTION_TICKS: u32 = 3 * 13;
+const FREE_POSITION_SEARCH_RANGE: i32 = 32;
+
+const PLAYER_HEALTH: u32 = 100;
+const PLAYER_ATTACK: u32 = 15;
+const PLAYER_DEFENSE: u32 = 5;
+const PLAYER_RESPAWN_TICKS: u32 = 3 * 30;
+const NPC_HEALTH: u32 = 50;
+const NPC_ATTACK: u32 = 8;
+const NPC_DEFENSE: u32 = 2;
+const NPC_RESPAWN_TICKS: u32 = 10 * 30;
+const ATTACK_COOLDOWN_TICKS: u32 = 20;
+
+impl Default for WorldSettings {
+    fn default() -> Self {
+        Self {
+            player_movement_speed: PLAYER_MOVEMENT_SPEED,
+            npc_movement_speed: NPC_MOVEMENT_SPEED,
+        }
+    }
+}
 
 impl World {
     pub const TILE_SIZE_SIDE: f32 = 5.0;
+    const SPATIAL_CELL_SIZE: f32 = 4.0 * Self::TILE_SIZE_SIDE;
 
     pub fn get_grid_aligned_position(pos: &Vector2F) -> Vector2F {
         fn align_coord(coord: f32) -> f32 {
@@ -78,14 +151,35 @@ impl World {
     }
 
     pub fn new() -> Self {
+        Self::with_item_definitions(ItemDefinitions::default())
+    }
+
+    pub fn with_item_definitions(item_definitions: ItemDefinitions) -> Self {
         log::info!("World created");
         Self {
             new_entity_id: 0,
             entities: vec![],
+            entity_indices: HashMap::new(),
+            spatial_index: SpatialGrid::new(Self::SPATIAL_CELL_SIZE),
+            reservations: TileReservations::new(),
+            item_definitions: Arc::new(item_definitions),
+            ground_items: HashMap::new(),
+            chunks: ChunkMap::new(),
+            settings: WorldSettings::default(),
         }
     }
 
-    pub fn create_entity_player<S: AsRef<str>>(&mut self, name: S, intial_position: Vector2F, size: Vector2F) -> EntityId {
+    /// Applies to entities created afterwards
+    pub fn with_settings(mut self, settings: WorldSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    pub fn settings(&self) -> &WorldSettings {
+        &self.settings
+    }
+
+    pub fn create_entity_player<S: AsRef<str>>(&mut self, name: S, intial_position: Vector2F, size: Vector2F) -> Result<EntityId, WorldError> {
         let intial_position = Self::get_grid_aligned_position(&intial_position);
         let colors = [
             [255, 0, 0],
@@ -103,15 +197,19 @@ impl World {
             size,
             color,
             EntityStats {
-                movement_speed: PLAYER_MOVEMENT_SPEED
+                movement_speed: self.settings.player_movement_speed,
+                health: PLAYER_HEALTH,
+                max_health: PLAYER_HEALTH,
+                attack: PLAYER_ATTACK,
+                defense: PLAYER_DEFENSE,
             }, 
             EntityController::Player(PlayerController {
-                dummy: 123
+                spawnpoint: intial_position
             })
         )
     }
 
-    pub fn create_entity_npc<S: AsRef<str>>(&mut self, name: S, intial_position: Vector2F, size: Vector2F) -> EntityId {
+    pub fn create_entity_npc<S: AsRef<str>>(&mut self, name: S, intial_position: Vector2F, size: Vector2F) -> Result<EntityId, WorldError> {
         let intial_position = Self::get_grid_aligned_position(&intial_position);
         let channel = rand::random_range(35..150);
         let color = [channel, channel, channel];
@@ -121,7 +219,11 @@ impl World {
             size,
             color,
             EntityStats {
-                movement_speed: NPC_MOVEMENT_SPEED
+                movement_speed: self.settings.npc_movement_speed,
+                health: NPC_HEALTH,
+                max_health: NPC_HEALTH,
+                attack: NPC_ATTACK,
+                defense: NPC_DEFENSE,
             }, 
             EntityController::Npc(NpcController {
                 spawnpoint: intial_position,
@@ -131,75 +233,136 @@ impl World {
         )
     }
 
-    pub fn create_entity<S: AsRef<str>>(&mut self, name: S, intial_position: Vector2F, size: Vector2F, color: [u8; 3], stats: EntityStats, controller: EntityController) -> EntityId {
+    /// Entity is placed on the nearest free position, so it never overlaps other entities
+    pub fn create_entity<S: AsRef<str>>(&mut self, name: S, intial_position: Vector2F, size: Vector2F, color: [u8; 3], stats: EntityStats, controller: EntityController) -> Result<EntityId, WorldError> {
         let intial_position = Self::get_grid_aligned_position(&intial_position);
+        let intial_position = self.find_free_position_near(&intial_position, &size)
+            .ok_or(WorldError::NoFreeSpace)?;
         let new_id = self.new_entity_id;
         self.new_entity_id += 1;
 
+        let reserved = self.reservations.try_reserve(new_id, &Rect2F::new(intial_position.x, intial_position.y, size.x, size.y));
+        debug_assert!(reserved, "Free position should be reservable");
+
+        let sprite = match controller {
+            EntityController::Npc(_) => Entity::NPC_SPRITE,
+            EntityController::Player(_) => Entity::PLAYER_SPRITE,
+        };
         let entity = Entity { 
             id: new_id, 
             name: name.as_ref().to_string(),
             position: intial_position,
             size,
             color,
+            sprite: sprite.to_string(),
             state: EntityState::Idle,
             stats,
-            controller
+            controller,
+            attack_cooldown_counter: 0,
+            inventory: Inventory::default(),
         };
 
+        self.spatial_index.update(new_id, &entity.bounding_box());
+        self.entity_indices.insert(new_id, self.entities.len());
         self.entities.push(entity);
-        new_id
+        Ok(new_id)
     }
 
     pub fn remove_entity(&mut self, entity_id: EntityId) -> Result<(), WorldError> {
-        let position = self.entities.iter()
-            .position(|e| e.id == entity_id)
-            .ok_or(WorldError::EntityNotExist)?;
-        let _ = self.entities.remove(position);
+        let index = *self.entity_indices.get(&entity_id).ok_or(WorldError::EntityNotExist)?;
+        if !self.entities[index].is_player() && self.entities[index].is_alive() {
+            self.drop_loot(entity_id);
+        }
+
+        let _ = self.entities.swap_remove(index);
+        self.entity_indices.remove(&entity_id);
+        if let Some(moved_entity) = self.entities.get(index) {
+            self.entity_indices.insert(moved_entity.id, index);
+        }
+
+        self.spatial_index.remove(entity_id);
+        self.reservations.release_all(entity_id);
         Ok(())
     }
 
+    /// Closest position to the given one, where area of `size` is not reserved
+    pub fn find_free_position_near(&self, position: &Vector2F, size: &Vector2F) -> Option<Vector2F> {
+        self.reservations.find_free_position_near(position, size, FREE_POSITION_SEARCH_RANGE)
+    }
+
     pub fn get_entity_by_id(&self, entity_id: EntityId) -> Option<&Entity> {
-        self.entities.iter().find(|e| e.id == entity_id)
+        self.entity_indices.get(&entity_id).map(|index| &self.entities[*index])
     }
 
     pub fn get_entity_by_id_mut(&mut self, entity_id: EntityId) -> Option<&mut Entity> {
-        self.entities.iter_mut().find(|e| e.id == entity_id)
+        self.entity_indices.get(&entity_id).map(|index| &mut self.entities[*index])
+    }
+
+    /// Entities with bounding box intersecting area, found with spatial index
+    pub fn query_entities_in_area(&self, area: &Rect2F) -> impl Iterator<Item = &Entity> + '_ {
+        let area = *area;
+        self.spatial_index.query(&area)
+            .into_iter()
+            .filter_map(|entity_id| self.get_entity_by_id(entity_id))
+            .filter(move |e| e.bounding_box().intersects(&area))
     }
 
     pub fn is_tile_occupied(&self, tile_position: &Vector2F) -> bool {
         let checked_tile = Rect2F::new(tile_position.x, tile_position.y, Self::TILE_SIZE_SIDE, Self::TILE_SIZE_SIDE);
-        for entity in self.entities.iter() {
-            let is_colliding = match entity.state {
-                EntityState::Idle => checked_tile.contains(&entity.position),
-                EntityState::Moving { from_position, destination } => {
-                    checked_tile.contains(&from_position) || checked_tile.contains(&destination)
-                },
-            };
+        self.is_area_occupied(&checked_tile, None)
+    }
 
-            if is_colliding {
-                return true;
-            }
-        }
-        false
+    /// Check if any tile of area is reserved by entity other than `ignored_entity`
+    pub fn is_area_occupied(&self, area: &Rect2F, ignored_entity: Option<EntityId>) -> bool {
+        self.reservations.is_reserved_by_other(area, ignored_entity)
     }
 
-    pub fn tick(&mut self) {
+    /// Returns chunks loaded and unloaded by this tick
+    pub fn tick(&mut self) -> Vec<ChunkEvent> {
         log::trace!("World tick");
 
-        // TODO Do it better
-        let occupied_positions: Vec<_> = self
-        .entities
-        .iter()
-        .map(|e| match &e.state {
-            EntityState::Moving { destination, .. } => *destination,
-            EntityState::Idle => e.position,
-        })
-        .collect();
+        let player_chunks: Vec<ChunkCoord> = self.entities.iter()
+            .filter(|e| e.is_player())
+            .map(|e| Chunk::coord_of(&e.position))
+            .collect();
+        self.chunks.update_active(player_chunks);
+        let chunk_events = self.chunks.drain_events();
+
+        let reservations = &mut self.reservations;
+        let chunks = &self.chunks;
 
         self.entities.iter_mut().for_each(|e| {
             log::trace!(" - {e:?}");
 
+            // NPCs far from players sleep, move in progress is finished so only destination stays reserved
+            if !e.is_player() && !chunks.is_loaded(Chunk::coord_of(&e.position)) {
+                if let EntityState::Moving { destination, .. } = e.state {
+                    log::debug!("   {} falls asleep, finishing move to {}", e.name, destination);
+                    reservations.retain_only(e.id, &e.bounding_box_at(&destination));
+                    e.position = destination;
+                    e.state = EntityState::Idle;
+                }
+                return;
+            }
+
+            e.attack_cooldown_counter = e.attack_cooldown_counter.saturating_sub(1);
+
+            if let EntityState::Dead { respawn_counter } = &mut e.state {
+                if *respawn_counter > 0 {
+                    *respawn_counter -= 1;
+                } else {
+                    let spawnpoint = e.spawnpoint();
+                    if let Some(respawn_position) = reservations.find_free_position_near(&spawnpoint, &e.size, FREE_POSITION_SEARCH_RANGE) {
+                        log::info!("   {} respawned at {}", e.name, respawn_position);
+                        e.position = respawn_position;
+                        reservations.retain_only(e.id, &e.bounding_box());
+                        e.stats.health = e.stats.max_health;
+                        e.state = EntityState::Idle;
+                    }
+                }
+                return;
+            }
+
             if let EntityState::Moving { from_position, destination } = e.state {
                 // Interpolate movement
                 // Destination was checked when entity was idle -> no need to check
@@ -218,6 +381,7 @@ impl World {
                 // Align to destination, Change state to Idle and reset counter
                 if destination_was_reached {
                     log::debug!("   {} reached destination {} go IDLE", e.name, destination);
+                    reservations.retain_only(e.id, &e.bounding_box_at(&destination));
                     e.state = EntityState::Idle;
                     if let EntityController::Npc(npc_controller) = &mut e.controller {
                         npc_controller.change_destination_counter = NPC_DIRECTION_SELECTION_TICKS;
@@ -251,8 +415,9 @@ impl World {
                                 let random_direction = directions.choose(&mut rand::rng()).unwrap();
 
                                 let destination_position = e.position + (*random_direction * Self::TILE_SIZE_SIDE);
+                                let destination_area = e.bounding_box_at(&destination_position);
 
-                                if !occupied_positions.contains(&destination_position) {
+                                if reservations.try_reserve(e.id, &destination_area) {
                                     log::info!("   {} Setting new destination from {} -to-> {} go MOVING!", 
                                         e.name, e.position, destination_position
                                     );
@@ -273,14 +438,38 @@ impl World {
                 },
             }
         });
+
+        // Also catches positions changed from outside of tick
+        for e in self.entities.iter() {
+            self.spatial_index.update(e.id, &e.bounding_box());
+        }
+        chunk_events
     }
     
     pub fn iter_entities(&self) -> impl Iterator<Item = &Entity> {
         self.entities.iter()
     }
 
+    /// Chunk is loaded if it is near any player, updated every tick
+    pub fn get_chunk(&self, coord: ChunkCoord) -> Option<&Chunk> {
+        self.chunks.get(coord)
+    }
+
+    pub fn is_chunk_loaded(&self, coord: ChunkCoord) -> bool {
+        self.chunks.is_loaded(coord)
+    }
+
     pub fn try_start_move_entity_to(&mut self, entity_id: EntityId, next_position: Vector2F) -> Result<(), WorldError> {
-        if self.is_tile_occupied(&next_position) {
+        let entity = self.get_entity_by_id(entity_id).ok_or(WorldError::EntityNotExist)?;
+        if !entity.is_alive() {
+            return Err(WorldError::EntityIsDead);
+        }
+        if entity.is_moving() {
+            return Err(WorldError::EntityCannotMoveThere);
+        }
+        let destination_area = entity.bounding_box_at(&next_position);
+
+        if !self.reservations.try_reserve(entity_id, &destination_area) {
             Err(WorldError::EntityCannotMoveThere)
         } else {
             let entity = self.get_entity_by_id_mut(entity_id).ok_or(WorldError::EntityNotExist)?;
@@ -293,9 +482,225 @@ impl World {
         }
     }
 
+    /// Instantly place entity on grid aligned position, cancels movement in progress
+    pub fn try_teleport_entity(&mut self, entity_id: EntityId, position: Vector2F) -> Result<(), WorldError> {
+        let entity = self.get_entity_by_id(entity_id).ok_or(WorldError::EntityNotExist)?;
+        if !entity.is_alive() {
+            return Err(WorldError::EntityIsDead);
+        }
+        let position = Self::get_grid_aligned_position(&position);
+        let destination_area = entity.bounding_box_at(&position);
+        if self.reservations.is_reserved_by_other(&destination_area, Some(entity_id)) {
+            return Err(WorldError::EntityCannotMoveThere);
+        }
+
+        self.reservations.retain_only(entity_id, &destination_area);
+        let entity = self.get_entity_by_id_mut(entity_id).ok_or(WorldError::EntityNotExist)?;
+        entity.position = position;
+        entity.state = EntityState::Idle;
+        self.spatial_index.update(entity_id, &destination_area);
+        Ok(())
+    }
+
+    /// Attack is possible only on living entity on tile adjacent to the attacker
+    pub fn try_attack_entity(&mut self, attacker_id: EntityId, target_id: EntityId) -> Result<AttackOutcome, WorldError> {
+        let attacker = self.get_entity_by_id(attacker_id).ok_or(WorldError::EntityNotExist)?;
+        let target = self.get_entity_by_id(target_id).ok_or(WorldError::EntityNotExist)?;
+
+        if !attacker.is_alive() || !target.is_alive() {
+            return Err(WorldError::EntityIsDead);
+        }
+        if attacker.attack_cooldown_counter > 0 {
+            return Err(WorldError::AttackOnCooldown);
+        }
+
+        let attacker_box = attacker.bounding_box();
+        let attack_range = Rect2F::new(
+            attacker_box.pos.x - Self::TILE_SIZE_SIDE, 
+            attacker_box.pos.y - Self::TILE_SIZE_SIDE, 
+            attacker_box.size.x + 2.0 * Self::TILE_SIZE_SIDE, 
+            attacker_box.size.y + 2.0 * Self::TILE_SIZE_SIDE
+        );
+        if attacker_id == target_id || !attack_range.intersects(&target.bounding_box()) {
+            return Err(WorldError::TargetOutOfRange);
+        }
+
+        let damage = attacker.stats.attack.saturating_sub(target.stats.defense).max(1);
+
+        let attacker = self.get_entity_by_id_mut(attacker_id).ok_or(WorldError::EntityNotExist)?;
+        attacker.attack_cooldown_counter = ATTACK_COOLDOWN_TICKS;
+
+        let target = self.get_entity_by_id_mut(target_id).ok_or(WorldError::EntityNotExist)?;
+        target.stats.health = target.stats.health.saturating_sub(damage);
+        let target_killed = target.stats.health == 0;
+        let target_is_player = target.is_player();
+
+        if target_killed {
+            log::info!("{} was killed by {}", target.name, attacker_id);
+            target.state = EntityState::Dead { 
+                respawn_counter: if target.is_player() { PLAYER_RESPAWN_TICKS } else { NPC_RESPAWN_TICKS }
+            };
+            self.reservations.release_all(target_id);
+            if !target_is_player {
+                self.drop_loot(target_id);
+            }
+        }
+
+        Ok(AttackOutcome {
+            damage,
+            target_killed
+        })
+    }
+
+    /// Drop inventory and random loot from NPC table on the entity tile
+    fn drop_loot(&mut self, entity_id: EntityId) {
+        let Some(entity) = self.get_entity_by_id_mut(entity_id) else {
+            return;
+        };
+        let position = Self::get_grid_aligned_position(&entity.position);
+        let mut dropped = entity.inventory.take_all();
+        dropped.extend(self.item_definitions.roll_npc_loot());
+
+        for stack in dropped {
+            log::debug!("Dropped {stack:?} at {position}");
+            self.put_ground_item(position, stack);
+        }
+    }
+
+    pub(crate) fn put_ground_item(&mut self, position: Vector2F, stack: ItemStack) {
+        let bucket = self.ground_items.entry(Chunk::coord_of(&position)).or_default();
+        let existing = bucket.iter_mut()
+            .find(|item| item.position == position && item.stack.item_id == stack.item_id);
+        match existing {
+            Some(item) => item.stack.count += stack.count,
+            None => bucket.push(GroundItem { position, stack }),
+        }
+    }
+
+    pub fn iter_ground_items(&self) -> impl Iterator<Item = &GroundItem> {
+        self.ground_items.values().flatten()
+    }
+
+    /// Items lying in area, only chunks touched by area are searched
+    pub fn query_ground_items_in_area(&self, area: &Rect2F) -> impl Iterator<Item = &GroundItem> + '_ {
+        let area = *area;
+        Chunk::coords_in_area(&area)
+            .filter_map(|coord| self.ground_items.get(&coord))
+            .flatten()
+            .filter(move |item| area.contains(&item.position))
+    }
+
+    pub fn get_item_definitions(&self) -> &ItemDefinitions {
+        &self.item_definitions
+    }
+
+    fn get_living_entity_mut(&mut self, entity_id: EntityId) -> Result<&mut Entity, WorldError> {
+        let entity = self.get_entity_by_id_mut(entity_id).ok_or(WorldError::EntityNotExist)?;
+        if entity.is_alive() {
+            Ok(entity)
+        } else {
+            Err(WorldError::EntityIsDead)
+        }
+    }
+
+    /// Pick up all items lying on tiles covered by the entity.
+    /// Items that do not fit into inventory stay on the ground.
+    pub fn try_pick_up_items(&mut self, entity_id: EntityId) -> Result<Vec<ItemStack>, WorldError> {
+        let entity = self.get_living_entity_mut(entity_id)?;
+        let entity_box = entity.bounding_box();
+
+        // Item tile may start up to one tile before the entity box
+        let reach = Rect2F::new(
+            entity_box.pos.x - Self::TILE_SIZE_SIDE,
+            entity_box.pos.y - Self::TILE_SIZE_SIDE,
+            entity_box.size.x + Self::TILE_SIZE_SIDE,
+            entity_box.size.y + Self::TILE_SIZE_SIDE
+        );
+        let mut in_reach = vec![];
+        for coord in Chunk::coords_in_area(&reach) {
+            let Some(bucket) = self.ground_items.get_mut(&coord) else {
+                continue;
+            };
+            let (picked, left): (Vec<_>, Vec<_>) = std::mem::take(bucket)
+                .into_iter()
+                .partition(|item| {
+                    Rect2F::new(item.position.x, item.position.y, Self::TILE_SIZE_SIDE, Self::TILE_SIZE_SIDE)
+                        .intersects(&entity_box)
+                });
+            in_reach.extend(picked);
+            if left.is_empty() {
+                self.ground_items.remove(&coord);
+            } else {
+                *bucket = left;
+            }
+        }
+
+        if in_reach.is_empty() {
+            return Err(WorldError::NothingToPickUp);
+        }
+
+        // Indexed directly, so item definitions can be borrowed at the same time
+        let index = *self.entity_indices.get(&entity_id).ok_or(WorldError::EntityNotExist)?;
+        let entity = &mut self.entities[index];
+        let mut picked_up = vec![];
+        let mut left_on_ground = vec![];
+        for item in in_reach {
+            let not_fitting = entity.inventory.add(item.stack, &self.item_definitions);
+            if not_fitting < item.stack.count {
+                picked_up.push(ItemStack { item_id: item.stack.item_id, count: item.stack.count - not_fitting });
+            }
+            if not_fitting > 0 {
+                left_on_ground.push(GroundItem { 
+                    position: item.position, 
+                    stack: ItemStack { item_id: item.stack.item_id, count: not_fitting }
+                });
+            }
+        }
+        for item in left_on_ground {
+            self.put_ground_item(item.position, item.stack);
+        }
+
+        if picked_up.is_empty() {
+            Err(WorldError::InventoryFull)
+        } else {
+            Ok(picked_up)
+        }
+    }
+
+    pub fn try_drop_item(&mut self, entity_id: EntityId, item_id: ItemId, count: u32) -> Result<(), WorldError> {
+        let entity = self.get_living_entity_mut(entity_id)?;
+        if !entity.inventory.remove(item_id, count) {
+            return Err(WorldError::ItemNotInInventory);
+        }
+        let position = Self::get_grid_aligned_position(&entity.position);
+        self.put_ground_item(position, ItemStack { item_id, count });
+        Ok(())
+    }
+
+    /// Apply item effect on the entity, single item is consumed
+    pub fn try_use_item(&mut self, entity_id: EntityId, item_id: ItemId) -> Result<(), WorldError> {
+        let effect = self.item_definitions.get(item_id)
+            .and_then(|def| def.effect.clone())
+            .ok_or(WorldError::ItemNotUsable)?;
+
+        let entity = self.get_living_entity_mut(entity_id)?;
+        if !entity.inventory.remove(item_id, 1) {
+            return Err(WorldError::ItemNotInInventory);
+        }
+
+        match effect {
+            ItemEffect::Heal { amount } => {
+                entity.stats.health = (entity.stats.health + amount).min(entity.stats.max_health);
+            },
+        }
+        Ok(())
+    }
 }
 
 impl Entity {
+    pub const PLAYER_SPRITE: &str = "player";
+    pub const NPC_SPRITE: &str = "npc";
+
     pub fn is_player(&self) -> bool {
         matches!(self.controller, EntityController::Player(_))
     }
@@ -303,6 +708,69 @@ impl Entity {
     pub fn is_moving(&self) -> bool {
         matches!(self.state, EntityState::Moving { from_position: _, destination: _ })
     }
+
+    /// Vector from start to destination of movement in progress
+    pub fn movement_direction(&self) -> Option<Vector2F> {
+        match self.state {
+            EntityState::Moving { from_position, destination } => Some(destination - from_position),
+            _ => None,
+        }
+    }
+
+    pub fn is_alive(&self) -> bool {
+        !matches!(self.state, EntityState::Dead { respawn_counter: _ })
+    }
+
+    pub fn health(&self) -> u32 {
+        self.stats.health
+    }
+
+    pub fn max_health(&self) -> u32 {
+        self.stats.max_health
+    }
+
+    /// Used when restoring saved state, never above max health
+    pub(crate) fn set_health(&mut self, health: u32) {
+        self.stats.health = health.min(self.stats.max_health);
+    }
+
+    pub fn inventory(&self) -> &Inventory {
+        &self.inventory
+    }
+
+    /// Used when restoring saved state
+    pub(crate) fn set_inventory(&mut self, inventory: Inventory) {
+        self.inventory = inventory;
+    }
+
+    pub fn spawnpoint(&self) -> Vector2F {
+        match &self.controller {
+            EntityController::Npc(npc_controller) => npc_controller.spawnpoint,
+            EntityController::Player(player_controller) => player_controller.spawnpoint,
+        }
+    }
+
+    /// Used when restoring saved state, entity respawns there after death
+    pub(crate) fn set_spawnpoint(&mut self, spawnpoint: Vector2F) {
+        match &mut self.controller {
+            EntityController::Npc(npc_controller) => npc_controller.spawnpoint = spawnpoint,
+            EntityController::Player(player_controller) => player_controller.spawnpoint = spawnpoint,
+        }
+    }
+
+    pub fn bounding_box(&self) -> Rect2F {
+        self.bounding_box_at(&self.position)
+    }
+
+    pub fn bounding_box_at(&self, position: &Vector2F) -> Rect2F {
+        Rect2F::new(position.x, position.y, self.size.x, self.size.y)
+    }
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[test]
@@ -316,7 +784,7 @@ fn test_world_entity_creation_should_increase_entities_count() {
     let mut world = World::new();
     assert_eq!(world.new_entity_id, 0);
 
-    let new_entity_id = world.create_entity_npc("Bob", Vector2F::new(1.0, 2.0), Vector2F::new(1.0, 1.0));
+    let new_entity_id = world.create_entity_npc("Bob", Vector2F::new(1.0, 2.0), Vector2F::new(1.0, 1.0)).unwrap();
     assert_eq!(new_entity_id, 0);
 
     assert_eq!(world.new_entity_id, 1);
@@ -328,11 +796,11 @@ fn test_world_entity_access() {
     let entity_position = Vector2F::new(1.0, 2.0);
 
     let mut world = World::new();
-    let new_entity_id = world.create_entity_npc(entity_name, entity_position, Vector2F::new(1.0, 1.0));
+    let new_entity_id = world.create_entity_npc(entity_name, entity_position, Vector2F::new(1.0, 1.0)).unwrap();
 
     let entity = world.get_entity_by_id(new_entity_id).unwrap();
     assert_eq!(entity.name, entity_name);
-    assert_eq!(entity.position, entity_position);
+    assert_eq!(entity.position, World::get_grid_aligned_position(&entity_position));
     assert_eq!(entity.state, EntityState::Idle);
 }
 
@@ -342,11 +810,11 @@ fn test_world_entity_translate() {
     let translation = Vector2F::new(100.0, 500.0);
 
     let mut world = World::new();
-    let new_entity_id = world.create_entity_npc("Bob", entity_initial_position, Vector2F::new(1.0, 1.0));
+    let new_entity_id = world.create_entity_npc("Bob", entity_initial_position, Vector2F::new(1.0, 1.0)).unwrap();
 
     let entity = world.get_entity_by_id_mut(new_entity_id).unwrap();
     entity.position += translation;
-    assert_eq!(entity.position, entity_initial_position + translation);
+    assert_eq!(entity.position, World::get_grid_aligned_position(&entity_initial_position) + translation);
 }
 
 #[test]
@@ -371,4 +839,437 @@ fn test_coords_negative_alignment() {
     let v2_expected = Vector2F::new((x_tiles_count - 1.0) * World::TILE_SIZE_SIDE, (y_tiles_count - 1.0) * World::TILE_SIZE_SIDE);
 
     assert_eq!(v2, v2_expected, "v1={v1:?}");
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_world_tile_occupied_by_entity_bounding_box() {
+    let mut world = World::new();
+    world.create_entity_npc("Bob", Vector2F::new(5.0, 5.0), Vector2F::new(4.8, 4.8)).unwrap();
+
+    assert!(world.is_tile_occupied(&Vector2F::new(5.0, 5.0)));
+    assert!(!world.is_tile_occupied(&Vector2F::new(0.0, 0.0)));
+    assert!(!world.is_tile_occupied(&Vector2F::new(10.0, 5.0)));
+    assert!(!world.is_tile_occupied(&Vector2F::new(5.0, 10.0)));
+}
+
+#[test]
+fn test_world_multi_tile_entity_blocks_every_covered_tile() {
+    let mut world = World::new();
+    let boss_size = Vector2F::new(2.0 * World::TILE_SIZE_SIDE - 0.2, 2.0 * World::TILE_SIZE_SIDE - 0.2);
+    world.create_entity_npc("Boss", Vector2F::new(0.0, 0.0), boss_size).unwrap();
+
+    for (x, y) in [(0.0, 0.0), (5.0, 0.0), (0.0, 5.0), (5.0, 5.0)] {
+        assert!(world.is_tile_occupied(&Vector2F::new(x, y)), "tile [{x},{y}] should be occupied");
+    }
+    for (x, y) in [(10.0, 0.0), (0.0, 10.0), (-5.0, 0.0), (0.0, -5.0)] {
+        assert!(!world.is_tile_occupied(&Vector2F::new(x, y)), "tile [{x},{y}] should be free");
+    }
+}
+
+#[test]
+fn test_world_entity_cannot_move_into_multi_tile_entity() {
+    let mut world = World::new();
+    let boss_size = Vector2F::new(2.0 * World::TILE_SIZE_SIDE - 0.2, 2.0 * World::TILE_SIZE_SIDE - 0.2);
+    world.create_entity_npc("Boss", Vector2F::new(0.0, 0.0), boss_size).unwrap();
+    let player_id = world.create_entity_player("Player", Vector2F::new(10.0, 5.0), Vector2F::new(4.8, 4.8)).unwrap();
+
+    let result = world.try_start_move_entity_to(player_id, Vector2F::new(5.0, 5.0));
+    assert!(matches!(result, Err(WorldError::EntityCannotMoveThere)));
+
+    let result = world.try_start_move_entity_to(player_id, Vector2F::new(10.0, 10.0));
+    assert!(result.is_ok());
+}
+
+#[test]
+fn test_world_multi_tile_entity_can_move_next_to_itself() {
+    let mut world = World::new();
+    let boss_size = Vector2F::new(2.0 * World::TILE_SIZE_SIDE - 0.2, 2.0 * World::TILE_SIZE_SIDE - 0.2);
+    let boss_id = world.create_entity_npc("Boss", Vector2F::new(0.0, 0.0), boss_size).unwrap();
+
+    let result = world.try_start_move_entity_to(boss_id, Vector2F::new(5.0, 0.0));
+    assert!(result.is_ok());
+    assert!(world.is_tile_occupied(&Vector2F::new(10.0, 5.0)));
+}
+
+#[test]
+fn test_world_spawn_on_occupied_tile_uses_nearest_free_tile() {
+    let mut world = World::new();
+    let first_id = world.create_entity_player("Player", Vector2F::new(0.0, 0.0), Vector2F::new(4.8, 4.8)).unwrap();
+    let second_id = world.create_entity_player("Player", Vector2F::new(0.0, 0.0), Vector2F::new(4.8, 4.8)).unwrap();
+
+    let first = world.get_entity_by_id(first_id).unwrap();
+    let second = world.get_entity_by_id(second_id).unwrap();
+    assert_eq!(first.position, Vector2F::new(0.0, 0.0));
+    assert!(!first.bounding_box().intersects(&second.bounding_box()));
+    let offset = second.position - first.position;
+    assert_eq!(offset.x.abs().max(offset.y.abs()), World::TILE_SIZE_SIDE);
+}
+
+#[test]
+fn test_world_spawn_without_free_tile_fails() {
+    let mut world = World::new();
+    let wall_size = Vector2F::new(400.0, 400.0);
+    world.create_entity_npc("Wall", Vector2F::new(-200.0, -200.0), wall_size).unwrap();
+
+    let result = world.create_entity_player("Player", Vector2F::new(0.0, 0.0), Vector2F::new(4.8, 4.8));
+    assert_eq!(result, Err(WorldError::NoFreeSpace));
+    assert_eq!(world.iter_entities().count(), 1);
+}
+
+#[test]
+fn test_world_destination_is_reserved_when_movement_starts() {
+    let mut world = World::new();
+    let first_id = world.create_entity_player("Player", Vector2F::new(0.0, 0.0), Vector2F::new(4.8, 4.8)).unwrap();
+    let second_id = world.create_entity_player("Player", Vector2F::new(10.0, 0.0), Vector2F::new(4.8, 4.8)).unwrap();
+
+    assert!(world.try_start_move_entity_to(first_id, Vector2F::new(5.0, 0.0)).is_ok());
+    assert!(matches!(
+        world.try_start_move_entity_to(second_id, Vector2F::new(5.0, 0.0)),
+        Err(WorldError::EntityCannotMoveThere)
+    ));
+
+    // Arrival releases the tile left behind
+    for _ in 0..100 {
+        world.tick();
+    }
+    assert!(!world.is_tile_occupied(&Vector2F::new(0.0, 0.0)));
+    assert!(world.try_start_move_entity_to(second_id, Vector2F::new(10.0, 5.0)).is_ok());
+}
+
+#[test]
+fn test_world_attack_adjacent_entity_deals_damage() {
+    let mut world = World::new();
+    let player_id = world.create_entity_player("Player", Vector2F::new(0.0, 0.0), Vector2F::new(4.8, 4.8)).unwrap();
+    let npc_id = world.create_entity_npc("Bob", Vector2F::new(5.0, 0.0), Vector2F::new(4.8, 4.8)).unwrap();
+
+    let outcome = world.try_attack_entity(player_id, npc_id).unwrap();
+    assert_eq!(outcome, AttackOutcome { damage: PLAYER_ATTACK - NPC_DEFENSE, target_killed: false });
+    assert_eq!(world.get_entity_by_id(npc_id).unwrap().health(), NPC_HEALTH - outcome.damage);
+}
+
+#[test]
+fn test_world_attack_requires_adjacent_target() {
+    let mut world = World::new();
+    let player_id = world.create_entity_player("Player", Vector2F::new(0.0, 0.0), Vector2F::new(4.8, 4.8)).unwrap();
+    let npc_id = world.create_entity_npc("Bob", Vector2F::new(10.0, 0.0), Vector2F::new(4.8, 4.8)).unwrap();
+
+    assert!(matches!(world.try_attack_entity(player_id, npc_id), Err(WorldError::TargetOutOfRange)));
+    assert!(matches!(world.try_attack_entity(player_id, player_id), Err(WorldError::TargetOutOfRange)));
+}
+
+#[test]
+fn test_world_attack_cooldown() {
+    let mut world = World::new();
+    let player_id = world.create_entity_player("Player", Vector2F::new(0.0, 0.0), Vector2F::new(4.8, 4.8)).unwrap();
+    let other_id = world.create_entity_player("Other", Vector2F::new(5.0, 5.0), Vector2F::new(4.8, 4.8)).unwrap();
+
+    assert!(world.try_attack_entity(player_id, other_id).is_ok());
+    assert!(matches!(world.try_attack_entity(player_id, other_id), Err(WorldError::AttackOnCooldown)));
+
+    for _ in 0..ATTACK_COOLDOWN_TICKS {
+        world.tick();
+    }
+    assert!(world.try_attack_entity(player_id, other_id).is_ok());
+}
+
+#[test]
+fn test_world_killed_npc_respawns_at_spawnpoint() {
+    let mut world = World::new();
+    let spawnpoint = Vector2F::new(5.0, 0.0);
+    let player_id = world.create_entity_player("Player", Vector2F::new(0.0, 0.0), Vector2F::new(4.8, 4.8)).unwrap();
+    let npc_id = world.create_entity_npc("Bob", spawnpoint, Vector2F::new(4.8, 4.8)).unwrap();
+    if let EntityController::Npc(npc_controller) = &mut world.get_entity_by_id_mut(npc_id).unwrap().controller {
+        npc_controller.roaming_range = None;
+    }
+
+    let mut killed = false;
+    while !killed {
+        killed = world.try_attack_entity(player_id, npc_id).unwrap().target_killed;
+        for _ in 0..ATTACK_COOLDOWN_TICKS {
+            world.tick();
+        }
+    }
+
+    let npc = world.get_entity_by_id(npc_id).unwrap();
+    assert!(!npc.is_alive());
+    assert_eq!(npc.health(), 0);
+    assert!(!world.is_tile_occupied(&npc.position));
+    assert!(matches!(world.try_attack_entity(player_id, npc_id), Err(WorldError::EntityIsDead)));
+
+    for _ in 0..=NPC_RESPAWN_TICKS {
+        world.tick();
+    }
+
+    let npc = world.get_entity_by_id(npc_id).unwrap();
+    assert!(npc.is_alive());
+    assert_eq!(npc.health(), NPC_HEALTH);
+    assert_eq!(npc.position, spawnpoint);
+    assert!(world.is_tile_occupied(&spawnpoint));
+}
+
+#[test]
+fn test_world_drop_and_pick_up_item() {
+    let mut world = World::new();
+    let player_id = world.create_entity_player("Player", Vector2F::new(0.0, 0.0), Vector2F::new(4.8, 4.8)).unwrap();
+    world.get_entity_by_id_mut(player_id).unwrap().inventory.add(ItemStack { item_id: 2, count: 3 }, &ItemDefinitions::default());
+
+    assert!(matches!(world.try_drop_item(player_id, 2, 5), Err(WorldError::ItemNotInInventory)));
+    world.try_drop_item(player_id, 2, 2).unwrap();
+    assert_eq!(world.get_entity_by_id(player_id).unwrap().inventory().count(2), 1);
+    assert_eq!(world.iter_ground_items().next().unwrap(), &GroundItem { 
+        position: Vector2F::new(0.0, 0.0), 
+        stack: ItemStack { item_id: 2, count: 2 } 
+    });
+
+    let picked_up = world.try_pick_up_items(player_id).unwrap();
+    assert_eq!(picked_up, vec![ItemStack { item_id: 2, count: 2 }]);
+    assert_eq!(world.get_entity_by_id(player_id).unwrap().inventory().count(2), 3);
+    assert_eq!(world.iter_ground_items().count(), 0);
+    assert!(matches!(world.try_pick_up_items(player_id), Err(WorldError::NothingToPickUp)));
+}
+
+#[test]
+fn test_world_pick_up_only_items_in_reach() {
+    let mut world = World::new();
+    let player_id = world.create_entity_player("Player", Vector2F::new(0.0, 0.0), Vector2F::new(4.8, 4.8)).unwrap();
+    world.put_ground_item(Vector2F::new(5.0, 0.0), ItemStack { item_id: 0, count: 1 });
+
+    assert!(matches!(world.try_pick_up_items(player_id), Err(WorldError::NothingToPickUp)));
+    assert_eq!(world.iter_ground_items().count(), 1);
+}
+
+#[test]
+fn test_world_pick_up_items_across_chunk_border() {
+    let mut world = World::new();
+    let player_id = world.create_entity_player("Player", Vector2F::new(20.0, 0.0), Vector2F::new(4.8, 4.8)).unwrap();
+    // Halfway between tiles of two chunks
+    world.get_entity_by_id_mut(player_id).unwrap().position = Vector2F::new(Chunk::SIZE - 3.0, 0.0);
+    world.put_ground_item(Vector2F::new(Chunk::SIZE - 5.0, 0.0), ItemStack { item_id: 0, count: 1 });
+    world.put_ground_item(Vector2F::new(Chunk::SIZE, 0.0), ItemStack { item_id: 0, count: 1 });
+
+    assert_eq!(world.try_pick_up_items(player_id).unwrap().len(), 2);
+    assert_eq!(world.iter_ground_items().count(), 0);
+}
+
+#[test]
+fn test_world_query_ground_items_in_area() {
+    let mut world = World::new();
+    for x in [-30.0, 0.0, 20.0, 200.0] {
+        world.put_ground_item(Vector2F::new(x, 0.0), ItemStack { item_id: 0, count: 1 });
+    }
+
+    let area = Rect2F::new(-30.0, -5.0, 55.0, 10.0);
+    let mut found: Vec<_> = world.query_ground_items_in_area(&area).map(|item| item.position.x).collect();
+    found.sort_by(f32::total_cmp);
+    assert_eq!(found, vec![-30.0, 0.0, 20.0]);
+}
+
+#[test]
+fn test_world_use_item_heals() {
+    let mut world = World::new();
+    let player_id = world.create_entity_player("Player", Vector2F::new(0.0, 0.0), Vector2F::new(4.8, 4.8)).unwrap();
+    let player = world.get_entity_by_id_mut(player_id).unwrap();
+    player.stats.health = 10;
+    player.inventory.add(ItemStack { item_id: 1, count: 1 }, &ItemDefinitions::default());
+    player.inventory.add(ItemStack { item_id: 2, count: 1 }, &ItemDefinitions::default());
+
+    assert!(matches!(world.try_use_item(player_id, 2), Err(WorldError::ItemNotUsable)));
+    world.try_use_item(player_id, 1).unwrap();
+    assert_eq!(world.get_entity_by_id(player_id).unwrap().health(), 40);
+    assert!(matches!(world.try_use_item(player_id, 1), Err(WorldError::ItemNotInInventory)));
+}
+
+#[test]
+fn test_world_removed_npc_drops_its_inventory() {
+    let mut world = World::with_item_definitions(ItemDefinitions::from_json_str(
+        r#"{"items": [{"id": 0, "name": "Coin", "max_stack": 99}], "npc_loot": [{"item_id": 0, "chance": 1.0, "count": 2}]}"#
+    ).unwrap());
+    let npc_id = world.create_entity_npc("Bob", Vector2F::new(5.0, 5.0), Vector2F::new(4.8, 4.8)).unwrap();
+    world.get_entity_by_id_mut(npc_id).unwrap().inventory.add(ItemStack { item_id: 0, count: 3 }, &ItemDefinitions::default());
+
+    world.remove_entity(npc_id).unwrap();
+    assert_eq!(world.iter_ground_items().collect::<Vec<_>>(), vec![&GroundItem {
+        position: Vector2F::new(5.0, 5.0),
+        stack: ItemStack { item_id: 0, count: 5 }
+    }]);
+}
+
+#[test]
+fn test_world_query_entities_in_area() {
+    let mut world = World::new();
+    let size = Vector2F::new(4.8, 4.8);
+    let near_id = world.create_entity_player("Near", Vector2F::new(5.0, 5.0), size).unwrap();
+    let far_id = world.create_entity_player("Far", Vector2F::new(200.0, 5.0), size).unwrap();
+    let boss_id = world.create_entity_npc("Boss", Vector2F::new(-20.0, 0.0), Vector2F::new(19.8, 4.8)).unwrap();
+
+    let area = Rect2F::new(0.0, 0.0, 20.0, 20.0);
+    let mut found: Vec<_> = world.query_entities_in_area(&area).map(|e| e.id).collect();
+    found.sort();
+    assert_eq!(found, vec![near_id]);
+
+    let area = Rect2F::new(-3.0, 0.0, 20.0, 20.0);
+    let mut found: Vec<_> = world.query_entities_in_area(&area).map(|e| e.id).collect();
+    found.sort();
+    assert_eq!(found, vec![near_id, boss_id]);
+
+    world.remove_entity(near_id).unwrap();
+    assert_eq!(world.query_entities_in_area(&area).map(|e| e.id).collect::<Vec<_>>(), vec![boss_id]);
+    assert_eq!(world.get_entity_by_id(far_id).unwrap().name, "Far");
+}
+
+#[test]
+fn test_world_spatial_index_follows_moving_entity() {
+    let mut world = World::new();
+    let player_id = world.create_entity_player("Player", Vector2F::new(0.0, 0.0), Vector2F::new(4.8, 4.8)).unwrap();
+    let destination = Rect2F::new(100.0, 0.0, 5.0, 5.0);
+    world.get_entity_by_id_mut(player_id).unwrap().position = destination.pos;
+    assert_eq!(world.query_entities_in_area(&destination).count(), 0);
+
+    world.tick();
+    assert_eq!(world.query_entities_in_area(&destination).next().unwrap().id, player_id);
+}
+
+#[cfg(test)]
+fn assert_entities_do_not_overlap(world: &World) {
+    let entities: Vec<_> = world.iter_entities().collect();
+    for (idx, first) in entities.iter().enumerate() {
+        for second in entities.iter().skip(idx + 1) {
+            assert!(
+                !first.bounding_box().intersects(&second.bounding_box()),
+                "{} at {} overlaps {} at {}", first.id, first.position, second.id, second.position
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+proptest::proptest! {
+    #![proptest_config(proptest::prelude::ProptestConfig::with_cases(48))]
+
+    #[test]
+    fn test_world_entities_never_share_tile(
+        spawns in proptest::collection::vec((-3i32..3, -3i32..3, proptest::bool::ANY), 2..20),
+        moves in proptest::collection::vec((proptest::num::usize::ANY, 0usize..4), 0..150),
+    ) {
+        let mut world = World::new();
+        let size = Vector2F::new(4.8, 4.8);
+        let ids: Vec<_> = spawns.iter()
+            .map(|&(x, y, is_player)| {
+                let position = Vector2F::new(x as f32, y as f32) * World::TILE_SIZE_SIDE;
+                if is_player {
+                    world.create_entity_player("Player", position, size).unwrap()
+                } else {
+                    world.create_entity_npc("Bot", position, size).unwrap()
+                }
+            })
+            .collect();
+        assert_entities_do_not_overlap(&world);
+
+        let directions = [
+            Vector2F::new(1.0, 0.0),
+            Vector2F::new(-1.0, 0.0),
+            Vector2F::new(0.0, 1.0),
+            Vector2F::new(0.0, -1.0),
+        ];
+
+        for (entity_idx, direction_idx) in moves {
+            let entity_id = ids[entity_idx % ids.len()];
+            let position = world.get_entity_by_id(entity_id).unwrap().position;
+            let _ = world.try_start_move_entity_to(entity_id, position + directions[direction_idx] * World::TILE_SIZE_SIDE);
+
+            world.tick();
+            assert_entities_do_not_overlap(&world);
+        }
+
+        for _ in 0..200 {
+            world.tick();
+            assert_entities_do_not_overlap(&world);
+        }
+    }
+}
+
+#[test]
+fn test_world_npc_far_from_players_sleeps() {
+    let mut world = World::new();
+    let player_id = world.create_entity_player("Player", Vector2F::new(0.0, 0.0), Vector2F::new(4.8, 4.8)).unwrap();
+    let far_position = Vector2F::new((ChunkMap::ACTIVE_RADIUS + 2) as f32 * Chunk::SIZE, 0.0);
+    let near_id = world.create_entity_npc("Near", Vector2F::new(10.0, 0.0), Vector2F::new(4.8, 4.8)).unwrap();
+    let far_id = world.create_entity_npc("Far", far_position, Vector2F::new(4.8, 4.8)).unwrap();
+
+    let mut near_moved = false;
+    for _ in 0..10 * NPC_DIRECTION_SELECTION_TICKS {
+        world.tick();
+        near_moved |= world.get_entity_by_id(near_id).unwrap().position != Vector2F::new(10.0, 0.0);
+        assert_eq!(world.get_entity_by_id(far_id).unwrap().position, far_position);
+    }
+    assert!(near_moved);
+    assert!(!world.is_chunk_loaded(Chunk::coord_of(&far_position)));
+
+    // Player approaches, NPC wakes up
+    world.get_entity_by_id_mut(player_id).unwrap().position = far_position - Vector2F::new(Chunk::SIZE, 0.0);
+    let mut far_moved = false;
+    for _ in 0..10 * NPC_DIRECTION_SELECTION_TICKS {
+        world.tick();
+        far_moved |= world.get_entity_by_id(far_id).unwrap().position != far_position;
+    }
+    assert!(far_moved);
+}
+
+#[test]
+fn test_world_npc_falling_asleep_finishes_move() {
+    let mut world = World::new();
+    let player_id = world.create_entity_player("Player", Vector2F::new(0.0, 0.0), Vector2F::new(4.8, 4.8)).unwrap();
+    let npc_id = world.create_entity_npc("Bob", Vector2F::new(10.0, 0.0), Vector2F::new(4.8, 4.8)).unwrap();
+    world.try_start_move_entity_to(npc_id, Vector2F::new(15.0, 0.0)).unwrap();
+    world.tick();
+    assert!(world.get_entity_by_id(npc_id).unwrap().is_moving());
+
+    // Player leaves, NPC chunk unloads in the middle of the move
+    world.get_entity_by_id_mut(player_id).unwrap().position = Vector2F::new((ChunkMap::ACTIVE_RADIUS + 2) as f32 * Chunk::SIZE, 0.0);
+    world.tick();
+    let npc = world.get_entity_by_id(npc_id).unwrap();
+    assert_eq!(npc.state, EntityState::Idle);
+    assert_eq!(npc.position, Vector2F::new(15.0, 0.0));
+    assert!(!world.is_tile_occupied(&Vector2F::new(10.0, 0.0)));
+    assert!(world.is_tile_occupied(&Vector2F::new(15.0, 0.0)));
+}
+
+#[test]
+fn test_world_chunk_events_follow_player() {
+    let mut world = World::new();
+    assert!(world.tick().is_empty());
+
+    let player_id = world.create_entity_player("Player", Vector2F::new(0.0, 0.0), Vector2F::new(4.8, 4.8)).unwrap();
+    assert!(world.tick().contains(&ChunkEvent::Loaded(ChunkCoord::new(0, 0))));
+    assert!(world.get_chunk(ChunkCoord::new(0, 0)).is_some());
+    // Events are reported only by the tick which caused them
+    assert!(world.tick().is_empty());
+
+    world.get_entity_by_id_mut(player_id).unwrap().position = Vector2F::new(0.0, Chunk::SIZE);
+    let events = world.tick();
+    assert!(events.contains(&ChunkEvent::Unloaded(ChunkCoord::new(0, -ChunkMap::ACTIVE_RADIUS))));
+    assert!(events.contains(&ChunkEvent::Loaded(ChunkCoord::new(0, ChunkMap::ACTIVE_RADIUS + 1))));
+
+    world.remove_entity(player_id).unwrap();
+    assert!(world.tick().iter().all(|event| matches!(event, ChunkEvent::Unloaded(_))));
+    assert!(!world.is_chunk_loaded(ChunkCoord::new(0, 1)));
+}
+
+#[test]
+fn test_world_teleport_entity() {
+    let mut world = World::new();
+    let player_id = world.create_entity_player("Player", Vector2F::new(0.0, 0.0), Vector2F::new(4.8, 4.8)).unwrap();
+    world.create_entity_player("Other", Vector2F::new(100.0, 0.0), Vector2F::new(4.8, 4.8)).unwrap();
+    world.try_start_move_entity_to(player_id, Vector2F::new(5.0, 0.0)).unwrap();
+
+    assert!(matches!(world.try_teleport_entity(player_id, Vector2F::new(101.0, 2.0)), Err(WorldError::EntityCannotMoveThere)));
+    world.try_teleport_entity(player_id, Vector2F::new(51.0, 2.0)).unwrap();
+
+    let player = world.get_entity_by_id(player_id).unwrap();
+    assert_eq!(player.position, Vector2F::new(50.0, 0.0));
+    assert!(!player.is_moving());
+    assert!(!world.is_tile_occupied(&Vector2F::new(0.0, 0.0)));
+    assert!(!world.is_tile_occupied(&Vector2F::new(5.0, 0.0)));
+    assert!(world.is_tile_occupied(&Vector2F::new(50.0, 0.0)));
+    assert_eq!(world.query_entities_in_area(&Rect2F::new(50.0, 0.0, 5.0, 5.0)).next().unwrap().id, player_id);
+}
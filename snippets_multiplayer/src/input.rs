@@ -0,0 +1,376 @@
+use std::{
+    collections::HashMap,
+    path::Path,
+    time::Duration
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    client_requests::{ChatChannel, ClientRequest, MoveDirection},
+    config::ConfigError
+};
+
+/// Key names per action. Characters are matched ignoring case, other keys by name, e.g. "ArrowUp", "Enter", "F3".
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeyBindings {
+    pub move_up: Vec<String>,
+    pub move_down: Vec<String>,
+    pub move_left: Vec<String>,
+    pub move_right: Vec<String>,
+    pub zoom_in: Vec<String>,
+    pub zoom_out: Vec<String>,
+    /// Starts typing chat message. While typing, Enter sends it and Escape cancels, those two can not be rebound.
+    pub chat: Vec<String>,
+    pub debug_overlay: Vec<String>,
+    /// Camera follows the player again after panning
+    pub recenter: Vec<String>,
+}
+
+/// Client input settings, read from TOML file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InputConfig {
+    /// Held movement key sends next move after this many milliseconds
+    pub move_repeat_ms: u64,
+    pub bindings: KeyBindings,
+}
+
+/// High-level result of input, independent of windowing library
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputAction {
+    Move(MoveDirection),
+    ZoomIn,
+    ZoomOut,
+    SendChat(String),
+    ToggleDebugOverlay,
+    Recenter,
+}
+
+/// What a key of `KeyBindings` does
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Binding {
+    Move(MoveDirection),
+    ZoomIn,
+    ZoomOut,
+    OpenChat,
+    ToggleDebugOverlay,
+    Recenter,
+}
+
+/// Turns key presses into actions, keeps track of held keys and typed chat message
+pub struct InputMap {
+    bindings: HashMap<String, Binding>,
+    move_repeat: Duration,
+    /// Oldest first, the newest held movement key wins
+    held_keys: Vec<String>,
+    since_move: Duration,
+    chat_draft: Option<String>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let keys = |keys: &[&str]| keys.iter().map(|key| key.to_string()).collect();
+        Self {
+            move_up: keys(&["W", "ArrowUp"]),
+            move_down: keys(&["S", "ArrowDown"]),
+            move_left: keys(&["A", "ArrowLeft"]),
+            move_right: keys(&["D", "ArrowRight"]),
+            zoom_in: keys(&["=", "+"]),
+            zoom_out: keys(&["-"]),
+            chat: keys(&["Enter"]),
+            debug_overlay: keys(&["F3"]),
+            recenter: keys(&["C"]),
+        }
+    }
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self {
+            move_repeat_ms: 200,
+            bindings: KeyBindings::default(),
+        }
+    }
+}
+
+impl InputConfig {
+    /// Missing fields take default values
+    pub fn from_toml_str(toml: &str) -> Result<Self, ConfigError> {
+        let config: Self = toml::from_str(toml).map_err(|source| ConfigError::ParseError { path: None, source })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let toml = std::fs::read_to_string(path).map_err(|source| ConfigError::IoError { path: path.to_path_buf(), source })?;
+        let config: Self = toml::from_str(&toml).map_err(|source| ConfigError::ParseError { path: Some(path.to_path_buf()), source })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.validate_move_repeat()?;
+        self.bindings_by_key().map(|_| ())
+    }
+
+    fn validate_move_repeat(&self) -> Result<(), ConfigError> {
+        if self.move_repeat_ms == 0 {
+            return Err(ConfigError::InvalidValue { field: "move_repeat_ms", reason: "Must be at least 1".to_string() });
+        }
+        Ok(())
+    }
+
+    /// Each key can trigger only one action
+    fn bindings_by_key(&self) -> Result<HashMap<String, Binding>, ConfigError> {
+        let bindings = &self.bindings;
+        let actions = [
+            ("move_up", &bindings.move_up, Binding::Move(MoveDirection::Up)),
+            ("move_down", &bindings.move_down, Binding::Move(MoveDirection::Down)),
+            ("move_left", &bindings.move_left, Binding::Move(MoveDirection::Left)),
+            ("move_right", &bindings.move_right, Binding::Move(MoveDirection::Right)),
+            ("zoom_in", &bindings.zoom_in, Binding::ZoomIn),
+            ("zoom_out", &bindings.zoom_out, Binding::ZoomOut),
+            ("chat", &bindings.chat, Binding::OpenChat),
+            ("debug_overlay", &bindings.debug_overlay, Binding::ToggleDebugOverlay),
+            ("recenter", &bindings.recenter, Binding::Recenter),
+        ];
+
+        let mut by_key = HashMap::new();
+        let mut action_names = HashMap::new();
+        for (action_name, keys, binding) in actions {
+            for key in keys {
+                let key = InputMap::normalize_key(key);
+                if key.is_empty() {
+                    return Err(ConfigError::InvalidValue { field: "bindings", reason: format!("Empty key in '{action_name}'") });
+                }
+                if let Some(other_name) = action_names.insert(key.clone(), action_name) {
+                    if other_name != action_name {
+                        return Err(ConfigError::InvalidValue { field: "bindings", reason: format!("Key '{key}' is bound to both '{other_name}' and '{action_name}'") });
+                    }
+                }
+                by_key.insert(key, binding);
+            }
+        }
+        Ok(by_key)
+    }
+}
+
+impl InputAction {
+    /// Actions handled by server, others only change the client view
+    pub fn to_request(&self) -> Option<ClientRequest> {
+        match self {
+            InputAction::Move(dir) => Some(ClientRequest::Move { dir: *dir }),
+            InputAction::SendChat(text) => Some(ClientRequest::Chat { channel: ChatChannel::Global, text: text.clone() }),
+            _ => None,
+        }
+    }
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        Self::new(&InputConfig::default()).expect("Default bindings should be valid")
+    }
+}
+
+impl InputMap {
+    /// Fixed keys while typing chat message, bindings do not apply then
+    const SEND_CHAT_KEY: &str = "Enter";
+    const CANCEL_CHAT_KEY: &str = "Escape";
+    const ERASE_KEY: &str = "Backspace";
+
+    pub fn new(config: &InputConfig) -> Result<Self, ConfigError> {
+        config.validate_move_repeat()?;
+        Ok(Self {
+            bindings: config.bindings_by_key()?,
+            move_repeat: Duration::from_millis(config.move_repeat_ms),
+            held_keys: vec![],
+            since_move: Duration::ZERO,
+            chat_draft: None,
+        })
+    }
+
+    /// Bindings from TOML file like `assets/input.toml`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        Self::new(&InputConfig::load(path)?)
+    }
+
+    /// Single characters are lowercase, so "W" and "w" are the same key
+    pub fn normalize_key(key: &str) -> String {
+        let key = key.trim();
+        let mut chars = key.chars();
+        match (chars.next(), chars.next()) {
+            (Some(character), None) => character.to_lowercase().collect(),
+            _ => key.to_string(),
+        }
+    }
+
+    /// Message typed so far, None when not typing
+    pub fn chat_draft(&self) -> Option<&str> {
+        self.chat_draft.as_deref()
+    }
+
+    /// `text` is what the key types, used only while typing chat message.
+    /// Repeated presses of held key are ignored, movement repeats in `update` instead.
+    pub fn key_down(&mut self, key: &str, text: Option<&str>) -> Option<InputAction> {
+        let key = Self::normalize_key(key);
+        if let Some(draft) = &mut self.chat_draft {
+            match key.as_str() {
+                Self::SEND_CHAT_KEY => {
+                    let text = self.chat_draft.take().unwrap_or_default();
+                    return (!text.trim().is_empty()).then_some(InputAction::SendChat(text));
+                },
+                Self::CANCEL_CHAT_KEY => self.chat_draft = None,
+                Self::ERASE_KEY => {
+                    draft.pop();
+                },
+                _ => draft.extend(text.unwrap_or_default().chars().filter(|character| !character.is_control())),
+            }
+            return None;
+        }
+
+        if self.held_keys.contains(&key) {
+            return None;
+        }
+        let binding = *self.bindings.get(&key)?;
+        if binding == Binding::OpenChat {
+            // Typed letters must not keep moving the player
+            self.held_keys.clear();
+            self.chat_draft = Some(String::new());
+            return None;
+        }
+        self.held_keys.push(key);
+
+        Some(match binding {
+            Binding::Move(direction) => {
+                self.since_move = Duration::ZERO;
+                InputAction::Move(direction)
+            },
+            Binding::ZoomIn => InputAction::ZoomIn,
+            Binding::ZoomOut => InputAction::ZoomOut,
+            Binding::ToggleDebugOverlay => InputAction::ToggleDebugOverlay,
+            Binding::Recenter => InputAction::Recenter,
+            Binding::OpenChat => unreachable!("Chat is opened above"),
+        })
+    }
+
+    pub fn key_up(&mut self, key: &str) {
+        let key = Self::normalize_key(key);
+        self.held_keys.retain(|held_key| *held_key != key);
+    }
+
+    /// E.g. when window loses focus and releases would not arrive
+    pub fn release_all(&mut self) {
+        self.held_keys.clear();
+    }
+
+    /// Repeats movement of the newest held movement key
+    pub fn update(&mut self, frame_time: Duration) -> Option<InputAction> {
+        let direction = self.held_keys.iter().rev().find_map(|key| match self.bindings.get(key) {
+            Some(Binding::Move(direction)) => Some(*direction),
+            _ => None,
+        })?;
+
+        self.since_move += frame_time;
+        if self.since_move < self.move_repeat {
+            return None;
+        }
+        self.since_move -= self.move_repeat;
+        // Long frame sends one move, not a burst
+        if self.since_move >= self.move_repeat {
+            self.since_move = Duration::ZERO;
+        }
+        Some(InputAction::Move(direction))
+    }
+}
+
+#[test]
+fn test_input_config_from_toml() {
+    let config = InputConfig::from_toml_str(r#"
+        move_repeat_ms = 100
+
+        [bindings]
+        move_up = ["I"]
+        debug_overlay = ["F1"]
+    "#).unwrap();
+    assert_eq!(config.move_repeat_ms, 100);
+    assert_eq!(config.bindings.move_up, vec!["I"]);
+    assert_eq!(config.bindings.move_down, KeyBindings::default().move_down);
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/input.toml");
+    assert_eq!(InputConfig::load(path).unwrap(), InputConfig::default());
+
+    assert!(matches!(InputConfig::from_toml_str("move_repeat_ms = 0"), Err(ConfigError::InvalidValue { field: "move_repeat_ms", .. })));
+    assert!(matches!(InputConfig::from_toml_str("[bindings]\njump = [\"Space\"]"), Err(ConfigError::ParseError { path: None, .. })));
+    // Same key for two actions, case of characters does not matter
+    let conflict = InputConfig::from_toml_str("[bindings]\nzoom_in = [\"w\"]");
+    assert!(matches!(conflict, Err(ConfigError::InvalidValue { field: "bindings", reason }) if reason.contains("'move_up' and 'zoom_in'")));
+}
+
+#[test]
+fn test_input_map_actions() {
+    let mut input = InputMap::new(&InputConfig::default()).unwrap();
+
+    assert_eq!(input.key_down("W", Some("W")), Some(InputAction::Move(MoveDirection::Up)));
+    input.key_up("w");
+    assert_eq!(input.key_down("ArrowLeft", None), Some(InputAction::Move(MoveDirection::Left)));
+    assert_eq!(input.key_down("+", Some("+")), Some(InputAction::ZoomIn));
+    assert_eq!(input.key_down("F3", None), Some(InputAction::ToggleDebugOverlay));
+    assert_eq!(input.key_down("q", Some("q")), None);
+
+    assert!(matches!(InputAction::Move(MoveDirection::Up).to_request(), Some(ClientRequest::Move { dir: MoveDirection::Up })));
+    assert!(InputAction::ZoomOut.to_request().is_none());
+}
+
+#[test]
+fn test_input_map_held_move_repeats() {
+    let config = InputConfig { move_repeat_ms: 100, ..Default::default() };
+    let mut input = InputMap::new(&config).unwrap();
+
+    assert_eq!(input.key_down("d", Some("d")), Some(InputAction::Move(MoveDirection::Right)));
+    // Key repeat of the OS does not add moves
+    assert_eq!(input.key_down("d", Some("d")), None);
+    assert_eq!(input.update(Duration::from_millis(60)), None);
+    assert_eq!(input.update(Duration::from_millis(60)), Some(InputAction::Move(MoveDirection::Right)));
+
+    // Newest held key wins, releasing it goes back to the older one
+    assert_eq!(input.key_down("s", Some("s")), Some(InputAction::Move(MoveDirection::Down)));
+    assert_eq!(input.update(Duration::from_millis(100)), Some(InputAction::Move(MoveDirection::Down)));
+    input.key_up("s");
+    assert_eq!(input.update(Duration::from_secs(10)), Some(InputAction::Move(MoveDirection::Right)));
+    assert_eq!(input.update(Duration::from_millis(1)), None);
+
+    input.key_up("d");
+    assert_eq!(input.update(Duration::from_secs(1)), None);
+}
+
+#[test]
+fn test_input_map_chat() {
+    let mut input = InputMap::new(&InputConfig::default()).unwrap();
+    input.key_down("w", Some("w"));
+
+    // Letters are typed instead of moving
+    assert_eq!(input.key_down("Enter", Some("\r")), None);
+    assert_eq!(input.chat_draft(), Some(""));
+    assert_eq!(input.update(Duration::from_secs(1)), None);
+    for key in ["w", "a", "x"] {
+        assert_eq!(input.key_down(key, Some(key)), None);
+    }
+    input.key_down("Backspace", None);
+    assert_eq!(input.chat_draft(), Some("wa"));
+
+    let action = input.key_down("Enter", Some("\r"));
+    assert_eq!(action, Some(InputAction::SendChat("wa".to_string())));
+    assert!(matches!(action.unwrap().to_request(), Some(ClientRequest::Chat { channel: ChatChannel::Global, text }) if text == "wa"));
+    assert_eq!(input.chat_draft(), None);
+
+    // Empty message is not sent, Escape cancels
+    input.key_down("Enter", None);
+    assert_eq!(input.key_down("Enter", None), None);
+    input.key_down("Enter", None);
+    input.key_down("h", Some("h"));
+    input.key_down("Escape", None);
+    assert_eq!(input.chat_draft(), None);
+}
@@ -0,0 +1,211 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    client_requests::SpriteIds,
+    game::{
+        chunk::{Chunk, ChunkCoord, ChunkMap},
+        common::{Rect2F, Vector2F},
+        world::{Entity, EntityId, World}
+    }
+};
+
+/// Part of the world around player entity, that client is interested in
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "shape")]
+pub enum InterestArea {
+    Rect {
+        half_width: f32,
+        half_height: f32,
+    },
+    Radius {
+        radius: f32,
+    },
+}
+
+impl Default for InterestArea {
+    fn default() -> Self {
+        InterestArea::Rect {
+            half_width: 16.0 * World::TILE_SIZE_SIDE,
+            half_height: 10.0 * World::TILE_SIZE_SIDE
+        }
+    }
+}
+
+impl InterestArea {
+    /// Upper bound of single extent, so client cannot request the whole world
+    pub const MAX_EXTENT: f32 = 64.0 * World::TILE_SIZE_SIDE;
+
+    pub fn clamped(self) -> Self {
+        match self {
+            InterestArea::Rect { half_width, half_height } => InterestArea::Rect {
+                half_width: half_width.clamp(0.0, Self::MAX_EXTENT),
+                half_height: half_height.clamp(0.0, Self::MAX_EXTENT)
+            },
+            InterestArea::Radius { radius } => InterestArea::Radius {
+                radius: radius.clamp(0.0, Self::MAX_EXTENT)
+            },
+        }
+    }
+
+    /// Rect enclosing area centered at point
+    pub fn bounding_rect(&self, center: &Vector2F) -> Rect2F {
+        let (half_width, half_height) = match *self {
+            InterestArea::Rect { half_width, half_height } => (half_width, half_height),
+            InterestArea::Radius { radius } => (radius, radius),
+        };
+        Rect2F::new(center.x - half_width, center.y - half_height, 2.0 * half_width, 2.0 * half_height)
+    }
+
+    /// Entity is inside if any part of its bounding box is
+    pub fn contains(&self, center: &Vector2F, entity: &Entity) -> bool {
+        let bounding_box = entity.bounding_box();
+        match *self {
+            InterestArea::Rect { .. } => self.bounding_rect(center).intersects(&bounding_box),
+            InterestArea::Radius { radius } => {
+                let nearest_point = Vector2F::new(
+                    center.x.clamp(bounding_box.pos.x, bounding_box.pos.x + bounding_box.size.x),
+                    center.y.clamp(bounding_box.pos.y, bounding_box.pos.y + bounding_box.size.y)
+                );
+                (nearest_point - *center).length_squared() <= radius.powi(2)
+            },
+        }
+    }
+}
+
+/// Entities and chunks last sent to the client, used to notify about those leaving the area
+#[derive(Debug, Default)]
+pub struct InterestState {
+    pub area: InterestArea,
+    /// Sprite names already sent to the client
+    pub sprite_ids: SpriteIds,
+    known_entities: HashSet<EntityId>,
+    known_chunks: HashSet<ChunkCoord>,
+}
+
+impl InterestState {
+    pub fn new(area: InterestArea) -> Self {
+        Self {
+            area,
+            sprite_ids: SpriteIds::default(),
+            known_entities: HashSet::new(),
+            known_chunks: HashSet::new(),
+        }
+    }
+
+    /// Entities visible from viewer position and ids of those no longer visible
+    pub fn update<'a>(&mut self, viewer_id: EntityId, world: &'a World) -> (Vec<&'a Entity>, Vec<EntityId>) {
+        let center = match world.get_entity_by_id(viewer_id) {
+            Some(viewer) => viewer.position + viewer.size * 0.5,
+            None => Vector2F::zero(),
+        };
+
+        let query_rect = self.area.bounding_rect(&center);
+        let visible: Vec<&Entity> = world.query_entities_in_area(&query_rect)
+            .filter(|e| self.area.contains(&center, e))
+            .collect();
+
+        let visible_ids: HashSet<EntityId> = visible.iter().map(|e| e.id).collect();
+        let mut despawned: Vec<EntityId> = self.known_entities.difference(&visible_ids).copied().collect();
+        despawned.sort();
+        self.known_entities = visible_ids;
+
+        (visible, despawned)
+    }
+
+    /// Loaded chunks around viewer not sent yet and coords of those client should drop
+    pub fn update_chunks<'a>(&mut self, viewer_id: EntityId, world: &'a World) -> (Vec<&'a Chunk>, Vec<ChunkCoord>) {
+        let nearby: HashSet<ChunkCoord> = match world.get_entity_by_id(viewer_id) {
+            Some(viewer) => ChunkMap::chunks_around(Chunk::coord_of(&viewer.position))
+                .filter(|coord| world.is_chunk_loaded(*coord))
+                .collect(),
+            None => HashSet::new(),
+        };
+
+        let mut streamed: Vec<&Chunk> = nearby.difference(&self.known_chunks)
+            .filter_map(|coord| world.get_chunk(*coord))
+            .collect();
+        streamed.sort_by_key(|chunk| chunk.coord);
+        let mut dropped: Vec<ChunkCoord> = self.known_chunks.difference(&nearby).copied().collect();
+        dropped.sort();
+        self.known_chunks = nearby;
+
+        (streamed, dropped)
+    }
+}
+
+#[test]
+fn test_interest_area_radius_contains() {
+    let mut world = World::new();
+    let size = Vector2F::new(4.8, 4.8);
+    let near_id = world.create_entity_player("Near", Vector2F::new(10.0, 0.0), size).unwrap();
+    let far_id = world.create_entity_player("Far", Vector2F::new(30.0, 30.0), size).unwrap();
+    let area = InterestArea::Radius { radius: 20.0 };
+
+    assert!(area.contains(&Vector2F::zero(), world.get_entity_by_id(near_id).unwrap()));
+    assert!(!area.contains(&Vector2F::zero(), world.get_entity_by_id(far_id).unwrap()));
+
+    // Corner of wide entity is out of range, but its right side is close
+    let wide_id = world.create_entity_npc("Wide", Vector2F::new(-30.0, 0.0), Vector2F::new(19.8, 4.8)).unwrap();
+    assert!(area.contains(&Vector2F::zero(), world.get_entity_by_id(wide_id).unwrap()));
+}
+
+#[test]
+fn test_interest_area_is_clamped() {
+    let area = InterestArea::Radius { radius: 1e9 }.clamped();
+    assert_eq!(area, InterestArea::Radius { radius: InterestArea::MAX_EXTENT });
+}
+
+#[test]
+fn test_interest_state_reports_despawned_entities() {
+    let mut world = World::new();
+    let size = Vector2F::new(4.8, 4.8);
+    let viewer_id = world.create_entity_player("Viewer", Vector2F::new(0.0, 0.0), size).unwrap();
+    let other_id = world.create_entity_player("Other", Vector2F::new(10.0, 0.0), size).unwrap();
+    let far_id = world.create_entity_player("Far", Vector2F::new(500.0, 0.0), size).unwrap();
+    let mut interest = InterestState::new(InterestArea::Rect { half_width: 50.0, half_height: 50.0 });
+
+    let (visible, despawned) = interest.update(viewer_id, &world);
+    let mut visible_ids: Vec<_> = visible.iter().map(|e| e.id).collect();
+    visible_ids.sort();
+    assert_eq!(visible_ids, vec![viewer_id, other_id]);
+    assert!(despawned.is_empty());
+    assert!(!visible_ids.contains(&far_id));
+
+    world.get_entity_by_id_mut(other_id).unwrap().position = Vector2F::new(300.0, 0.0);
+    world.tick();
+
+    let (visible, despawned) = interest.update(viewer_id, &world);
+    assert_eq!(visible.iter().map(|e| e.id).collect::<Vec<_>>(), vec![viewer_id]);
+    assert_eq!(despawned, vec![other_id]);
+
+    // Despawn is reported only once
+    let (_, despawned) = interest.update(viewer_id, &world);
+    assert!(despawned.is_empty());
+}
+
+#[test]
+fn test_interest_state_streams_chunks_once() {
+    let mut world = World::new();
+    let viewer_id = world.create_entity_player("Viewer", Vector2F::new(0.0, 0.0), Vector2F::new(4.8, 4.8)).unwrap();
+    let mut interest = InterestState::default();
+
+    // Nothing loaded before first tick
+    assert_eq!(interest.update_chunks(viewer_id, &world), (vec![], vec![]));
+
+    world.tick();
+    let (streamed, dropped) = interest.update_chunks(viewer_id, &world);
+    let side = (2 * ChunkMap::ACTIVE_RADIUS + 1) as usize;
+    assert_eq!(streamed.len(), side * side);
+    assert!(dropped.is_empty());
+    assert_eq!(interest.update_chunks(viewer_id, &world), (vec![], vec![]));
+
+    world.get_entity_by_id_mut(viewer_id).unwrap().position = Vector2F::new(-Chunk::SIZE, 0.0);
+    world.tick();
+    let (streamed, dropped) = interest.update_chunks(viewer_id, &world);
+    assert_eq!(streamed.len(), side);
+    assert!(streamed.iter().all(|chunk| chunk.coord.x == -ChunkMap::ACTIVE_RADIUS - 1));
+    assert_eq!(dropped.len(), side);
+    assert!(dropped.iter().all(|coord| coord.x == ChunkMap::ACTIVE_RADIUS));
+}
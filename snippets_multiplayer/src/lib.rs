@@ -1,7 +1,21 @@
 pub mod multiplayer_server;
 pub mod multiplayer_client;
 pub mod client_requests;
+pub mod session_registry;
+pub mod chat;
+pub mod interest;
+pub mod rooms;
+pub mod admin;
+pub mod metrics;
+pub mod world_handle;
+pub mod persistence;
+pub mod config;
+pub mod input;
+pub mod admission;
+pub mod loadtest;
 pub mod game;
 pub mod rendering;
 
-pub const TEST_SERVER_ADRESS: &str = "127.0.0.1:4321";
\ No newline at end of file
+pub const TEST_SERVER_ADRESS: &str = "127.0.0.1:4321";
+pub const ADMIN_SERVER_ADRESS: &str = "127.0.0.1:4322";
+pub const METRICS_SERVER_ADRESS: &str = "127.0.0.1:4323";
\ No newline at end of file
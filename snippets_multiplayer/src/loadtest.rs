@@ -0,0 +1,263 @@
+use std::time::{Duration, Instant};
+
+use tokio::sync::{mpsc, watch};
+
+use crate::{
+    client_requests::{ClientRequest, ClientResponse, MoveDirection},
+    multiplayer_client::request_response
+};
+
+/// How simulated player spends its time
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum)]
+pub enum BehaviourProfile {
+    /// Mostly watches the world, moves rarely
+    Observer,
+    /// Walks around like a regular player
+    Wanderer,
+    /// Sends requests back to back, without thinking
+    Stress,
+}
+
+#[derive(Debug, Clone)]
+pub struct LoadTestConfig {
+    pub address: std::net::SocketAddr,
+    pub players: usize,
+    pub duration: Duration,
+    pub profile: BehaviourProfile,
+    /// Players connect evenly spread over this time, requests are measured afterwards
+    pub ramp_up: Duration,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct LatencySummary {
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct LoadTestReport {
+    pub players_connected: usize,
+    pub connect_errors: usize,
+    pub requests: usize,
+    /// Error responses and broken connections
+    pub errors: usize,
+    /// Until every player connected or failed to, not part of `elapsed`
+    pub ramp_up: Duration,
+    /// Measured once all players are connected
+    pub elapsed: Duration,
+    pub latency: LatencySummary,
+}
+
+/// Bots still not connected after ramp-up and this time are not waited for
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Default)]
+struct BotResult {
+    connected: bool,
+    latencies: Vec<Duration>,
+    errors: usize,
+}
+
+impl BehaviourProfile {
+    /// Share of requests which are `Move`, rest is `WorldCheck`
+    pub fn move_ratio(&self) -> f32 {
+        match self {
+            BehaviourProfile::Observer => 0.1,
+            BehaviourProfile::Wanderer => 0.5,
+            BehaviourProfile::Stress => 0.5,
+        }
+    }
+
+    pub fn think_time(&self) -> Duration {
+        match self {
+            BehaviourProfile::Observer => Duration::from_millis(500),
+            BehaviourProfile::Wanderer => Duration::from_millis(100),
+            BehaviourProfile::Stress => Duration::ZERO,
+        }
+    }
+
+    fn next_request(&self) -> ClientRequest {
+        if rand::random::<f32>() >= self.move_ratio() {
+            return ClientRequest::WorldCheck;
+        }
+        let dir = match rand::random_range(0..4) {
+            0 => MoveDirection::Up,
+            1 => MoveDirection::Down,
+            2 => MoveDirection::Left,
+            _ => MoveDirection::Right,
+        };
+        ClientRequest::Move { dir }
+    }
+}
+
+impl LatencySummary {
+    /// Nearest rank percentiles
+    pub fn from_latencies(mut latencies: Vec<Duration>) -> Self {
+        if latencies.is_empty() {
+            return Self::default();
+        }
+        latencies.sort();
+        let percentile = |p: f64| {
+            let rank = ((p / 100.0) * latencies.len() as f64).ceil() as usize;
+            latencies[rank.clamp(1, latencies.len()) - 1]
+        };
+
+        Self {
+            p50: percentile(50.0),
+            p90: percentile(90.0),
+            p99: percentile(99.0),
+            max: *latencies.last().expect("Latencies should not be empty"),
+        }
+    }
+}
+
+impl LoadTestReport {
+    pub fn throughput(&self) -> f64 {
+        if self.elapsed.is_zero() {
+            return 0.0;
+        }
+        self.requests as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+impl std::fmt::Display for LoadTestReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Players:    {} connected, {} failed to connect in {:.1}s", self.players_connected, self.connect_errors, self.ramp_up.as_secs_f64())?;
+        writeln!(f, "Requests:   {} in {:.1}s, {:.0} requests/s", self.requests, self.elapsed.as_secs_f64(), self.throughput())?;
+        writeln!(f, "Errors:     {}", self.errors)?;
+        write!(f, "Latency:    p50={:?} p90={:?} p99={:?} max={:?}", self.latency.p50, self.latency.p90, self.latency.p99, self.latency.max)
+    }
+}
+
+/// Reports once connected and waits for start, so requests are sent only after ramp-up
+async fn run_bot(config: LoadTestConfig, start_delay: Duration, ready: mpsc::Sender<()>, mut start: watch::Receiver<bool>) -> BotResult {
+    let mut result = BotResult::default();
+    tokio::time::sleep(start_delay).await;
+
+    let connect = async {
+        let socket = tokio::net::TcpStream::connect(config.address).await.ok()?;
+        let (reader, mut writer) = socket.into_split();
+        let mut reader = tokio::io::BufReader::new(reader);
+        let response = request_response(&ClientRequest::GetId, &mut reader, &mut writer, |_| {}).await;
+        matches!(response, Some(ClientResponse::GetId { .. })).then_some((reader, writer))
+    };
+    let connection = tokio::time::timeout(CONNECT_TIMEOUT, connect).await.ok().flatten();
+    let _ = ready.send(()).await;
+    drop(ready);
+    let Some((mut reader, mut writer)) = connection else {
+        return result;
+    };
+    if start.wait_for(|started| *started).await.is_err() {
+        return result;
+    }
+    result.connected = true;
+
+    let deadline = tokio::time::Instant::now() + config.duration;
+    while tokio::time::Instant::now() < deadline {
+        let request = config.profile.next_request();
+        let started = Instant::now();
+        // Pushed chat messages are not part of the measured traffic
+        match request_response(&request, &mut reader, &mut writer, |_| {}).await {
+            Some(ClientResponse::BadRequest { .. } | ClientResponse::OtherError { .. }) => result.errors += 1,
+            Some(_) => result.latencies.push(started.elapsed()),
+            None => {
+                result.errors += 1;
+                break;
+            },
+        }
+        tokio::time::sleep(config.profile.think_time()).await;
+    }
+    result
+}
+
+/// Connects simulated players and runs them until configured duration passes
+pub async fn run_load_test(config: LoadTestConfig) -> LoadTestReport {
+    let ramp_up_started = Instant::now();
+    let (ready_sender, ready_receiver) = mpsc::channel(config.players.max(1));
+    let (start_sender, start_receiver) = watch::channel(false);
+
+    let bots: Vec<_> = (0..config.players).map(|idx| {
+        let start_delay = config.ramp_up.mul_f64(idx as f64 / config.players as f64);
+        tokio::spawn(run_bot(config.clone(), start_delay, ready_sender.clone(), start_receiver.clone()))
+    }).collect();
+    drop(ready_sender);
+    let ready = wait_for_bots(ready_receiver, config.players, config.ramp_up + CONNECT_TIMEOUT).await;
+    if ready < config.players {
+        log::warn!("Only {ready} of {} players finished connecting, starting anyway", config.players);
+    }
+    start_sender.send_replace(true);
+    let started = Instant::now();
+
+    let mut report = LoadTestReport::default();
+    let mut latencies = vec![];
+    for bot in bots {
+        let result = match bot.await {
+            Ok(result) => result,
+            Err(e) => {
+                log::error!("Bot failed, reason {e}");
+                BotResult::default()
+            }
+        };
+        if result.connected {
+            report.players_connected += 1;
+        } else {
+            report.connect_errors += 1;
+        }
+        report.errors += result.errors;
+        report.requests += result.latencies.len() + result.errors;
+        latencies.extend(result.latencies);
+    }
+    report.ramp_up = started - ramp_up_started;
+    report.elapsed = started.elapsed();
+    report.latency = LatencySummary::from_latencies(latencies);
+    report
+}
+
+/// Counts ready bots until all of them reported, gave up, or timeout passed.
+/// Bot which failed without reporting drops its sender, so it is not waited for.
+async fn wait_for_bots(mut ready: mpsc::Receiver<()>, players: usize, timeout: Duration) -> usize {
+    let mut count = 0;
+    let _ = tokio::time::timeout(timeout, async {
+        while count < players && ready.recv().await.is_some() {
+            count += 1;
+        }
+    }).await;
+    count
+}
+
+#[test]
+fn test_loadtest_latency_percentiles() {
+    let latencies = (1..=100).map(Duration::from_millis).collect();
+    let summary = LatencySummary::from_latencies(latencies);
+
+    assert_eq!(summary.p50, Duration::from_millis(50));
+    assert_eq!(summary.p90, Duration::from_millis(90));
+    assert_eq!(summary.p99, Duration::from_millis(99));
+    assert_eq!(summary.max, Duration::from_millis(100));
+    assert_eq!(LatencySummary::from_latencies(vec![]), LatencySummary::default());
+}
+
+#[tokio::test]
+async fn test_loadtest_failed_bot_does_not_block_others() {
+    let (ready_sender, ready_receiver) = mpsc::channel(3);
+    for idx in 0..3 {
+        let ready = ready_sender.clone();
+        tokio::spawn(async move {
+            // Bot which errors out returns without reporting
+            if idx != 1 {
+                ready.send(()).await.unwrap();
+            }
+        });
+    }
+    drop(ready_sender);
+
+    let started = Instant::now();
+    assert_eq!(wait_for_bots(ready_receiver, 3, Duration::from_secs(10)).await, 2);
+    assert!(started.elapsed() < Duration::from_secs(1));
+
+    // Bot hanging while holding its sender is given up on after timeout
+    let (_hanging_sender, ready_receiver) = mpsc::channel(1);
+    assert_eq!(wait_for_bots(ready_receiver, 1, Duration::from_millis(10)).await, 0);
+}
@@ -0,0 +1,374 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicI64, AtomicU64, Ordering},
+        Arc, Mutex
+    },
+    time::Duration
+};
+
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+
+pub type SharedMetrics = Arc<Metrics>;
+
+const DURATION_BUCKETS: &[f64] = &[0.0001, 0.00025, 0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1];
+const SIZE_BUCKETS: &[f64] = &[64.0, 128.0, 256.0, 512.0, 1024.0, 4096.0, 16384.0, 65536.0];
+
+#[derive(Debug, Default)]
+pub struct Gauge(AtomicI64);
+
+/// Counter per label value, e.g. per request type
+#[derive(Debug)]
+pub struct LabeledCounter {
+    label: &'static str,
+    values: Mutex<BTreeMap<String, u64>>,
+}
+
+/// Gauge per label set, label set is already formatted e.g. `room="0",kind="npc"`
+#[derive(Debug, Default)]
+pub struct LabeledGauge {
+    values: Mutex<BTreeMap<String, i64>>,
+}
+
+/// Cumulative buckets as in Prometheus, `+Inf` bucket equals `count`
+#[derive(Debug)]
+pub struct Histogram {
+    bounds: &'static [f64],
+    buckets: Vec<AtomicU64>,
+    count: AtomicU64,
+    sum_bits: AtomicU64,
+}
+
+#[derive(Debug)]
+pub struct Metrics {
+    pub sessions_connected: Gauge,
+    pub requests_total: LabeledCounter,
+    pub response_size_bytes: Histogram,
+    pub tick_duration_seconds: Histogram,
+    pub world_command_latency_seconds: Histogram,
+    pub world_entities: LabeledGauge,
+}
+
+impl Gauge {
+    pub fn inc(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn dec(&self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn set(&self, value: i64) {
+        self.0.store(value, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> i64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl LabeledCounter {
+    pub fn new(label: &'static str) -> Self {
+        Self {
+            label,
+            values: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn inc(&self, label_value: &str) {
+        if let Ok(mut values) = self.values.lock() {
+            *values.entry(label_value.to_string()).or_default() += 1;
+        }
+    }
+
+    pub fn get(&self, label_value: &str) -> u64 {
+        self.values.lock().map_or(0, |values| values.get(label_value).copied().unwrap_or(0))
+    }
+}
+
+impl LabeledGauge {
+    pub fn set(&self, labels: String, value: i64) {
+        if let Ok(mut values) = self.values.lock() {
+            values.insert(labels, value);
+        }
+    }
+
+    /// Drop all label sets starting with prefix, e.g. of removed room
+    pub fn remove_prefixed(&self, prefix: &str) {
+        if let Ok(mut values) = self.values.lock() {
+            values.retain(|labels, _| !labels.starts_with(prefix));
+        }
+    }
+}
+
+impl Histogram {
+    pub fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            buckets: bounds.iter().map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum_bits: AtomicU64::new(0f64.to_bits()),
+        }
+    }
+
+    pub fn observe(&self, value: f64) {
+        for (bound, bucket) in self.bounds.iter().zip(self.buckets.iter()) {
+            if value <= *bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        // No atomic f64, swap bits in CAS loop
+        let _ = self.sum_bits.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+            Some((f64::from_bits(bits) + value).to_bits())
+        });
+    }
+
+    pub fn observe_duration(&self, duration: Duration) {
+        self.observe(duration.as_secs_f64());
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    pub fn sum(&self) -> f64 {
+        f64::from_bits(self.sum_bits.load(Ordering::Relaxed))
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            sessions_connected: Gauge::default(),
+            requests_total: LabeledCounter::new("request"),
+            response_size_bytes: Histogram::new(SIZE_BUCKETS),
+            tick_duration_seconds: Histogram::new(DURATION_BUCKETS),
+            world_command_latency_seconds: Histogram::new(DURATION_BUCKETS),
+            world_entities: LabeledGauge::default(),
+        }
+    }
+}
+
+impl Metrics {
+    const PREFIX: &'static str = "snippets";
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn into_shared(self) -> SharedMetrics {
+        Arc::new(self)
+    }
+
+    /// Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        write_header(&mut out, "sessions_connected", "Connected client sessions", "gauge");
+        let _ = writeln!(out, "{}_sessions_connected {}", Self::PREFIX, self.sessions_connected.get());
+
+        write_header(&mut out, "requests_total", "Client requests by type", "counter");
+        if let Ok(values) = self.requests_total.values.lock() {
+            for (label_value, count) in values.iter() {
+                let _ = writeln!(out, "{}_requests_total{{{}=\"{label_value}\"}} {count}", Self::PREFIX, self.requests_total.label);
+            }
+        }
+
+        write_header(&mut out, "world_entities", "Entities in world", "gauge");
+        if let Ok(values) = self.world_entities.values.lock() {
+            for (labels, count) in values.iter() {
+                let _ = writeln!(out, "{}_world_entities{{{labels}}} {count}", Self::PREFIX);
+            }
+        }
+
+        write_histogram(&mut out, "response_size_bytes", "Size of responses sent to clients", &self.response_size_bytes);
+        write_histogram(&mut out, "tick_duration_seconds", "Duration of world tick", &self.tick_duration_seconds);
+        write_histogram(&mut out, "world_command_latency_seconds", "Time from queueing world command to its result", &self.world_command_latency_seconds);
+
+        out
+    }
+}
+
+fn write_header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {}_{name} {help}", Metrics::PREFIX);
+    let _ = writeln!(out, "# TYPE {}_{name} {kind}", Metrics::PREFIX);
+}
+
+fn write_histogram(out: &mut String, name: &str, help: &str, histogram: &Histogram) {
+    write_header(out, name, help, "histogram");
+    for (bound, bucket) in histogram.bounds.iter().zip(histogram.buckets.iter()) {
+        let _ = writeln!(out, "{}_{name}_bucket{{le=\"{bound}\"}} {}", Metrics::PREFIX, bucket.load(Ordering::Relaxed));
+    }
+    let _ = writeln!(out, "{}_{name}_bucket{{le=\"+Inf\"}} {}", Metrics::PREFIX, histogram.count());
+    let _ = writeln!(out, "{}_{name}_sum {}", Metrics::PREFIX, histogram.sum());
+    let _ = writeln!(out, "{}_{name}_count {}", Metrics::PREFIX, histogram.count());
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MetricsServerError {
+    #[error("IoError, reason='{0}'")]
+    IoError(#[from] tokio::io::Error),
+
+    #[error("Failed to shutdown metrics server")]
+    ShutdownError,
+
+    #[error("Could not join task, reason='{0}'")]
+    TaskJoinError(#[from] tokio::task::JoinError),
+}
+
+pub struct MetricsServerHandler {
+    connection_task_handler: tokio::task::JoinHandle<()>,
+    shutdown_sender: tokio::sync::oneshot::Sender<()>,
+}
+
+/// Minimal HTTP server answering `GET /metrics` for Prometheus scraper
+pub struct MetricsServer {
+    listener: tokio::net::TcpListener,
+}
+
+impl MetricsServer {
+    pub async fn bind<A: tokio::net::ToSocketAddrs>(addr: A) -> Result<Self, MetricsServerError> {
+        Ok(Self {
+            listener: tokio::net::TcpListener::bind(addr).await?,
+        })
+    }
+
+    pub fn get_local_address(&self) -> Result<std::net::SocketAddr, std::io::Error> {
+        self.listener.local_addr()
+    }
+
+    pub async fn run(self, metrics: SharedMetrics) -> Result<MetricsServerHandler, MetricsServerError> {
+        let (shutdown_sender, mut shutdown_receiver) = tokio::sync::oneshot::channel();
+
+        let connection_task_handler = tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = &mut shutdown_receiver => {
+                        log::debug!("Metrics server received shut down signal...");
+                        break;
+                    },
+                    incomming_connection = self.listener.accept() => {
+                        if let Ok((socket, _)) = incomming_connection {
+                            let metrics = metrics.clone();
+                            tokio::spawn(async move {
+                                if let Err(e) = process_scrape(socket, metrics).await {
+                                    log::warn!("Metrics scrape failed, reason {e}");
+                                }
+                            });
+                        }
+                    },
+                }
+            }
+        });
+
+        Ok(MetricsServerHandler {
+            connection_task_handler,
+            shutdown_sender,
+        })
+    }
+}
+
+impl MetricsServerHandler {
+    pub async fn shutdown(self) -> Result<(), MetricsServerError> {
+        self.shutdown_sender.send(()).map_err(|_| MetricsServerError::ShutdownError)?;
+        self.connection_task_handler.await?;
+        Ok(())
+    }
+}
+
+/// Single request per connection, headers are read and ignored
+async fn process_scrape(mut socket: tokio::net::TcpStream, metrics: SharedMetrics) -> Result<(), std::io::Error> {
+    let (reader, mut writer) = socket.split();
+    let mut lines = tokio::io::BufReader::new(reader).lines();
+
+    let request_line = lines.next_line().await?.unwrap_or_default();
+    while let Some(header) = lines.next_line().await? {
+        if header.trim().is_empty() {
+            break;
+        }
+    }
+
+    let (status, content_type, body) = match request_line.split_whitespace().take(2).collect::<Vec<_>>()[..] {
+        ["GET", "/metrics"] => ("200 OK", "text/plain; version=0.0.4", metrics.render()),
+        ["GET", _] => ("404 Not Found", "text/plain", "Not found\n".to_string()),
+        _ => ("405 Method Not Allowed", "text/plain", "Method not allowed\n".to_string()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    writer.write_all(response.as_bytes()).await?;
+    writer.shutdown().await
+}
+
+#[cfg(test)]
+pub(crate) async fn test_scrape(address: std::net::SocketAddr, path: &str) -> String {
+    use tokio::io::AsyncReadExt;
+
+    let mut stream = tokio::net::TcpStream::connect(address).await.unwrap();
+    stream.write_all(format!("GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+/// Value of the sample line with exactly this name and labels
+#[cfg(test)]
+pub(crate) fn test_metric_value(scraped: &str, sample: &str) -> Option<f64> {
+    scraped.lines()
+        .find_map(|line| line.strip_prefix(sample)?.strip_prefix(' '))
+        .and_then(|value| value.trim().parse().ok())
+}
+
+#[test]
+fn test_metrics_histogram_buckets() {
+    let histogram = Histogram::new(&[1.0, 5.0]);
+    histogram.observe(0.5);
+    histogram.observe(3.0);
+    histogram.observe(10.0);
+
+    let mut out = String::new();
+    write_histogram(&mut out, "test", "Test", &histogram);
+    assert!(out.contains("snippets_test_bucket{le=\"1\"} 1\n"));
+    assert!(out.contains("snippets_test_bucket{le=\"5\"} 2\n"));
+    assert!(out.contains("snippets_test_bucket{le=\"+Inf\"} 3\n"));
+    assert!(out.contains("snippets_test_sum 13.5\n"));
+    assert!(out.contains("snippets_test_count 3\n"));
+}
+
+#[test]
+fn test_metrics_render_labeled() {
+    let metrics = Metrics::new();
+    metrics.requests_total.inc("GetId");
+    metrics.requests_total.inc("GetId");
+    metrics.world_entities.set("room=\"0\",kind=\"npc\"".to_string(), 4);
+    metrics.world_entities.set("room=\"1\",kind=\"npc\"".to_string(), 2);
+    metrics.world_entities.remove_prefixed("room=\"1\"");
+
+    let out = metrics.render();
+    assert!(out.contains("# TYPE snippets_requests_total counter\n"));
+    assert!(out.contains("snippets_requests_total{request=\"GetId\"} 2\n"));
+    assert!(out.contains("snippets_world_entities{room=\"0\",kind=\"npc\"} 4\n"));
+    assert!(!out.contains("room=\"1\""));
+}
+
+#[tokio::test]
+async fn test_metrics_server_scrape() {
+    let metrics = Metrics::new().into_shared();
+    metrics.sessions_connected.inc();
+    let server = MetricsServer::bind("127.0.0.1:0").await.unwrap();
+    let address = server.get_local_address().unwrap();
+    let handler = server.run(metrics.clone()).await.unwrap();
+
+    let response = test_scrape(address, "/metrics").await;
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.contains("snippets_sessions_connected 1\n"));
+
+    let response = test_scrape(address, "/other").await;
+    assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+    handler.shutdown().await.unwrap();
+}
@@ -1,8 +1,14 @@
-use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
 
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
-
-use crate::game::{common::Vector2F, world::{EntityId, World}};
+use crate::{
+    admission::{Admission, PlayerSlot, QueueEntry, QueueTicket, RejectionReason}, 
+    client_requests::{ClientRequest, ClientResponse}, 
+    game::{common::Vector2F, world::{EntityId, World}}, 
+    metrics::SharedMetrics, 
+    rooms::{RoomError, RoomId, RoomRegistry, SharedRoomRegistry}, 
+    session_registry::{SessionChannels, SharedSessionRegistry}, 
+    world_handle::{WorldHandle, WorldHandleError}
+};
 
 #[derive(Debug, thiserror::Error)]
 pub enum ClientSessionError {
@@ -14,6 +20,18 @@ pub struct ClientSession {
     address: std::net::SocketAddr,
 }
 
+/// Room in which the client currently plays
+pub struct SessionContext {
+    pub address: std::net::SocketAddr,
+    pub player_id: EntityId,
+    pub room_id: RoomId,
+    pub world: WorldHandle,
+    pub sessions: SharedSessionRegistry,
+    pub rooms: SharedRoomRegistry,
+    pub metrics: SharedMetrics,
+    channels: SessionChannels,
+}
+
 impl ClientSession {
     pub fn new(conenction: (tokio::net::TcpStream, std::net::SocketAddr)) -> Self {
         let (socket, address) = conenction;
@@ -23,93 +41,340 @@ impl ClientSession {
         }
     }
 
-    fn on_client_connect(world: Arc<Mutex<World>>) -> EntityId {
-        let player_id: EntityId = match world.lock() {
-            Ok(mut world_guard) => {
-                world_guard.create_entity_player(
-                    "Player", 
-                    Vector2F::new(0.0, 0.0),
-                    Vector2F::new(4.8, 4.8)
-                )
+    async fn on_client_connect(world: &WorldHandle) -> Result<EntityId, RoomError> {
+        world.execute(|world| {
+            let player_id = world.create_entity_player(
+                "Player", 
+                Vector2F::new(0.0, 0.0),
+                Vector2F::new(4.8, 4.8)
+            )?;
+            // Unique name, so player can be found by others e.g. for whispers
+            if let Some(player) = world.get_entity_by_id_mut(player_id) {
+                player.name = format!("Player{player_id}");
+            }
+            Ok(player_id)
+        }).await?.map_err(RoomError::WorldError)
+    }
+
+    fn on_client_disconnect(player_id: EntityId, world: &WorldHandle) {
+        let remove_player = move |world: &mut World| {
+            if world.remove_entity(player_id).is_err() {
+                log::warn!("Player {player_id} was already removed");
+            }
+        };
+        match world.send(remove_player) {
+            Ok(()) => {},
+            Err(WorldHandleError::CommandQueueFull) => {
+                // Removal must not be lost, wait for free place in the queue
+                let world = world.clone();
+                tokio::spawn(async move {
+                    if world.execute(remove_player).await.is_err() {
+                        log::debug!("World already stopped, player {player_id} not removed");
+                    }
+                });
             },
-            Err(e) => {
-                panic!("Could not acquite mutex, reason {e}");
+            Err(WorldHandleError::WorldTaskStopped) => {
+                log::debug!("World already stopped, player {player_id} not removed");
             },
-        };
+        }
+    }
 
-        player_id
+    async fn write_message(writer: &mut tokio::net::tcp::WriteHalf<'_>, message: &ClientResponse) -> std::io::Result<()> {
+        let mut message = serde_json::to_string(message).expect("Could not serialize message");
+        message.push('\n');
+        writer.write_all(message.as_bytes()).await?;
+        writer.flush().await
     }
 
-    /// Line is trimmed already
-    fn on_client_request(player_id: EntityId, request: &str, world: Arc<Mutex<World>>) -> String {
-        crate::client_requests::route_request(player_id, request, world)
+    /// Ban list is checked first, then client gets free place or waits in queue
+    async fn admit(
+        address: std::net::SocketAddr,
+        admission: &Admission,
+        lines: &mut tokio::io::Lines<tokio::io::BufReader<tokio::net::tcp::ReadHalf<'_>>>,
+        writer: &mut tokio::net::tcp::WriteHalf<'_>
+    ) -> Option<PlayerSlot> {
+        let reason = if admission.is_banned(&address.ip()) {
+            RejectionReason::Banned
+        } else {
+            match admission.connection_queue.join() {
+                QueueEntry::Admitted(player_slot) => return Some(player_slot),
+                QueueEntry::Queued(ticket) => {
+                    let player_slot = Self::wait_in_queue(ticket, lines, writer).await?;
+                    // Ban list could be reloaded while waiting
+                    if !admission.is_banned(&address.ip()) {
+                        Self::write_message(writer, &ClientResponse::Queued { position: 0 }).await.ok()?;
+                        return Some(player_slot);
+                    }
+                    RejectionReason::Banned
+                },
+                QueueEntry::Rejected => RejectionReason::ServerFull,
+            }
+        };
+
+        log::warn!("Connection from {address:?} rejected, reason {reason:?}");
+        if let Err(e) = Self::write_message(writer, &ClientResponse::ConnectionRejected { reason }).await {
+            log::debug!("Client could not be notified about rejection, reason: {e}");
+        }
+        None
     }
 
-    fn on_client_disconnect(player_id: EntityId, world: Arc<Mutex<World>>) {
-        match world.lock() {
-            Ok(mut world_guard) => {
-                world_guard.remove_entity(player_id).expect("Player should exist");
-            },
-            Err(e) => {
-                panic!("Could not acquite mutex, reason {e}");
-            },
+    /// Pushes every position change except the final one, returns None if client disconnects while waiting
+    async fn wait_in_queue(
+        mut ticket: QueueTicket,
+        lines: &mut tokio::io::Lines<tokio::io::BufReader<tokio::net::tcp::ReadHalf<'_>>>,
+        writer: &mut tokio::net::tcp::WriteHalf<'_>
+    ) -> Option<PlayerSlot> {
+        let mut notified_position = None;
+        loop {
+            ticket = match ticket.into_slot() {
+                Ok(player_slot) => return Some(player_slot),
+                Err(ticket) => ticket,
+            };
+
+            let position = ticket.position();
+            if notified_position != Some(position) {
+                notified_position = Some(position);
+                Self::write_message(writer, &ClientResponse::Queued { position }).await.ok()?;
+            }
+
+            tokio::select! {
+                changed = ticket.changed() => {
+                    if changed.is_err() {
+                        // Queue was closed, e.g. server shuts down
+                        if let Some(notice) = ticket.shutdown_notice() {
+                            let _ = Self::write_message(writer, &notice.response()).await;
+                        }
+                        return None;
+                    }
+                },
+                line = lines.next_line() => {
+                    line.ok()??;
+                    Self::write_message(writer, &ClientResponse::OtherError { err: "Waiting in queue".to_string() }).await.ok()?;
+                },
+            }
         }
     }
 
-    async fn process_client_connection(&mut self, world: Arc<Mutex<World>>) {
+    async fn process_client_connection(&mut self, rooms: SharedRoomRegistry, admission: Admission) {
         log::info!("Processing client connection: {:?}", self.address);
-        let player_entity_id: EntityId = Self::on_client_connect(world.clone());
 
         let (reader, mut writer) = self.socket.split();
-        let mut buf_reader = tokio::io::BufReader::new(reader);
-        let mut line_buff = String::new();
+        // `next_line` is cancel safe, so it can be raced with pushed messages
+        let mut lines = tokio::io::BufReader::new(reader).lines();
 
-        loop {
-            let cloned_world = world.clone();
-            match buf_reader.read_line(&mut line_buff).await {
-                Ok(0) => {
-                    log::debug!("Client finished connection");
-                    log::info!("Client see world: {:?}", cloned_world);
-                    break;
-                },
-                Ok(_) => {
-                    let line = line_buff.trim();
-                    log::debug!("Client send line: '{}'", line);
+        // Held until session ends
+        let Some(_player_slot) = Self::admit(self.address, &admission, &mut lines, &mut writer).await else {
+            return;
+        };
 
-                    let mut response = Self::on_client_request(
-                        player_entity_id, 
-                        line, 
-                        cloned_world
-                    );
-                    log::debug!("Response with: '{}'", response);
+        let (channels, mut receivers) = SessionChannels::new();
+        let mut session_context = match SessionContext::enter(self.address, rooms, RoomRegistry::MAIN_ROOM_ID, channels).await {
+            Ok(session_context) => session_context,
+            Err(e) => {
+                log::error!("Client could not enter main room, reason {e}");
+                return;
+            }
+        };
+        // Ban list reloaded before session was registered did not kick it
+        if admission.is_banned(&self.address.ip()) {
+            log::warn!("Client {:?} was banned while entering the game", self.address);
+            let _ = Self::write_message(&mut writer, &ClientResponse::Kicked { reason: "Banned".to_string() }).await;
+            session_context.leave();
+            return;
+        }
 
-                    response.push('\n');
+        session_context.metrics.sessions_connected.inc();
 
-                    if let Err(e) = writer.write_all(response.as_bytes()).await {
-                        log::error!("Client could not send response {} reason: {e}", line_buff.trim());
-                    }
+        loop {
+            let mut closes_connection = false;
+            let mut response = tokio::select! {
+                line = lines.next_line() => {
+                    match line {
+                        Ok(None) => {
+                            log::debug!("Client finished connection");
+                            break;
+                        },
+                        Ok(Some(line)) => {
+                            let line = line.trim();
+                            log::debug!("Client send line: '{}'", line);
 
-                    if let Err(e) = writer.flush().await {
-                        log::error!("Client could not flush reason: {e}");
+                            let response = Self::on_client_request(&mut session_context, line).await;
+                            log::debug!("Response with: '{}'", response);
+                            response
+                        },
+                        Err(e) => {
+                            log::error!("Client faile reason = {e}, finished connection");
+                            break;
+                        }
                     }
                 },
-                Err(e) => {
-                    log::error!("Client faile reason = {e}, finished connection");
-                    break;
-                }
+                Some(pushed) = receivers.pushed.recv() => {
+                    closes_connection = pushed.closes_connection();
+                    serde_json::to_string(&pushed).expect("Could not serialize pushed message")
+                },
+                Ok(()) = receivers.closed.changed() => {
+                    let reason = receivers.closed.borrow_and_update().clone().unwrap_or_default();
+                    closes_connection = true;
+                    serde_json::to_string(&ClientResponse::Kicked { reason }).expect("Could not serialize kick")
+                },
+            };
+
+            response.push('\n');
+            session_context.metrics.response_size_bytes.observe(response.len() as f64);
+
+            if let Err(e) = writer.write_all(response.as_bytes()).await {
+                log::error!("Client could not send response {} reason: {e}", response.trim());
+            }
+
+            if let Err(e) = writer.flush().await {
+                log::error!("Client could not flush reason: {e}");
+            }
+
+            if closes_connection {
+                log::info!("Server closes connection with {:?}", self.address);
+                break;
             }
-            line_buff.clear();
         }
 
         log::debug!("Client disconnected");
-        Self::on_client_disconnect(player_entity_id, world);
+        session_context.leave();
+        session_context.metrics.sessions_connected.dec();
     }
 
-    pub fn run(mut self, world: Arc<Mutex<World>>) -> Result<(), ClientSessionError> {
-        let _client_session_handler = tokio::spawn(async move {
-            self.process_client_connection(world).await
+    /// Line is trimmed already
+    async fn on_client_request(session_context: &mut SessionContext, request: &str) -> String {
+        crate::client_requests::route_request(session_context, request).await
+    }
+
+    pub fn run(mut self, rooms: SharedRoomRegistry, admission: Admission) -> Result<tokio::task::JoinHandle<()>, ClientSessionError> {
+        let client_session_handler = tokio::spawn(async move {
+            self.process_client_connection(rooms, admission).await
         });
 
-        Ok(())
+        Ok(client_session_handler)
     }
-}
\ No newline at end of file
+}
+
+impl SessionContext {
+    /// Spawn player in the room and start receiving pushed messages there
+    pub async fn enter(
+        address: std::net::SocketAddr,
+        rooms: SharedRoomRegistry,
+        room_id: RoomId,
+        channels: SessionChannels
+    ) -> Result<Self, RoomError> {
+        let (world, sessions, metrics) = {
+            let rooms_guard = rooms.lock().map_err(|_| RoomError::RegistryPoisoned)?;
+            let room = rooms_guard.get(room_id)?;
+            (room.world.clone(), room.sessions.clone(), rooms_guard.metrics())
+        };
+
+        let mut world = world;
+        let player_id = ClientSession::on_client_connect(&world).await?;
+        // Client should see itself from the very first request
+        world.wait_for_snapshot(|snapshot| snapshot.get_entity_by_id(player_id).is_some()).await?;
+        if let Ok(mut sessions_guard) = sessions.lock() {
+            sessions_guard.register(player_id, address, channels.clone());
+        }
+
+        let session_context = Self {
+            address,
+            player_id,
+            room_id,
+            world,
+            sessions,
+            rooms,
+            metrics,
+            channels,
+        };
+        // Room could be closed by its last player in meantime
+        let room_exists = session_context.rooms.lock()
+            .map_err(|_| RoomError::RegistryPoisoned)?
+            .get(room_id)
+            .is_ok();
+        if !room_exists {
+            session_context.leave();
+            return Err(RoomError::RoomNotExist(room_id));
+        }
+        Ok(session_context)
+    }
+
+    /// Remove player from the current room, room left without players is closed
+    pub fn leave(&self) {
+        if let Ok(mut sessions_guard) = self.sessions.lock() {
+            sessions_guard.unregister(self.player_id);
+        }
+        ClientSession::on_client_disconnect(self.player_id, &self.world);
+
+        let removed_room = self.rooms.lock().ok()
+            .and_then(|mut rooms_guard| rooms_guard.remove_room_if_empty(self.room_id));
+        if let Some(room) = removed_room {
+            log::info!("Room {} '{}' is empty, closing it", room.id, room.name);
+            tokio::spawn(async move {
+                if let Err(e) = room.shutdown().await {
+                    log::error!("Room did not stop cleanly, reason {e}");
+                }
+            });
+        }
+    }
+
+    /// Player leaves current room and is spawned as new entity in the other one
+    pub async fn join_room(&mut self, room_id: RoomId) -> Result<(), RoomError> {
+        if room_id == self.room_id {
+            return Ok(());
+        }
+        // Fail early, before player is removed from the current room
+        self.rooms.lock().map_err(|_| RoomError::RegistryPoisoned)?.get(room_id)?;
+
+        self.leave();
+        match Self::enter(self.address, self.rooms.clone(), room_id, self.channels.clone()).await {
+            Ok(joined) => {
+                log::info!("Player {} moved from room {} to room {room_id} as {}", self.player_id, self.room_id, joined.player_id);
+                *self = joined;
+                Ok(())
+            },
+            Err(e) => {
+                // Room vanished in meantime, go back. Previous room is closed too if player was the last one there.
+                *self = match Self::enter(self.address, self.rooms.clone(), self.room_id, self.channels.clone()).await {
+                    Ok(previous) => previous,
+                    Err(_) => Self::enter(self.address, self.rooms.clone(), RoomRegistry::MAIN_ROOM_ID, self.channels.clone()).await?,
+                };
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Client side of a request. Messages pushed by server in meantime are passed to `on_push`,
+/// returns None when connection is closed.
+pub async fn request_response<R, W>(
+    request: &ClientRequest,
+    reader: &mut R,
+    writer: &mut W,
+    mut on_push: impl FnMut(ClientResponse),
+) -> Option<ClientResponse>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut line = serde_json::to_string(request).expect("Could not serialize request");
+    line.push('\n');
+    writer.write_all(line.as_bytes()).await.ok()?;
+    writer.flush().await.ok()?;
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await.ok()? == 0 {
+            return None;
+        }
+        let response: ClientResponse = serde_json::from_str(line.trim()).ok()?;
+        if !response.is_push() {
+            return Some(response);
+        }
+        let closes_connection = response.closes_connection();
+        on_push(response);
+        if closes_connection {
+            return None;
+        }
+    }
+}
@@ -1,14 +1,17 @@
-use std::{
-    sync::{
-        Arc, 
-        Mutex
-    },
-    time::Duration
-};
+use std::{path::PathBuf, time::Duration};
 
 use crate::{
-    game::world::World, 
-    multiplayer_client::ClientSession
+    admission::{Admission, BanList, ConnectionQueue}, 
+    client_requests::ClientResponse, 
+    config::ServerConfig, 
+    game::{map::MapDefinition, world::WorldSettings}, 
+    interest::InterestArea, 
+    metrics::SharedMetrics, 
+    multiplayer_client::ClientSession, 
+    persistence::{RoomState, ServerState}, 
+    rooms::{RoomError, RoomRegistry, RoomSettings, SharedRoomRegistry}, 
+    session_registry::SharedSessionRegistry, 
+    world_handle::WorldHandle
 };
 
 #[derive(Debug, thiserror::Error)]
@@ -21,22 +24,71 @@ pub enum MultiplayerServerError {
 
     #[error("Could not join task, reason='{0}'")]
     TaskJoinError(#[from] tokio::task::JoinError),
+
+    #[error("Room error, reason='{0}'")]
+    RoomError(#[from] crate::rooms::RoomError),
+
+    #[error("Could not persist state, reason='{0}'")]
+    PersistenceError(#[from] crate::persistence::PersistenceError),
+
+    #[error("Config error, reason='{0}'")]
+    ConfigError(#[from] crate::config::ConfigError),
+
+    #[error("World error, reason='{0}'")]
+    WorldHandleError(#[from] crate::world_handle::WorldHandleError),
+}
+
+/// Sent to every client before server goes down
+#[derive(Debug, Clone)]
+pub struct ShutdownNotice {
+    pub reason: String,
+    pub reconnect_after: Option<Duration>,
 }
 
 pub struct MultiplayerServerHandler {
-    pub world: Arc<Mutex<World>>,
-    connection_task_handler: tokio::task::JoinHandle<()>,
-    main_task_handler: tokio::task::JoinHandle<()>,
-    shutdown_sender: tokio::sync::oneshot::Sender<()>,
+    /// World of the main room
+    pub world: WorldHandle,
+    /// Clients of the main room
+    pub sessions: SharedSessionRegistry,
+    pub rooms: SharedRoomRegistry,
+    pub metrics: SharedMetrics,
+    pub admission: Admission,
+    main_task_handler: tokio::task::JoinHandle<Result<(), MultiplayerServerError>>,
+    shutdown_sender: tokio::sync::oneshot::Sender<ShutdownNotice>,
 }
 
 pub struct MultiplayerServer {
     listener: tokio::net::TcpListener,
+    room_settings: RoomSettings,
+    max_players: Option<usize>,
+    queue_size: usize,
+    ban_list: BanList,
+    map: Option<MapDefinition>,
+    shutdown_timeout: Duration,
+    persistence_path: Option<PathBuf>,
+}
+
+impl Default for ShutdownNotice {
+    fn default() -> Self {
+        Self {
+            reason: "Server is shutting down".to_string(),
+            reconnect_after: None,
+        }
+    }
+}
+
+impl ShutdownNotice {
+    pub fn response(&self) -> ClientResponse {
+        ClientResponse::ServerShutdown {
+            reason: self.reason.clone(),
+            reconnect_after: self.reconnect_after.map(|duration| duration.as_secs()),
+        }
+    }
 }
 
 impl MultiplayerServer {
-    // const MAIN_LOOP_INTERVAL: Duration = Duration::from_millis(250); // Slow for testing purpose
-    const MAIN_LOOP_INTERVAL: Duration = Duration::from_millis(32);
+    pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+    const IDLE_ROOM_CHECK_INTERVAL: Duration = Duration::from_secs(1);
 
     pub async fn bind_any_local() -> Result<Self, MultiplayerServerError> {
         Self::bind("127.0.0.1:0").await
@@ -45,81 +97,286 @@ impl MultiplayerServer {
     pub async fn bind<A: tokio::net::ToSocketAddrs>(addr: A) -> Result<Self, MultiplayerServerError> {
         Ok(Self {
             listener: tokio::net::TcpListener::bind(addr).await?,
+            room_settings: RoomSettings::default(),
+            max_players: None,
+            queue_size: 0,
+            ban_list: BanList::new(),
+            map: None,
+            shutdown_timeout: Self::DEFAULT_SHUTDOWN_TIMEOUT,
+            persistence_path: None,
         })
     }
 
+    /// Binds configured address, map file is loaded already here
+    pub async fn bind_with_config(config: &ServerConfig) -> Result<Self, MultiplayerServerError> {
+        config.validate()?;
+        let map = config.load_map()?;
+        let ban_list = config.load_ban_list()?;
+        let mut server = Self::bind(config.address).await?
+            .with_tick_interval(config.tick_interval())
+            .with_world_settings(config.world)
+            .with_map(map)
+            .with_queue_size(config.queue_size)
+            .with_ban_list(ban_list);
+        if let Some(max_players) = config.max_players {
+            server = server.with_max_players(max_players);
+        }
+        if let Some(persistence_path) = &config.persistence_path {
+            server = server.with_persistence_path(persistence_path);
+        }
+        Ok(server)
+    }
+
+    /// Default part of the world sent to each client in `WorldCheck`
+    pub fn with_interest_area(mut self, interest_area: InterestArea) -> Self {
+        self.room_settings.interest_area = interest_area;
+        self
+    }
+
+    /// Initial tick interval of every room
+    pub fn with_tick_interval(mut self, tick_interval: Duration) -> Self {
+        self.room_settings.tick_interval = tick_interval;
+        self
+    }
+
+    pub fn with_world_settings(mut self, world_settings: WorldSettings) -> Self {
+        self.room_settings.world_settings = world_settings;
+        self
+    }
+
+    /// Rooms other than main are closed after staying without players this long
+    pub fn with_room_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.room_settings.idle_timeout = idle_timeout;
+        self
+    }
+
+    /// Connections above limit wait in queue or are rejected if queue is full
+    pub fn with_max_players(mut self, max_players: usize) -> Self {
+        self.max_players = Some(max_players);
+        self
+    }
+
+    /// Number of connections waiting for free place, 0 rejects immediately
+    pub fn with_queue_size(mut self, queue_size: usize) -> Self {
+        self.queue_size = queue_size;
+        self
+    }
+
+    pub fn with_ban_list(mut self, ban_list: BanList) -> Self {
+        self.ban_list = ban_list;
+        self
+    }
+
+    /// Spawned in the main room when server starts
+    pub fn with_map(mut self, map: MapDefinition) -> Self {
+        self.map = Some(map);
+        self
+    }
+
+    /// How long sessions have to close after shutdown notice, remaining are aborted
+    pub fn with_shutdown_timeout(mut self, shutdown_timeout: Duration) -> Self {
+        self.shutdown_timeout = shutdown_timeout;
+        self
+    }
+
+    /// State of all rooms is saved there on shutdown
+    pub fn with_persistence_path<P: Into<PathBuf>>(mut self, persistence_path: P) -> Self {
+        self.persistence_path = Some(persistence_path.into());
+        self
+    }
+
     pub fn get_local_address(&self) -> Result<std::net::SocketAddr, std::io::Error> {
         self.listener.local_addr()
     }
 
     pub async fn run(self) -> Result<MultiplayerServerHandler, MultiplayerServerError> {
-        let world = Arc::new(Mutex::new(World::new()));
-        let world_shared_clients = world.clone();
-        let world_shared = world.clone();
+        let rooms = RoomRegistry::with_settings(self.room_settings).into_shared();
+        let rooms_shared_clients = rooms.clone();
+        let rooms_shared = rooms.clone();
+        let (world, sessions, metrics) = {
+            let rooms_guard = rooms.lock().map_err(|_| RoomError::RegistryPoisoned)?;
+            let main_room = rooms_guard.get(RoomRegistry::MAIN_ROOM_ID)?;
+            (main_room.world.clone(), main_room.sessions.clone(), rooms_guard.metrics())
+        };
+        let saved_state = match &self.persistence_path {
+            Some(persistence_path) => ServerState::load_if_exists(persistence_path)?,
+            None => None,
+        };
+        // Saved NPCs include those spawned from map before restart
+        if let Some(saved_state) = saved_state {
+            Self::restore_state(&rooms, saved_state).await?;
+        } else if let Some(map) = self.map {
+            let spawned = world.execute(move |world| map.spawn(world).map(|spawned| spawned.len()))
+                .await?
+                .map_err(RoomError::WorldError)?;
+            log::info!("Map loaded, spawned {spawned} NPCs");
+        }
+        let admission = Admission::new(self.ban_list, ConnectionQueue::new(self.max_players, self.queue_size));
+        let admission_clients = admission.clone();
+        let admission_shared = admission.clone();
+        let shutdown_timeout = self.shutdown_timeout;
+        let persistence_path = self.persistence_path;
 
-        let (shutdown_sender, mut shutdown_receiver) = tokio::sync::oneshot::channel();
+        let (shutdown_sender, shutdown_receiver) = tokio::sync::oneshot::channel::<ShutdownNotice>();
         let (shutdown_server_sender, mut shutdown_server_receiver) = tokio::sync::oneshot::channel();
 
+        let listener = self.listener;
         let connection_task_handler = tokio::spawn(async move {
+            let mut client_session_handlers: Vec<tokio::task::JoinHandle<()>> = vec![];
+            let mut idle_check = tokio::time::interval(Self::IDLE_ROOM_CHECK_INTERVAL);
             loop {
                 tokio::select! {
                     _ = &mut shutdown_server_receiver => {
                         log::debug!("Received server shut down signal...");
                         break;
                     },
-                    incomming_connection = self.listener.accept() => {
+                    incomming_connection = listener.accept() => {
                         if let Ok(connection) = incomming_connection {
                             let client_session = ClientSession::new(connection);
-                             //TODO consider storing handler.await.unwrap();
-                            client_session.run(world_shared_clients.clone()).unwrap()
+                            client_session_handlers.retain(|handler| !handler.is_finished());
+                            client_session_handlers.push(client_session.run(rooms_shared_clients.clone(), admission_clients.clone()).unwrap());
                         }
                     },
-                    _ = tokio::time::sleep(Duration::from_secs(1)) => {
-                        log::trace!("Dummy sleep, to remove...");
+                    _ = idle_check.tick() => {
+                        Self::close_idle_rooms(&rooms_shared_clients);
                     },
                 }
             }
+            client_session_handlers
         });
 
+        // Each room ticks on its own task, main task only coordinates shutdown
         let main_task_handler = tokio::spawn(async move {
-            loop {
-                // TODO add timing to have steady ticks/sec average
-                tokio::select! {
-                    _ = &mut shutdown_receiver => {
-                        // Received shutdown signal
-                        log::debug!("Received shut down signal...");
+            let notice = shutdown_receiver.await.unwrap_or_else(|_| {
+                log::warn!("Server handler dropped without shutdown");
+                ShutdownNotice::default()
+            });
+            log::debug!("Received shut down signal...");
 
-                        if shutdown_server_sender.send(()).is_err() {
-                            log::error!("Could not emit signal to stop server!");
-                        }
+            if shutdown_server_sender.send(()).is_err() {
+                log::error!("Could not emit signal to stop server!");
+            }
+            let client_session_handlers = connection_task_handler.await?;
+            let dropped = admission_shared.connection_queue.close(notice.clone());
+            if dropped > 0 {
+                log::info!("Notified {dropped} queued connections about shutdown");
+            }
 
-                        break;
-                    },
-                    _ = tokio::time::sleep(Self::MAIN_LOOP_INTERVAL) => {
-                        // Execute every tick
-                        if let Ok(mut world_lock) = world_shared.lock() {
-                            world_lock.tick();
-                        }
-                    },
+            let notified = Self::notify_sessions(&rooms_shared, &notice);
+            log::info!("Notified {notified} clients about shutdown, waiting for sessions to close...");
+            Self::drain_sessions(client_session_handlers, shutdown_timeout).await;
+
+            let rooms_to_stop = match rooms_shared.lock() {
+                Ok(mut rooms_guard) => rooms_guard.drain(),
+                Err(_) => vec![],
+            };
+            let mut state = ServerState::default();
+            for room in rooms_to_stop {
+                let room_info = room.info();
+                match room.shutdown().await {
+                    Ok(world) => state.rooms.push(RoomState::new(&room_info, &world)),
+                    Err(e) => log::error!("Room did not stop cleanly, reason {e}"),
                 }
             }
+
+            if let Some(persistence_path) = persistence_path {
+                state.rooms.sort_by_key(|room| room.room_id);
+                state.save(&persistence_path)?;
+                log::info!("State saved to {persistence_path:?}");
+            }
+            Ok(())
         });
 
         Ok(MultiplayerServerHandler {
             world,
-            connection_task_handler,
+            sessions,
+            rooms,
+            metrics,
+            admission,
             main_task_handler,
             shutdown_sender,
         })
     }
+
+    /// Rooms are stopped on their own tasks, so accepting connections is not delayed
+    fn close_idle_rooms(rooms: &SharedRoomRegistry) {
+        let idle_rooms = match rooms.lock() {
+            Ok(mut rooms_guard) => rooms_guard.remove_idle_rooms(),
+            Err(_) => vec![],
+        };
+        for room in idle_rooms {
+            log::info!("Room {} '{}' stayed empty, closing it", room.id, room.name);
+            tokio::spawn(async move {
+                if let Err(e) = room.shutdown().await {
+                    log::error!("Room did not stop cleanly, reason {e}");
+                }
+            });
+        }
+    }
+
+    async fn restore_state(rooms: &SharedRoomRegistry, state: ServerState) -> Result<(), RoomError> {
+        for room_state in state.rooms {
+            let world = rooms.lock()
+                .map_err(|_| RoomError::RegistryPoisoned)?
+                .restore_room(room_state.room_id, &room_state.name)?
+                .world.clone();
+            let room_id = room_state.room_id;
+            let restored = world.execute(move |world| room_state.restore(world))
+                .await?
+                .map_err(RoomError::WorldError)?;
+            log::info!("Room {room_id} restored with {restored} NPCs");
+        }
+        Ok(())
+    }
+
+    /// Returns number of clients which were notified
+    fn notify_sessions(rooms: &SharedRoomRegistry, notice: &ShutdownNotice) -> usize {
+        let rooms_sessions: Vec<SharedSessionRegistry> = match rooms.lock() {
+            Ok(rooms_guard) => rooms_guard.iter().map(|room| room.sessions.clone()).collect(),
+            Err(_) => vec![],
+        };
+
+        let mut notified = 0;
+        for sessions in rooms_sessions {
+            let Ok(sessions_guard) = sessions.lock() else {
+                continue;
+            };
+            for player_id in sessions_guard.player_ids() {
+                let pushed = sessions_guard.push(player_id, notice.response());
+                if pushed {
+                    notified += 1;
+                }
+            }
+        }
+        notified
+    }
+
+    /// Sessions which did not close in time are aborted
+    async fn drain_sessions(client_session_handlers: Vec<tokio::task::JoinHandle<()>>, timeout: Duration) {
+        let deadline = tokio::time::Instant::now() + timeout;
+        let mut aborted = 0;
+        for mut client_session_handler in client_session_handlers {
+            if tokio::time::timeout_at(deadline, &mut client_session_handler).await.is_err() {
+                client_session_handler.abort();
+                aborted += 1;
+            }
+        }
+        if aborted > 0 {
+            log::warn!("{aborted} sessions did not close in time and were aborted");
+        }
+    }
 }
 
 impl MultiplayerServerHandler {
     pub async fn shutdown(self) -> Result<(), MultiplayerServerError> {
+        self.shutdown_with_notice(ShutdownNotice::default()).await
+    }
+
+    /// Clients are notified, sessions drained and state persisted before returning
+    pub async fn shutdown_with_notice(self, notice: ShutdownNotice) -> Result<(), MultiplayerServerError> {
         log::debug!("Gracefully shutting down server...");
-        self.shutdown_sender.send(()).map_err(|_| MultiplayerServerError::ShutdownError)?;
-        self.main_task_handler.await?;
-        self.connection_task_handler.await?;
+        self.shutdown_sender.send(notice).map_err(|_| MultiplayerServerError::ShutdownError)?;
+        self.main_task_handler.await??;
         log::debug!("Server shut down successfully!");
         Ok(())
     }
@@ -137,15 +394,345 @@ async fn test_server_creation() {
 
 #[tokio::test]
 async fn test_server_adding_entities() {
+    use crate::game::common::Vector2F;
+
     let server = MultiplayerServer::bind_any_local().await.unwrap();
     let server_handler = server.run().await.unwrap();
 
-    {
-        let mut world = server_handler.world.lock().unwrap();
-        world.create_entity_npc("Tuna", Vector2F::new(10.5, 20.3), Vector2F::new(1.0, 1.0));
+    server_handler.world.execute(|world| {
+        world.create_entity_npc("Tuna", Vector2F::new(10.5, 20.3), Vector2F::new(1.0, 1.0)).unwrap();
         // world.create_entity_npc("Starlette", Vector2F::new(-2.5, 0.0));
-    }
+    }).await.unwrap();
 
     tokio::time::sleep(Duration::from_millis(11000)).await;
     server_handler.shutdown().await.unwrap();
-}
\ No newline at end of file
+}
+#[cfg(test)]
+async fn test_client_request(
+    lines: &mut tokio::io::Lines<tokio::io::BufReader<tokio::net::tcp::OwnedReadHalf>>,
+    writer: &mut tokio::net::tcp::OwnedWriteHalf,
+    request: &crate::client_requests::ClientRequest,
+) -> crate::client_requests::ClientResponse {
+    use tokio::io::AsyncWriteExt;
+
+    let mut request = serde_json::to_string(request).unwrap();
+    request.push('\n');
+    writer.write_all(request.as_bytes()).await.unwrap();
+    let response = lines.next_line().await.unwrap().unwrap();
+    serde_json::from_str(&response).unwrap()
+}
+
+#[tokio::test]
+async fn test_server_chat_is_pushed_to_other_clients() {
+    use tokio::io::AsyncBufReadExt;
+    use crate::client_requests::{ChatChannel, ClientRequest, ClientResponse};
+
+    let server = MultiplayerServer::bind_any_local().await.unwrap();
+    let server_address = server.get_local_address().unwrap();
+    let server_handler = server.run().await.unwrap();
+
+    let mut clients = vec![];
+    for _ in 0..2 {
+        let (reader, mut writer) = tokio::net::TcpStream::connect(server_address).await.unwrap().into_split();
+        let mut lines = tokio::io::BufReader::new(reader).lines();
+        let ClientResponse::GetId { id } = test_client_request(&mut lines, &mut writer, &ClientRequest::GetId).await else {
+            panic!("Expected id");
+        };
+        clients.push((id, lines, writer));
+    }
+
+    let (sender_id, sender_lines, sender_writer) = &mut clients[0];
+    let response = test_client_request(sender_lines, sender_writer, &ClientRequest::Chat { 
+        channel: ChatChannel::Global, 
+        text: "Hello there".to_string() 
+    }).await;
+    assert!(matches!(response, ClientResponse::Chat { delivered: 1 }));
+    let sender_id = *sender_id;
+
+    let (_, receiver_lines, _) = &mut clients[1];
+    let pushed = tokio::time::timeout(Duration::from_secs(1), receiver_lines.next_line()).await.unwrap().unwrap().unwrap();
+    match serde_json::from_str(&pushed).unwrap() {
+        ClientResponse::ChatMessage { from_id, from_name, text, channel } => {
+            assert_eq!(from_id, sender_id);
+            assert_eq!(from_name, format!("Player{sender_id}"));
+            assert_eq!(text, "Hello there");
+            assert_eq!(channel, ChatChannel::Global);
+        },
+        _ => panic!("Expected chat message, got '{pushed}'"),
+    }
+
+    drop(clients);
+    server_handler.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_server_client_moves_between_rooms() {
+    use tokio::io::AsyncBufReadExt;
+    use crate::client_requests::{ClientRequest, ClientResponse};
+
+    let server = MultiplayerServer::bind_any_local().await.unwrap();
+    let server_address = server.get_local_address().unwrap();
+    let server_handler = server.run().await.unwrap();
+
+    let (reader, mut writer) = tokio::net::TcpStream::connect(server_address).await.unwrap().into_split();
+    let mut lines = tokio::io::BufReader::new(reader).lines();
+    let ClientResponse::GetId { id: main_id } = test_client_request(&mut lines, &mut writer, &ClientRequest::GetId).await else {
+        panic!("Expected id");
+    };
+
+    let ClientResponse::RoomCreated { room_id } = test_client_request(&mut lines, &mut writer, &ClientRequest::CreateRoom { 
+        name: "Arena".to_string() 
+    }).await else {
+        panic!("Expected room created");
+    };
+    let ClientResponse::Rooms { rooms } = test_client_request(&mut lines, &mut writer, &ClientRequest::ListRooms).await else {
+        panic!("Expected rooms list");
+    };
+    assert_eq!(rooms.len(), 2);
+    assert_eq!(rooms[1].name, "Arena");
+
+    let ClientResponse::RoomJoined { room_id: joined_room_id, player_id: arena_id } = 
+        test_client_request(&mut lines, &mut writer, &ClientRequest::JoinRoom { room_id }).await else {
+        panic!("Expected room joined");
+    };
+    assert_eq!(joined_room_id, room_id);
+    assert!(server_handler.world.execute(move |world| world.get_entity_by_id(main_id).is_none()).await.unwrap());
+    assert!(server_handler.sessions.lock().unwrap().is_empty());
+    let (arena_world, arena_sessions) = {
+        let rooms = server_handler.rooms.lock().unwrap();
+        let arena = rooms.get(room_id).unwrap();
+        (arena.world.clone(), arena.sessions.clone())
+    };
+    assert!(arena_world.execute(move |world| world.get_entity_by_id(arena_id).is_some()).await.unwrap());
+    assert_eq!(arena_sessions.lock().unwrap().len(), 1);
+
+    let response = test_client_request(&mut lines, &mut writer, &ClientRequest::JoinRoom { room_id: 1234 }).await;
+    assert!(matches!(response, ClientResponse::LobbyError { .. }));
+
+    let ClientResponse::RoomJoined { room_id: joined_room_id, player_id } = 
+        test_client_request(&mut lines, &mut writer, &ClientRequest::LeaveRoom).await else {
+        panic!("Expected room joined");
+    };
+    assert_eq!(joined_room_id, RoomRegistry::MAIN_ROOM_ID);
+    assert!(server_handler.world.execute(move |world| world.get_entity_by_id(player_id).is_some()).await.unwrap());
+    assert_eq!(server_handler.sessions.lock().unwrap().len(), 1);
+
+    // Arena was closed by its last player
+    let ClientResponse::Rooms { rooms } = test_client_request(&mut lines, &mut writer, &ClientRequest::ListRooms).await else {
+        panic!("Expected rooms list");
+    };
+    assert_eq!(rooms.iter().map(|room| room.id).collect::<Vec<_>>(), vec![RoomRegistry::MAIN_ROOM_ID]);
+
+    drop((lines, writer));
+    server_handler.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_server_metrics_are_scraped() {
+    use tokio::io::AsyncBufReadExt;
+    use crate::{client_requests::{ClientRequest, ClientResponse}, metrics::{test_metric_value, test_scrape, MetricsServer}};
+
+    let server = MultiplayerServer::bind_any_local().await.unwrap();
+    let server_address = server.get_local_address().unwrap();
+    let server_handler = server.run().await.unwrap();
+    let metrics_server = MetricsServer::bind("127.0.0.1:0").await.unwrap();
+    let metrics_address = metrics_server.get_local_address().unwrap();
+    let metrics_handler = metrics_server.run(server_handler.metrics.clone()).await.unwrap();
+
+    let (reader, mut writer) = tokio::net::TcpStream::connect(server_address).await.unwrap().into_split();
+    let mut lines = tokio::io::BufReader::new(reader).lines();
+    for _ in 0..3 {
+        let response = test_client_request(&mut lines, &mut writer, &ClientRequest::WorldCheck).await;
+        assert!(matches!(response, ClientResponse::WorldCheck { .. }));
+    }
+    tokio::time::sleep(crate::rooms::Room::DEFAULT_TICK_INTERVAL * 3).await;
+
+    let scraped = test_scrape(metrics_address, "/metrics").await;
+    assert!(scraped.contains("snippets_sessions_connected 1\n"), "{scraped}");
+    assert!(scraped.contains("snippets_requests_total{request=\"WorldCheck\"} 3\n"), "{scraped}");
+    assert!(scraped.contains("snippets_response_size_bytes_count 3\n"), "{scraped}");
+    assert!(scraped.contains("snippets_world_entities{room=\"0\",kind=\"player\"} 1\n"), "{scraped}");
+    for sample in ["snippets_tick_duration_seconds_count", "snippets_world_command_latency_seconds_count"] {
+        let value = test_metric_value(&scraped, sample).unwrap_or_else(|| panic!("Missing {sample} in {scraped}"));
+        assert!(value > 0.0, "{sample} is {value}");
+    }
+
+    drop((lines, writer));
+    metrics_handler.shutdown().await.unwrap();
+    server_handler.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_server_shutdown_notifies_clients_and_persists_state() {
+    use tokio::io::AsyncBufReadExt;
+    use crate::{client_requests::{ClientRequest, ClientResponse}, game::common::Vector2F};
+
+    let persistence_path = std::env::temp_dir().join(format!("snippets_shutdown_{}.json", std::process::id()));
+    let server = MultiplayerServer::bind_any_local().await.unwrap()
+        .with_shutdown_timeout(Duration::from_secs(1))
+        .with_persistence_path(&persistence_path);
+    let server_address = server.get_local_address().unwrap();
+    let server_handler = server.run().await.unwrap();
+    server_handler.world.execute(|world| {
+        world.create_entity_npc("Tuna", Vector2F::new(10.0, 10.0), Vector2F::new(4.8, 4.8)).unwrap();
+    }).await.unwrap();
+    let arena_id = server_handler.rooms.lock().unwrap().create_room("Arena").unwrap();
+
+    let (reader, mut writer) = tokio::net::TcpStream::connect(server_address).await.unwrap().into_split();
+    let mut lines = tokio::io::BufReader::new(reader).lines();
+    let response = test_client_request(&mut lines, &mut writer, &ClientRequest::GetId).await;
+    assert!(matches!(response, ClientResponse::GetId { .. }));
+
+    server_handler.shutdown_with_notice(ShutdownNotice {
+        reason: "Maintenance".to_string(),
+        reconnect_after: Some(Duration::from_secs(60)),
+    }).await.unwrap();
+
+    let pushed = lines.next_line().await.unwrap().unwrap();
+    match serde_json::from_str(&pushed).unwrap() {
+        ClientResponse::ServerShutdown { reason, reconnect_after } => {
+            assert_eq!(reason, "Maintenance");
+            assert_eq!(reconnect_after, Some(60));
+        },
+        _ => panic!("Expected shutdown notice, got '{pushed}'"),
+    }
+    assert!(lines.next_line().await.unwrap().is_none(), "Connection should be closed");
+
+    let state = ServerState::load(&persistence_path).unwrap();
+    assert_eq!(state.rooms.len(), 2);
+    // Player left before state was saved
+    assert_eq!(state.rooms[0].npcs.len(), 1);
+    assert_eq!(state.rooms[0].npcs[0].name, "Tuna");
+
+    // Saved state is loaded on the next start
+    let server_handler = MultiplayerServer::bind_any_local().await.unwrap()
+        .with_persistence_path(&persistence_path)
+        .run().await.unwrap();
+    let names: Vec<String> = server_handler.world.execute(|world| {
+        world.iter_entities().map(|entity| entity.name.clone()).collect()
+    }).await.unwrap();
+    assert_eq!(names, ["Tuna"]);
+    let rooms = server_handler.rooms.lock().unwrap().list();
+    assert_eq!(rooms.iter().map(|room| (room.id, room.name.as_str())).collect::<Vec<_>>(), [(0, "main"), (arena_id, "Arena")]);
+    server_handler.shutdown().await.unwrap();
+    std::fs::remove_file(&persistence_path).unwrap();
+}
+
+#[tokio::test]
+async fn test_server_from_config() {
+    use tokio::io::AsyncBufReadExt;
+
+    let config = ServerConfig {
+        address: "127.0.0.1:0".parse().unwrap(),
+        tick_rate: Some(50),
+        max_players: Some(1),
+        ..Default::default()
+    };
+    let server = MultiplayerServer::bind_with_config(&config).await.unwrap();
+    let server_address = server.get_local_address().unwrap();
+    let server_handler = server.run().await.unwrap();
+
+    let npcs = server_handler.world.execute(|world| world.iter_entities().count()).await.unwrap();
+    assert_eq!(npcs, MapDefinition::default().npcs.len());
+    {
+        let rooms = server_handler.rooms.lock().unwrap();
+        assert_eq!(rooms.get(RoomRegistry::MAIN_ROOM_ID).unwrap().tick_interval(), Duration::from_millis(20));
+    }
+
+    let (reader, mut writer) = tokio::net::TcpStream::connect(server_address).await.unwrap().into_split();
+    let mut lines = tokio::io::BufReader::new(reader).lines();
+    let response = test_client_request(&mut lines, &mut writer, &crate::client_requests::ClientRequest::GetId).await;
+    assert!(matches!(response, ClientResponse::GetId { .. }));
+    // Limit itself is enforced by admission
+    assert_eq!(server_handler.admission.connection_queue.max_players(), Some(1));
+
+    drop((lines, writer));
+    server_handler.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_server_queues_players_above_limit() {
+    use tokio::io::AsyncBufReadExt;
+    use crate::client_requests::ClientRequest;
+
+    async fn read_response(lines: &mut tokio::io::Lines<tokio::io::BufReader<tokio::net::tcp::OwnedReadHalf>>) -> ClientResponse {
+        let line = tokio::time::timeout(Duration::from_secs(2), lines.next_line()).await.unwrap().unwrap().unwrap();
+        serde_json::from_str(&line).unwrap()
+    }
+
+    let server = MultiplayerServer::bind_any_local().await.unwrap()
+        .with_max_players(1)
+        .with_queue_size(2);
+    let server_address = server.get_local_address().unwrap();
+    let server_handler = server.run().await.unwrap();
+
+    let (reader, mut writer) = tokio::net::TcpStream::connect(server_address).await.unwrap().into_split();
+    let mut playing_lines = tokio::io::BufReader::new(reader).lines();
+    let response = test_client_request(&mut playing_lines, &mut writer, &ClientRequest::GetId).await;
+    assert!(matches!(response, ClientResponse::GetId { .. }));
+
+    let mut queued = vec![];
+    for expected_position in 1..=2 {
+        let (reader, writer) = tokio::net::TcpStream::connect(server_address).await.unwrap().into_split();
+        let mut lines = tokio::io::BufReader::new(reader).lines();
+        assert!(matches!(read_response(&mut lines).await, ClientResponse::Queued { position } if position == expected_position));
+        queued.push((lines, writer));
+    }
+
+    // Queue is full
+    let (reader, _writer) = tokio::net::TcpStream::connect(server_address).await.unwrap().into_split();
+    let mut lines = tokio::io::BufReader::new(reader).lines();
+    assert!(matches!(read_response(&mut lines).await, ClientResponse::ConnectionRejected { .. }));
+
+    // Playing client leaves, first in queue enters and second moves forward
+    drop((playing_lines, writer));
+    let (first_lines, first_writer) = &mut queued[0];
+    assert!(matches!(read_response(first_lines).await, ClientResponse::Queued { position: 0 }));
+    let response = test_client_request(first_lines, first_writer, &ClientRequest::GetId).await;
+    assert!(matches!(response, ClientResponse::GetId { .. }));
+    let (second_lines, _) = &mut queued[1];
+    assert!(matches!(read_response(second_lines).await, ClientResponse::Queued { position: 1 }));
+
+    // Client still waiting in queue is told about shutdown too
+    server_handler.shutdown().await.unwrap();
+    let (second_lines, _) = &mut queued[1];
+    assert!(matches!(read_response(second_lines).await, ClientResponse::ServerShutdown { .. }));
+    assert!(second_lines.next_line().await.unwrap().is_none(), "Connection should be closed");
+}
+
+#[tokio::test]
+async fn test_server_rejects_banned_address() {
+    use tokio::io::AsyncBufReadExt;
+
+    let server = MultiplayerServer::bind_any_local().await.unwrap()
+        .with_ban_list(BanList::from_toml_str("ips = [\"127.0.0.1\"]").unwrap());
+    let server_address = server.get_local_address().unwrap();
+    let server_handler = server.run().await.unwrap();
+
+    let socket = tokio::net::TcpStream::connect(server_address).await.unwrap();
+    let mut lines = tokio::io::BufReader::new(socket).lines();
+    let rejected = lines.next_line().await.unwrap().unwrap();
+    assert!(matches!(
+        serde_json::from_str(&rejected).unwrap(), 
+        ClientResponse::ConnectionRejected { reason: crate::admission::RejectionReason::Banned }
+    ));
+    // Checked before player was spawned
+    assert_eq!(server_handler.world.execute(|world| world.iter_entities().count()).await.unwrap(), 0);
+
+    server_handler.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_server_closes_idle_rooms() {
+    let server = MultiplayerServer::bind_any_local().await.unwrap()
+        .with_room_idle_timeout(Duration::ZERO);
+    let server_handler = server.run().await.unwrap();
+    server_handler.rooms.lock().unwrap().create_room("Arena").unwrap();
+
+    // Created room nobody joined is gone after the next check, main room stays
+    tokio::time::sleep(MultiplayerServer::IDLE_ROOM_CHECK_INTERVAL * 2).await;
+    let rooms = server_handler.rooms.lock().unwrap().list();
+    assert_eq!(rooms.iter().map(|room| room.id).collect::<Vec<_>>(), vec![RoomRegistry::MAIN_ROOM_ID]);
+
+    server_handler.shutdown().await.unwrap();
+}
@@ -0,0 +1,180 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    client_requests::GroundItemData,
+    game::{common::Vector2F, item::{Inventory, ItemStack}, world::{Entity, World, WorldError}},
+    rooms::{RoomId, RoomInfo}
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum PersistenceError {
+    #[error("IoError, reason='{0}'")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Could not serialize state, reason='{0}'")]
+    SerializationError(#[from] serde_json::Error),
+}
+
+/// NPC as saved on disk, unlike the wire format it keeps what is needed to recreate it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedNpc {
+    pub name: String,
+    pub position: Vector2F,
+    pub size: Vector2F,
+    pub color: [u8; 3],
+    pub sprite: String,
+    pub spawnpoint: Vector2F,
+    pub health: u32,
+    pub inventory: Inventory,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RoomState {
+    pub room_id: RoomId,
+    pub name: String,
+    pub npcs: Vec<SavedNpc>,
+    pub items: Vec<GroundItemData>,
+}
+
+/// State of all rooms, saved when server shuts down and loaded on the next start
+#[derive(Serialize, Deserialize, Default)]
+pub struct ServerState {
+    pub rooms: Vec<RoomState>,
+}
+
+impl SavedNpc {
+    pub fn new(entity: &Entity) -> Self {
+        Self {
+            name: entity.name.clone(),
+            position: entity.position,
+            size: entity.size,
+            color: entity.color,
+            sprite: entity.sprite.clone(),
+            spawnpoint: entity.spawnpoint(),
+            health: entity.health(),
+            inventory: entity.inventory().clone(),
+        }
+    }
+}
+
+impl RoomState {
+    /// Players are not saved, they spawn again once they reconnect
+    pub fn new(room_info: &RoomInfo, world: &World) -> Self {
+        Self {
+            room_id: room_info.id,
+            name: room_info.name.clone(),
+            npcs: world.iter_entities().filter(|entity| !entity.is_player()).map(SavedNpc::new).collect(),
+            items: GroundItemData::vec_from_iter(world.iter_ground_items(), world.get_item_definitions()),
+        }
+    }
+
+    /// NPCs and ground items are put back. Returns number of restored NPCs.
+    pub fn restore(&self, world: &mut World) -> Result<usize, WorldError> {
+        for saved in &self.npcs {
+            // Dead NPC comes back at its spawnpoint with full health, as it would after respawn
+            let position = if saved.health > 0 { saved.position } else { saved.spawnpoint };
+            let npc_id = world.create_entity_npc(&saved.name, position, saved.size)?;
+            let npc = world.get_entity_by_id_mut(npc_id).ok_or(WorldError::EntityNotExist)?;
+            npc.color = saved.color;
+            npc.sprite = saved.sprite.clone();
+            npc.set_spawnpoint(saved.spawnpoint);
+            npc.set_inventory(saved.inventory.clone());
+            if saved.health > 0 {
+                npc.set_health(saved.health);
+            }
+        }
+        for saved in &self.items {
+            world.put_ground_item(saved.position, ItemStack { item_id: saved.item.item_id, count: saved.item.count });
+        }
+        Ok(self.npcs.len())
+    }
+}
+
+impl ServerState {
+    /// Written to temporary file first, so previous state survives failed save
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), PersistenceError> {
+        let path = path.as_ref();
+        let temporary_path = path.with_extension("tmp");
+        std::fs::write(&temporary_path, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(&temporary_path, path)?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, PersistenceError> {
+        let json = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    /// None when nothing was saved yet, e.g. on the first start
+    pub fn load_if_exists<P: AsRef<Path>>(path: P) -> Result<Option<Self>, PersistenceError> {
+        match Self::load(path) {
+            Ok(state) => Ok(Some(state)),
+            Err(PersistenceError::IoError(e)) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[test]
+fn test_persistence_save_and_load() {
+    let mut world = World::new();
+    world.create_entity_npc("Tuna", Vector2F::new(5.0, 10.0), Vector2F::new(4.8, 4.8)).unwrap();
+    let room_info = RoomInfo { id: 3, name: "Arena".to_string(), players: 0 };
+    let state = ServerState { rooms: vec![RoomState::new(&room_info, &world)] };
+
+    let path = std::env::temp_dir().join(format!("snippets_state_{}.json", std::process::id()));
+    state.save(&path).unwrap();
+    let loaded = ServerState::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded.rooms.len(), 1);
+    assert_eq!(loaded.rooms[0].room_id, 3);
+    assert_eq!(loaded.rooms[0].name, "Arena");
+    assert_eq!(loaded.rooms[0].npcs[0].name, "Tuna");
+}
+
+#[test]
+fn test_persistence_restore_skips_players() {
+    let mut world = World::new();
+    world.create_entity_player("Player", Vector2F::new(0.0, 0.0), Vector2F::new(4.8, 4.8)).unwrap();
+    let npc_id = world.create_entity_npc("Tuna", Vector2F::new(20.0, 10.0), Vector2F::new(4.8, 4.8)).unwrap();
+    world.get_entity_by_id_mut(npc_id).unwrap().sprite = "slime".to_string();
+    world.put_ground_item(Vector2F::new(30.0, 30.0), ItemStack { item_id: 1, count: 2 });
+    let room_info = RoomInfo { id: 0, name: "main".to_string(), players: 1 };
+    let state = RoomState::new(&room_info, &world);
+
+    let mut restored = World::new();
+    assert_eq!(state.restore(&mut restored).unwrap(), 1);
+    let npc = restored.iter_entities().next().unwrap();
+    assert_eq!((npc.name.as_str(), npc.position), ("Tuna", Vector2F::new(20.0, 10.0)));
+    assert_eq!(npc.sprite, "slime");
+    assert_eq!(restored.iter_entities().count(), 1);
+    let item = restored.iter_ground_items().next().unwrap();
+    assert_eq!((item.position, item.stack.count), (Vector2F::new(30.0, 30.0), 2));
+}
+
+#[test]
+fn test_persistence_npc_round_trips_spawnpoint_health_and_inventory() {
+    let mut world = World::new();
+    let spawnpoint = Vector2F::new(20.0, 10.0);
+    let npc_id = world.create_entity_npc("Tuna", spawnpoint, Vector2F::new(4.8, 4.8)).unwrap();
+    let mut inventory = Inventory::default();
+    inventory.add(ItemStack { item_id: 1, count: 3 }, world.get_item_definitions());
+    let npc = world.get_entity_by_id_mut(npc_id).unwrap();
+    npc.position = Vector2F::new(25.0, 10.0);
+    npc.set_health(7);
+    npc.set_inventory(inventory);
+    let room_info = RoomInfo { id: 0, name: "main".to_string(), players: 0 };
+    let json = serde_json::to_string(&ServerState { rooms: vec![RoomState::new(&room_info, &world)] }).unwrap();
+    let state: ServerState = serde_json::from_str(&json).unwrap();
+
+    let mut restored = World::new();
+    state.rooms[0].restore(&mut restored).unwrap();
+    let npc = restored.iter_entities().next().unwrap();
+    assert_eq!(npc.position, Vector2F::new(25.0, 10.0));
+    assert_eq!(npc.spawnpoint(), spawnpoint);
+    assert_eq!(npc.health(), 7);
+    assert_eq!(npc.inventory().count(1), 3);
+}
@@ -0,0 +1,150 @@
+use std::time::Duration;
+
+use crate::game::common::Vector2F;
+
+/// View into the world. Follows target with damping, unless moved away by panning.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Camera {
+    /// World position in the center of viewport
+    pub position: Vector2F,
+    /// NDC per world unit, vertically
+    pub scale: f32,
+    /// Followed position, usually the player
+    target: Option<Vector2F>,
+    /// Set by panning, camera stays in place until re-centered
+    free_look: bool,
+}
+
+impl Camera {
+    pub const DEFAULT_SCALE: f32 = 0.5;
+    pub const MIN_SCALE: f32 = 0.005;
+    pub const MAX_SCALE: f32 = 5.0;
+    /// Zoom change per mouse wheel line
+    const LINE_ZOOM_STEP: f32 = 0.1;
+    /// Touchpad pixels counted as one wheel line
+    const PIXELS_PER_LINE: f32 = 40.0;
+    /// How fast camera catches up with target, per second. Higher is stiffer.
+    const FOLLOW_RATE: f32 = 8.0;
+
+    pub fn new(position: Vector2F, scale: f32) -> Self {
+        Self {
+            position,
+            scale: scale.clamp(Self::MIN_SCALE, Self::MAX_SCALE),
+            target: None,
+            free_look: false,
+        }
+    }
+
+    pub fn is_free_look(&self) -> bool {
+        self.free_look
+    }
+
+    /// Multiplies scale, result stays within zoom limits
+    pub fn zoom(&mut self, factor: f32) {
+        self.scale = (self.scale * factor).clamp(Self::MIN_SCALE, Self::MAX_SCALE);
+    }
+
+    /// Mouse wheel, positive zooms in
+    pub fn zoom_lines(&mut self, lines: f32) {
+        self.zoom((1.0 + Self::LINE_ZOOM_STEP).powf(lines));
+    }
+
+    /// Touchpad scroll, positive zooms in
+    pub fn zoom_pixels(&mut self, pixels: f32) {
+        self.zoom_lines(pixels / Self::PIXELS_PER_LINE);
+    }
+
+    /// Camera jumps to the first target, later ones are followed in `update`
+    pub fn set_target(&mut self, target: Vector2F) {
+        if self.target.is_none() && !self.free_look {
+            self.position = target;
+        }
+        self.target = Some(target);
+    }
+
+    /// Moves towards target, exponential damping keeps the motion independent of frame rate
+    pub fn update(&mut self, frame_time: Duration) {
+        let Some(target) = self.target.filter(|_| !self.free_look) else {
+            return;
+        };
+        let factor = 1.0 - (-Self::FOLLOW_RATE * frame_time.as_secs_f32()).exp();
+        self.position += (target - self.position) * factor;
+    }
+
+    /// World under the cursor moves with it, `screen_delta` in pixels with y pointing down
+    pub fn pan(&mut self, screen_delta: Vector2F, viewport: (u32, u32)) {
+        let (viewport_width, viewport_height) = (viewport.0.max(1) as f32, viewport.1.max(1) as f32);
+        let aspect_ratio = viewport_width / viewport_height;
+        self.position.x -= screen_delta.x / viewport_width * 2.0 * aspect_ratio / self.scale;
+        self.position.y += screen_delta.y / viewport_height * 2.0 / self.scale;
+        self.free_look = true;
+    }
+
+    /// Ends free look, camera returns to target
+    pub fn recenter(&mut self) {
+        self.free_look = false;
+    }
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new(Vector2F::zero(), Self::DEFAULT_SCALE)
+    }
+}
+
+#[test]
+fn test_camera_zoom_is_clamped() {
+    let mut camera = Camera::default();
+    camera.zoom_lines(1.0);
+    assert!((camera.scale - Camera::DEFAULT_SCALE * 1.1).abs() < 1e-6);
+
+    // Touchpad sends many small deltas, together they zoom like wheel lines
+    let mut touchpad = Camera::default();
+    (0..4).for_each(|_| touchpad.zoom_pixels(10.0));
+    assert!((touchpad.scale - camera.scale).abs() < 1e-6);
+
+    camera.zoom_lines(1000.0);
+    assert_eq!(camera.scale, Camera::MAX_SCALE);
+    camera.zoom_pixels(-1e6);
+    assert_eq!(camera.scale, Camera::MIN_SCALE);
+}
+
+#[test]
+fn test_camera_follow_is_damped() {
+    let mut camera = Camera::default();
+    camera.set_target(Vector2F::new(10.0, 0.0));
+    assert_eq!(camera.position, Vector2F::new(10.0, 0.0));
+
+    camera.set_target(Vector2F::new(20.0, 0.0));
+    camera.update(Duration::from_millis(16));
+    assert!(camera.position.x > 10.0 && camera.position.x < 20.0);
+
+    // Same time split into more frames ends in the same place
+    let mut fine = camera.clone();
+    camera.update(Duration::from_millis(32));
+    (0..4).for_each(|_| fine.update(Duration::from_millis(8)));
+    assert!((camera.position.x - fine.position.x).abs() < 1e-3);
+
+    camera.update(Duration::from_secs(5));
+    assert!((camera.position.x - 20.0).abs() < 1e-3);
+}
+
+#[test]
+fn test_camera_pan_and_recenter() {
+    let mut camera = Camera::new(Vector2F::zero(), 0.1);
+    camera.set_target(Vector2F::zero());
+
+    // Dragging by half of viewport height moves view by half of visible world
+    camera.pan(Vector2F::new(0.0, 50.0), (200, 100));
+    assert!(camera.is_free_look());
+    assert!((camera.position.y - 10.0).abs() < 1e-4);
+    camera.pan(Vector2F::new(100.0, 0.0), (200, 100));
+    assert!((camera.position.x + 20.0).abs() < 1e-4);
+
+    // Target is ignored until re-centered
+    camera.update(Duration::from_secs(5));
+    assert!((camera.position.y - 10.0).abs() < 1e-4);
+    camera.recenter();
+    camera.update(Duration::from_secs(5));
+    assert!(camera.position.length() < 1e-3);
+}
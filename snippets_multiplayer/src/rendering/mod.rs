@@ -1,19 +1,288 @@
-use crate::game::common::Vector2F;
+use std::{collections::{HashMap, VecDeque}, time::Duration};
 
+use crate::{
+    client_requests::{ChunkData, EntityCheckData, GroundItemData, MoveDirection, SpriteData, SpriteId},
+    game::{
+        chunk::{Chunk, ChunkCoord, Terrain},
+        common::{Rect2F, Vector2F},
+        world::EntityId
+    }
+};
+
+use camera::Camera;
+
+pub mod camera;
 pub mod renderer;
+pub mod scene;
+pub mod software;
+pub mod sprites;
+pub mod terminal;
+pub mod text;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Default, Clone)]
 pub struct EntityView {
     pub position: Vector2F,
     pub size: Vector2F,
-    pub color: [f32; 3]
+    pub color: [f32; 3],
+    /// Health points as sent by server
+    pub health: u32,
+    pub max_health: u32,
+    pub id: EntityId,
+    pub name: String,
+    pub is_npc: bool,
+    /// Drawn as solid rect if not set or missing in sprite sheet
+    pub sprite: Option<String>,
+    pub facing: MoveDirection,
+    pub is_moving: bool,
+}
+
+/// Item stack lying on the ground, drawn below entities
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemView {
+    pub position: Vector2F,
+    pub name: String,
+    pub count: u32,
 }
 
+impl EntityView {
+    /// Fraction of max health in range 0..=1
+    pub fn health_fraction(&self) -> f32 {
+        (self.health as f32 / self.max_health.max(1) as f32).min(1.0)
+    }
+}
 
+impl ItemView {
+    pub const SIZE: Vector2F = Vector2F { x: 2.0, y: 2.0 };
+    pub const COLOR: [f32; 3] = [0.9, 0.75, 0.2];
+    pub const SPRITE: &str = "item";
+    /// Item is centered on its tile
+    const TILE_OFFSET: Vector2F = Vector2F { x: 1.5, y: 1.5 };
+}
+
+/// Parts of the scene which can be switched off, everything is drawn by default
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RenderLayers {
+    /// Terrain tiles and grid lines
+    pub terrain: bool,
+    /// Entities and items without sprite are drawn as solid rects
+    pub sprites: bool,
+    /// Entity names above them
+    pub names: bool,
+    pub minimap: bool,
+}
+
+impl Default for RenderLayers {
+    fn default() -> Self {
+        Self {
+            terrain: true,
+            sprites: true,
+            names: true,
+            minimap: true,
+        }
+    }
+}
+
+/// Backend drawing `AppData` into its target, window surface or image
+pub trait Renderer {
+    fn resize(&mut self, width: u32, height: u32);
+    fn render(&mut self, app_data: &AppData);
+}
 
 #[derive(Default)]
 pub struct AppData {
     pub entities: Vec<EntityView>,
-    pub camera_position: Vector2F,
-    pub scale: f32,
-}
\ No newline at end of file
+    pub items: Vec<ItemView>,
+    pub player_id: Option<EntityId>,
+    pub camera: Camera,
+    pub fps: f32,
+    /// Round trip of last `WorldCheck`
+    pub ping: Option<Duration>,
+    /// Newest line at the back
+    pub chat_log: VecDeque<String>,
+    /// Idle entities keep facing direction of their last movement
+    pub facings: HashMap<EntityId, MoveDirection>,
+    /// Drives sprite animations
+    pub animation_time: Duration,
+    /// Terrain of chunks streamed by server, row by row like `Chunk::terrain`
+    pub terrain: HashMap<ChunkCoord, Vec<Terrain>>,
+    /// Server sends each name once, entities refer to it by id
+    pub sprite_names: HashMap<SpriteId, String>,
+    /// Entity picked with mouse, cleared when it disappears
+    pub selected: Option<EntityId>,
+    /// Chat message being typed, shown below chat log
+    pub chat_draft: Option<String>,
+    /// HUD with FPS, ping and position is toggled by debug overlay key
+    pub hide_debug_overlay: bool,
+    pub layers: RenderLayers,
+}
+
+impl AppData {
+    pub const CHAT_LOG_LENGTH: usize = 8;
+
+    /// Oldest line is dropped when log is full
+    pub fn push_chat(&mut self, line: String) {
+        if self.chat_log.len() == Self::CHAT_LOG_LENGTH {
+            self.chat_log.pop_front();
+        }
+        self.chat_log.push_back(line);
+    }
+
+    pub fn player_position(&self) -> Option<Vector2F> {
+        let player_id = self.player_id?;
+        self.entities.iter()
+            .find(|entity| entity.id == player_id)
+            .map(|entity| entity.position)
+    }
+
+    /// Replaces views with `WorldCheck` content, camera targets the player
+    pub fn update_world(&mut self, entities: Vec<EntityCheckData>, items: Vec<GroundItemData>) {
+        self.entities.clear();
+        // Entities which left are forgotten
+        let previous_facings = std::mem::take(&mut self.facings);
+
+        self.items = items.into_iter()
+            .map(|ground_item| ItemView {
+                position: ground_item.position + ItemView::TILE_OFFSET,
+                name: ground_item.item.name,
+                count: ground_item.item.count,
+            })
+            .collect();
+
+        for entity in entities {
+            // Dead entities wait for respawn
+            if entity.health == 0 {
+                continue;
+            }
+
+            if Some(entity.id) == self.player_id {
+                self.camera.set_target(entity.position);
+            }
+
+            let facing = entity.facing
+                .or_else(|| previous_facings.get(&entity.id).copied())
+                .unwrap_or_default();
+            self.facings.insert(entity.id, facing);
+
+            let color = [
+                entity.color[0] as f32 / 255.0,
+                entity.color[1] as f32 / 255.0,
+                entity.color[2] as f32 / 255.0
+            ];
+
+            self.entities.push(EntityView { 
+                position: entity.position, 
+                size: entity.size, 
+                color,
+                health: entity.health,
+                max_health: entity.max_health,
+                id: entity.id,
+                name: entity.name,
+                is_npc: entity.is_npc,
+                sprite: entity.sprite.and_then(|id| self.sprite_names.get(&id).cloned()),
+                facing,
+                is_moving: entity.facing.is_some(),
+            });
+        }
+
+        if self.selected.is_some_and(|selected| !self.entities.iter().any(|entity| entity.id == selected)) {
+            self.selected = None;
+        }
+    }
+
+    /// Has to be called before `update_world` with sprites of the same `WorldCheck`
+    pub fn update_sprites(&mut self, sprites: Vec<SpriteData>) {
+        self.sprite_names.extend(sprites.into_iter().map(|sprite| (sprite.id, sprite.name)));
+    }
+
+    /// Keeps terrain of chunks near the player, so it does not have to be sent again
+    pub fn update_terrain(&mut self, chunks: Vec<ChunkData>, unloaded_chunks: Vec<ChunkCoord>) {
+        for coord in unloaded_chunks {
+            self.terrain.remove(&coord);
+        }
+        for chunk in chunks {
+            if chunk.terrain.len() == (Chunk::SIZE_TILES * Chunk::SIZE_TILES) as usize {
+                self.terrain.insert(chunk.coord, chunk.terrain);
+            } else {
+                log::warn!("Ignoring chunk {} with {} tiles", chunk.coord, chunk.terrain.len());
+            }
+        }
+    }
+
+    /// World area covered by the viewport
+    pub fn visible_area(&self, aspect_ratio: f32) -> Rect2F {
+        let min = self.ndc_to_world(Vector2F::new(-1.0, -1.0), aspect_ratio);
+        let max = self.ndc_to_world(Vector2F::new(1.0, 1.0), aspect_ratio);
+        Rect2F::new(min.x, min.y, max.x - min.x, max.y - min.y)
+    }
+
+    /// Normalized device coordinates, visible area is -1..1 on both axes, y points up
+    pub fn world_to_ndc(&self, position: Vector2F, aspect_ratio: f32) -> Vector2F {
+        Vector2F::new(
+            (position.x - self.camera.position.x) * self.camera.scale / aspect_ratio,
+            (position.y - self.camera.position.y) * self.camera.scale
+        )
+    }
+
+    pub fn ndc_to_world(&self, ndc: Vector2F, aspect_ratio: f32) -> Vector2F {
+        Vector2F::new(
+            ndc.x * aspect_ratio / self.camera.scale + self.camera.position.x,
+            ndc.y / self.camera.scale + self.camera.position.y
+        )
+    }
+
+    /// `screen_position` in pixels from top-left corner of viewport, like cursor position
+    pub fn screen_to_world(&self, screen_position: Vector2F, viewport: (u32, u32)) -> Vector2F {
+        let (viewport_width, viewport_height) = (viewport.0.max(1) as f32, viewport.1.max(1) as f32);
+        let ndc = Vector2F::new(
+            screen_position.x / viewport_width * 2.0 - 1.0,
+            1.0 - screen_position.y / viewport_height * 2.0
+        );
+        self.ndc_to_world(ndc, viewport_width / viewport_height)
+    }
+
+    /// Topmost entity covering the point, items on the ground are not pickable
+    pub fn entity_at(&self, world_position: Vector2F) -> Option<EntityId> {
+        self.entities.iter().rev()
+            .find(|entity| Rect2F { pos: entity.position, size: entity.size }.contains(&world_position))
+            .map(|entity| entity.id)
+    }
+}
+
+#[test]
+fn test_app_data_screen_to_world() {
+    let app_data = AppData { camera: Camera::new(Vector2F::new(100.0, 50.0), 0.1), ..Default::default() };
+    let viewport = (400, 200);
+    let assert_close = |actual: Vector2F, expected: Vector2F| assert!((actual - expected).length() < 1e-4, "{actual} != {expected}");
+
+    // Center of viewport is the camera, y points down on screen and up in the world
+    assert_close(app_data.screen_to_world(Vector2F::new(200.0, 100.0), viewport), Vector2F::new(100.0, 50.0));
+    assert_close(app_data.screen_to_world(Vector2F::new(0.0, 0.0), viewport), Vector2F::new(80.0, 60.0));
+    assert_close(app_data.screen_to_world(Vector2F::new(400.0, 200.0), viewport), Vector2F::new(120.0, 40.0));
+
+    // Inverse of projection used for drawing
+    let world = Vector2F::new(93.0, 57.5);
+    let ndc = app_data.world_to_ndc(world, 2.0);
+    let screen = Vector2F::new((ndc.x + 1.0) / 2.0 * 400.0, (1.0 - ndc.y) / 2.0 * 200.0);
+    assert_close(app_data.screen_to_world(screen, viewport), world);
+}
+
+#[test]
+fn test_app_data_entity_at() {
+    let mut app_data = AppData { camera: Camera::new(Vector2F::zero(), 0.1), ..Default::default() };
+    app_data.items.push(ItemView { position: Vector2F::new(-2.0, 0.0), name: "Coin".to_string(), count: 1 });
+    app_data.entities.push(EntityView { position: Vector2F::new(0.0, 0.0), size: Vector2F::new(5.0, 5.0), id: 1, ..Default::default() });
+    app_data.entities.push(EntityView { position: Vector2F::new(4.0, 4.0), size: Vector2F::new(5.0, 5.0), id: 2, ..Default::default() });
+
+    assert_eq!(app_data.entity_at(Vector2F::new(1.0, 1.0)), Some(1));
+    // Overlapping entities, the one drawn last wins
+    assert_eq!(app_data.entity_at(Vector2F::new(4.5, 4.5)), Some(2));
+    assert_eq!(app_data.entity_at(Vector2F::new(-1.0, 1.0)), None);
+}
+
+#[test]
+fn test_entity_view_health_fraction_is_clamped() {
+    let entity = |health, max_health| EntityView { health, max_health, ..Default::default() };
+    assert_eq!(entity(5, 10).health_fraction(), 0.5);
+    assert_eq!(entity(15, 10).health_fraction(), 1.0);
+    assert_eq!(entity(0, 0).health_fraction(), 0.0);
+}
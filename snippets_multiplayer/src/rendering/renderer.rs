@@ -1,6 +1,6 @@
 use std::{
     borrow::Cow, 
-    sync::{Arc, Mutex}
+    sync::Arc
 };
 
 use bytemuck::{
@@ -13,45 +13,119 @@ use wgpu::{
 };
 use winit::window::Window;
 
-use crate::game::common::Vector2F;
-
-use super::{AppData, EntityView};
+use super::{
+    scene::{self, Quad},
+    sprites::SpriteSheet,
+    text::{GlyphAtlas, GlyphQuad},
+    AppData, Renderer
+};
 
+/// Corner of unit quad, scaled and moved by instance
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable)]
 struct Vertex {
-    _pos: [f32; 4],
+    _pos: [f32; 2],
 }
 
-impl Vertex {
-    fn from_position(x: f32, y: f32) -> Self {
-        Vertex{
-            _pos: [x, y, 1.0, 1.0]
-        }
-    }
+/// One entity, health bar or panel, NDC rect with color. Zero `uv_size` means no sprite.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Instance {
+    position: [f32; 2],
+    size: [f32; 2],
+    color: [f32; 4],
+    uv_position: [f32; 2],
+    uv_size: [f32; 2],
 }
 
-// Define the uniform structure (color as vec4)
+/// Glyph rect in NDC with its place in atlas
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable)]
-struct Uniforms {
+struct GlyphInstance {
+    position: [f32; 2],
+    size: [f32; 2],
+    uv_position: [f32; 2],
+    uv_size: [f32; 2],
     color: [f32; 4],
 }
 
-fn create_ndc_rect_quad_vertices(x: f32, y: f32, w: f32, h: f32) -> (Vec<Vertex>, Vec<u16>) {
-    let vertex_data = vec![
-        Vertex::from_position(x, y),           // Bottom-left
-        Vertex::from_position(x + w, y),       // Bottom-right
-        Vertex::from_position(x + w, y + h),   // Top-right
-        Vertex::from_position(x, y + h),       // Top-left
-    ];
-    
-    let indices_data = vec![
-        0, 1, 2, // First triangle
-        2, 3, 0, // Second triangle
-    ];
+const UNIT_QUAD_VERTICES: [Vertex; 4] = [
+    Vertex { _pos: [0.0, 0.0] }, // Bottom-left
+    Vertex { _pos: [1.0, 0.0] }, // Bottom-right
+    Vertex { _pos: [1.0, 1.0] }, // Top-right
+    Vertex { _pos: [0.0, 1.0] }, // Top-left
+];
+
+const UNIT_QUAD_INDICES: [u16; 6] = [
+    0, 1, 2, // First triangle
+    2, 3, 0, // Second triangle
+];
+
+/// Instance buffer grows in powers of two, so it is not recreated every frame
+const MIN_INSTANCE_CAPACITY: usize = 1024;
+
+impl From<&Quad> for Instance {
+    fn from(quad: &Quad) -> Self {
+        Self {
+            position: [quad.position.x, quad.position.y],
+            size: [quad.size.x, quad.size.y],
+            color: quad.color,
+            uv_position: quad.sprite.map_or([0.0; 2], |sprite| sprite.uv_position),
+            uv_size: quad.sprite.map_or([0.0; 2], |sprite| sprite.uv_size),
+        }
+    }
+}
+
+impl From<&GlyphQuad> for GlyphInstance {
+    fn from(glyph: &GlyphQuad) -> Self {
+        Self {
+            position: [glyph.position.x, glyph.position.y],
+            size: [glyph.size.x, glyph.size.y],
+            uv_position: glyph.uv_position,
+            uv_size: glyph.uv_size,
+            color: glyph.color,
+        }
+    }
+}
+
+fn instance_capacity(instances: usize) -> usize {
+    instances.max(MIN_INSTANCE_CAPACITY).next_power_of_two()
+}
+
+/// Vertex buffer stepped per instance, rewritten every frame
+struct InstanceBuffer {
+    label: &'static str,
+    buffer: wgpu::Buffer,
+    capacity: usize,
+}
+
+impl InstanceBuffer {
+    fn new<T: Pod>(device: &Device, label: &'static str) -> Self {
+        let capacity = instance_capacity(0);
+        Self {
+            label,
+            buffer: Self::create_buffer::<T>(device, label, capacity),
+            capacity,
+        }
+    }
+
+    fn create_buffer<T: Pod>(device: &Device, label: &'static str, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: (capacity * std::mem::size_of::<T>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
 
-    (vertex_data, indices_data)
+    /// Uploads all instances at once, buffer is recreated only when it is too small
+    fn write<T: Pod>(&mut self, device: &Device, queue: &wgpu::Queue, instances: &[T]) {
+        if instances.len() > self.capacity {
+            self.capacity = instance_capacity(instances.len());
+            self.buffer = Self::create_buffer::<T>(device, self.label, self.capacity);
+        }
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(instances));
+    }
 }
 
 pub struct State {
@@ -62,11 +136,21 @@ pub struct State {
     surface: wgpu::Surface<'static>,
     surface_format: wgpu::TextureFormat,
     render_pipeline: RenderPipeline,
-    uniform_bind_group_layout: wgpu::BindGroupLayout,
+    text_pipeline: RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    tile_buffer: InstanceBuffer,
+    quad_buffer: InstanceBuffer,
+    glyph_buffer: InstanceBuffer,
+    minimap_buffer: InstanceBuffer,
+    atlas: GlyphAtlas,
+    atlas_bind_group: wgpu::BindGroup,
+    sprites: SpriteSheet,
+    sprite_bind_group: wgpu::BindGroup,
 }
 
 impl State {
-    pub async fn new(window: Arc<Window>) -> State {
+    pub async fn new(window: Arc<Window>, sprites: SpriteSheet) -> State {
         let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
 
         let adapter = instance
@@ -88,29 +172,59 @@ impl State {
         let cap = surface.get_capabilities(&adapter);
         let surface_format = cap.formats[0];
         
-        // Create uniform bind group layout.
-        let uniform_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("Uniform Bind Group Layout"),
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }],
-            });
-        
+        let texture_bind_group_layout = Self::prepare_texture_bind_group_layout(&device);
         let render_pipeline = Self::prepare_pipeline(
             &device,
             &surface,
             &adapter,
-            &uniform_bind_group_layout
+            &texture_bind_group_layout
+        );
+        let text_pipeline = Self::prepare_text_pipeline(
+            &device,
+            &surface,
+            &adapter,
+            &texture_bind_group_layout
         );
 
+        let sprite_bind_group = Self::prepare_texture_bind_group(
+            &device,
+            &queue,
+            &texture_bind_group_layout,
+            "Sprite Texture",
+            (sprites.width(), sprites.height()),
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            sprites.pixels()
+        );
+
+        let atlas = GlyphAtlas::new().expect("Embedded font should load");
+        let atlas_bind_group = Self::prepare_texture_bind_group(
+            &device,
+            &queue,
+            &texture_bind_group_layout,
+            "Glyph Atlas Texture",
+            (atlas.width(), atlas.height()),
+            wgpu::TextureFormat::R8Unorm,
+            atlas.coverage()
+        );
+
+        // Shared by all instances, created once
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Unit Quad Vertex Buffer"),
+            contents: bytemuck::cast_slice(&UNIT_QUAD_VERTICES),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Unit Quad Index Buffer"),
+            contents: bytemuck::cast_slice(&UNIT_QUAD_INDICES),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        let tile_buffer = InstanceBuffer::new::<Instance>(&device, "Tile Instance Buffer");
+        let quad_buffer = InstanceBuffer::new::<Instance>(&device, "Quad Instance Buffer");
+        let glyph_buffer = InstanceBuffer::new::<GlyphInstance>(&device, "Glyph Instance Buffer");
+        let minimap_buffer = InstanceBuffer::new::<Instance>(&device, "Minimap Instance Buffer");
+
         let state = State {
             window,
             device,
@@ -119,7 +233,17 @@ impl State {
             surface,
             surface_format,
             render_pipeline,
-            uniform_bind_group_layout
+            text_pipeline,
+            vertex_buffer,
+            index_buffer,
+            tile_buffer,
+            quad_buffer,
+            glyph_buffer,
+            minimap_buffer,
+            sprites,
+            sprite_bind_group,
+            atlas,
+            atlas_bind_group
         };
 
         // Configure surface for the first time
@@ -132,7 +256,7 @@ impl State {
         device: &Device,
         surface: &Surface, 
         adapter: &wgpu::Adapter,
-        uniform_bind_group_layout: &wgpu::BindGroupLayout,
+        texture_bind_group_layout: &wgpu::BindGroupLayout,
     ) -> RenderPipeline {
         // Load the shaders from disk
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
@@ -142,7 +266,7 @@ impl State {
 
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Rect Pipeline Layout"),
-            bind_group_layouts: &[uniform_bind_group_layout],
+            bind_group_layouts: &[texture_bind_group_layout],
             push_constant_ranges: &[],
         });
 
@@ -155,11 +279,18 @@ impl State {
                 wgpu::VertexAttribute {
                     offset: 0,
                     shader_location: 0,
-                    format: wgpu::VertexFormat::Float32x4,
+                    format: wgpu::VertexFormat::Float32x2,
                 },
             ],
         };
 
+        // Instance attributes advance once per drawn quad.
+        let instance_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Instance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &wgpu::vertex_attr_array![1 => Float32x2, 2 => Float32x2, 3 => Float32x4, 4 => Float32x2, 5 => Float32x2],
+        };
+
         let swapchain_capabilities = surface.get_capabilities(adapter);
         let swapchain_format = swapchain_capabilities.formats[0];
     
@@ -169,14 +300,152 @@ impl State {
             vertex: wgpu::VertexState {
                 module: &shader,
                 entry_point: Some("vs_main"),
-                buffers: &[vertex_layout],
+                buffers: &[vertex_layout, instance_layout],
                 compilation_options: Default::default(),
             },
             fragment: Some(wgpu::FragmentState {
                 module: &shader,
                 entry_point: Some("fs_main"),
                 compilation_options: Default::default(),
-                targets: &[Some(swapchain_format.into())],
+                // Sprites have transparent background
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: swapchain_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        })
+    }
+
+    /// Shared by sprite and glyph textures, both sampled with nearest filter
+    fn prepare_texture_bind_group_layout(device: &Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Texture Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        })
+    }
+
+    /// Uploads texture once, pixel art and glyphs are drawn without filtering
+    fn prepare_texture_bind_group(
+        device: &Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        label: &str,
+        (width, height): (u32, u32),
+        format: wgpu::TextureFormat,
+        data: &[u8],
+    ) -> wgpu::BindGroup {
+        let texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            data,
+        );
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some(label),
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(label),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        })
+    }
+
+    fn prepare_text_pipeline(
+        device: &Device,
+        surface: &Surface,
+        adapter: &wgpu::Adapter,
+        texture_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> RenderPipeline {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Text Shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("text.wgsl"))),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Text Pipeline Layout"),
+            bind_group_layouts: &[texture_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let vertex_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0 => Float32x2],
+        };
+
+        let instance_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<GlyphInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &wgpu::vertex_attr_array![1 => Float32x2, 2 => Float32x2, 3 => Float32x2, 4 => Float32x2, 5 => Float32x4],
+        };
+
+        let swapchain_format = surface.get_capabilities(adapter).formats[0];
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Text Render Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[vertex_layout, instance_layout],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                // Glyph edges are blended with what is below
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: swapchain_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
             }),
             primitive: wgpu::PrimitiveState::default(),
             depth_stencil: None,
@@ -204,9 +473,11 @@ impl State {
         };
         self.surface.configure(&self.device, &surface_config);
     }
+}
 
-    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
-        self.size = new_size;
+impl Renderer for State {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.size = winit::dpi::PhysicalSize::new(width, height);
 
         // reconfigure the surface
         self.configure_surface();
@@ -214,9 +485,7 @@ impl State {
         // TODO maybe configure pipeline (surface dependant)
     }
 
-    pub fn render(&mut self,
-        app_data: &AppData
-    ) {
+    fn render(&mut self, app_data: &AppData) {
         // Create texture view
         let surface_texture = self.surface.get_current_texture()
             .expect("failed to acquire next swapchain texture");
@@ -228,23 +497,42 @@ impl State {
                 ..Default::default()
             });
 
-        // Renders a GREEN screen
+        let aspect_ratio = self.size.width as f32 / self.size.height as f32;
+        let tiles: Vec<Instance> = scene::build_tiles(app_data, (self.size.width, self.size.height)).iter()
+            .map(Instance::from)
+            .collect();
+        let overlay = scene::build_overlay(app_data, &self.atlas, (self.size.width, self.size.height));
+        // Overlay panels go after world quads, so they are drawn on top
+        let quads: Vec<Instance> = scene::build_quads(app_data, aspect_ratio, &self.sprites).iter()
+            .chain(overlay.panels.iter())
+            .map(Instance::from)
+            .collect();
+        let glyphs: Vec<GlyphInstance> = overlay.glyphs.iter().map(GlyphInstance::from).collect();
+        let minimap = scene::build_minimap(app_data, (self.size.width, self.size.height));
+        let minimap_quads: Vec<Instance> = minimap.iter()
+            .flat_map(|minimap| minimap.quads.iter().map(Instance::from))
+            .collect();
+        self.tile_buffer.write(&self.device, &self.queue, &tiles);
+        self.quad_buffer.write(&self.device, &self.queue, &quads);
+        self.glyph_buffer.write(&self.device, &self.queue, &glyphs);
+        self.minimap_buffer.write(&self.device, &self.queue, &minimap_quads);
+
         let mut encoder = self.device.create_command_encoder(&Default::default());
 
-        // Create the renderpass which will clear the screen.
+        // Background pass clears the screen and draws terrain with grid
         {
-            let mut renderpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
+            let mut tile_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Tile Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
                     view: &texture_view,
                     resolve_target: None,
                     ops: wgpu::Operations {
                         // Clear with a dark gray color.
                         load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.1,
-                            b: 0.1,
-                            a: 1.0
+                            r: scene::CLEAR_COLOR[0] as f64,
+                            g: scene::CLEAR_COLOR[1] as f64,
+                            b: scene::CLEAR_COLOR[2] as f64,
+                            a: scene::CLEAR_COLOR[3] as f64
                         }),
                         store: wgpu::StoreOp::Store,
                     },
@@ -254,73 +542,116 @@ impl State {
                 occlusion_query_set: None,
             });
 
+            if !tiles.is_empty() {
+                tile_pass.set_pipeline(&self.render_pipeline);
+                tile_pass.set_bind_group(0, &self.sprite_bind_group, &[]);
+                tile_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+                tile_pass.set_vertex_buffer(1, self.tile_buffer.buffer.slice(..));
+                tile_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+                tile_pass.draw_indexed(0..UNIT_QUAD_INDICES.len() as u32, 0, 0..tiles.len() as u32);
+            }
+        }
+
+        // Entities and overlay are drawn over the background
+        {
+            let mut renderpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &texture_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
             // renderpass.push_debug_group("Prepare data for draw.");
 
             // If you wanted to call any drawing commands, they would go here.
             renderpass.set_pipeline(&self.render_pipeline);
 
-            let aspect_ratio = self.size.width as f32 / self.size.height as f32;
-            let scale_x = app_data.scale / aspect_ratio;
-            let scale_y = app_data.scale;
-
-            app_data.entities.iter().for_each(|ev| {
-
-                let uniform = Uniforms { color: [ev.color[0], ev.color[1], ev.color[2], 1.0] };
-                let uniform_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: Some("Entity Uniform Buffer"),
-                    contents: bytemuck::cast_slice(&[uniform]),
-                    usage: wgpu::BufferUsages::UNIFORM,
-                });
-        
-                // Create a bind group for this entity's uniform buffer.
-                let entity_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
-                    label: Some("Entity Bind Group"),
-                    layout: &self.uniform_bind_group_layout, // stored during initialization
-                    entries: &[wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: uniform_buffer.as_entire_binding(),
-                    }],
-                });
-
-                // --- Create the rectangle vertex and index buffers ---
-                let (vertices, indices) = create_ndc_rect_quad_vertices(
-                    (ev.position.x - app_data.camera_position.x) * scale_x,
-                    (ev.position.y - app_data.camera_position.y) * scale_y,
-                    ev.size.x * scale_x,
-                    ev.size.y * scale_y
-                );
-
-                let vertex_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: Some("Rect Vertex Buffer"),
-                    contents: bytemuck::cast_slice(&vertices),
-                    usage: wgpu::BufferUsages::VERTEX,
-                });
-
-                let index_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: Some("Rect Index Buffer"),
-                    contents: bytemuck::cast_slice(&indices),
-                    usage: wgpu::BufferUsages::INDEX,
-                });
-
-        
-                // Bind the uniform bind group (group 0).
-                renderpass.set_bind_group(0, &entity_bind_group, &[]);
-
-                renderpass.set_vertex_buffer(0, vertex_buffer.slice(..));
-                renderpass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            renderpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            renderpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
 
+            // All quads in one draw call
+            if !quads.is_empty() {
+                renderpass.set_bind_group(0, &self.sprite_bind_group, &[]);
+                renderpass.set_vertex_buffer(1, self.quad_buffer.buffer.slice(..));
+                renderpass.draw_indexed(0..UNIT_QUAD_INDICES.len() as u32, 0, 0..quads.len() as u32);
+            }
 
-                // Draw the rectangle using 6 indices.
-                renderpass.draw_indexed(0..indices.len() as u32, 0, 0..1);
-            });   
+            // Text on top of everything, also in one draw call
+            if !glyphs.is_empty() {
+                renderpass.set_pipeline(&self.text_pipeline);
+                renderpass.set_bind_group(0, &self.atlas_bind_group, &[]);
+                renderpass.set_vertex_buffer(1, self.glyph_buffer.buffer.slice(..));
+                renderpass.draw_indexed(0..UNIT_QUAD_INDICES.len() as u32, 0, 0..glyphs.len() as u32);
+            }
 
             // renderpass.pop_debug_group();
             // renderpass.insert_debug_marker("Draw!");
         }
 
+        // Minimap in its own viewport, on top of everything
+        if let Some(minimap) = minimap.filter(|_| !minimap_quads.is_empty()) {
+            let mut minimap_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Minimap Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &texture_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            minimap_pass.set_viewport(minimap.left as f32, minimap.top as f32, minimap.size as f32, minimap.size as f32, 0.0, 1.0);
+            minimap_pass.set_pipeline(&self.render_pipeline);
+            minimap_pass.set_bind_group(0, &self.sprite_bind_group, &[]);
+            minimap_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            minimap_pass.set_vertex_buffer(1, self.minimap_buffer.buffer.slice(..));
+            minimap_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            minimap_pass.draw_indexed(0..UNIT_QUAD_INDICES.len() as u32, 0, 0..minimap_quads.len() as u32);
+        }
+
         // Submit the command in the queue to execute
         self.queue.submit([encoder.finish()]);
         self.window.pre_present_notify();
         surface_texture.present();
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_renderer_instances() {
+    use crate::game::common::Vector2F;
+
+    assert_eq!(instance_capacity(0), MIN_INSTANCE_CAPACITY);
+    assert_eq!(instance_capacity(100_000), 131_072);
+    assert_eq!(std::mem::size_of::<Instance>(), 48);
+    assert_eq!(std::mem::size_of::<GlyphInstance>(), 48);
+
+    let quad = Quad { position: Vector2F::new(-0.5, 0.25), size: Vector2F::new(0.1, 0.2), color: [1.0, 0.5, 0.0, 1.0], sprite: None };
+    let instance = Instance::from(&quad);
+    assert_eq!((instance.position, instance.size, instance.color), ([-0.5, 0.25], [0.1, 0.2], [1.0, 0.5, 0.0, 1.0]));
+    assert_eq!(instance.uv_size, [0.0; 2]);
+
+    let sprite = super::sprites::SpriteFrame { uv_position: [0.25, 0.5], uv_size: [0.25, 0.125] };
+    let instance = Instance::from(&Quad { sprite: Some(sprite), ..quad });
+    assert_eq!((instance.uv_position, instance.uv_size), (sprite.uv_position, sprite.uv_size));
+}
+
+#[test]
+fn test_renderer_shader_is_valid() {
+    for source in [include_str!("shader.wgsl"), include_str!("text.wgsl")] {
+        let module = wgpu::naga::front::wgsl::parse_str(source).unwrap();
+        wgpu::naga::valid::Validator::new(Default::default(), wgpu::naga::valid::Capabilities::empty()).validate(&module).unwrap();
+    }
+}
@@ -0,0 +1,487 @@
+use crate::{
+    client_requests::MoveDirection,
+    game::{
+        chunk::{Chunk, ChunkMap, Terrain},
+        common::{Rect2F, Vector2F},
+        world::World
+    }
+};
+
+use super::{
+    sprites::{SpriteFrame, SpriteSheet},
+    text::{GlyphAtlas, GlyphQuad},
+    AppData,
+    EntityView,
+    ItemView
+};
+
+/// Same dark gray as GPU clear color
+pub const CLEAR_COLOR: [f32; 4] = [0.1, 0.1, 0.1, 1.0];
+
+const HEALTH_BAR_HEIGHT: f32 = 0.6;
+const HEALTH_BAR_OFFSET: f32 = 0.3;
+/// Outline around selected entity, in world units like health bar
+const SELECTION_OUTLINE: f32 = 0.3;
+const SELECTION_COLOR: [f32; 4] = [1.0, 0.9, 0.2, 1.0];
+
+/// Faint lines over terrain and clear color alike
+const GRID_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.06];
+/// Tiles smaller than this many pixels have no grid lines, they would blend into a flat color
+const MIN_GRID_SPACING: f32 = 4.0;
+
+const PLAYER_NAME_COLOR: [f32; 4] = [0.9, 0.9, 0.9, 1.0];
+const NPC_NAME_COLOR: [f32; 4] = [1.0, 0.8, 0.4, 1.0];
+const HUD_TEXT_COLOR: [f32; 4] = [0.8, 1.0, 0.8, 1.0];
+const PANEL_COLOR: [f32; 4] = [0.03, 0.03, 0.03, 1.0];
+/// Distances in pixels
+const PANEL_MARGIN: f32 = 6.0;
+const PANEL_PADDING: f32 = 4.0;
+const NAME_OFFSET: f32 = 2.0;
+/// Part of viewport width taken by chat panel
+const CHAT_PANEL_WIDTH: f32 = 0.5;
+
+/// Minimap side in pixels, smaller on small windows and hidden on tiny ones
+const MINIMAP_SIZE: f32 = 160.0;
+const MINIMAP_MAX_FRACTION: f32 = 0.3;
+const MINIMAP_MIN_SIZE: f32 = 32.0;
+/// Entity dot side in pixels
+const MINIMAP_DOT_SIZE: f32 = 3.0;
+const MINIMAP_OWN_COLOR: [f32; 4] = [0.3, 0.8, 1.0, 1.0];
+const MINIMAP_CAMERA_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.6];
+
+/// Rect in NDC, position is bottom-left corner. Sprite frame is tinted with color.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Quad {
+    pub position: Vector2F,
+    pub size: Vector2F,
+    pub color: [f32; 4],
+    pub sprite: Option<SpriteFrame>,
+}
+
+/// Screen space elements drawn over the world, sized in pixels so zoom does not affect them
+#[derive(Debug, Default)]
+pub struct Overlay {
+    /// Solid backgrounds, drawn before glyphs
+    pub panels: Vec<Quad>,
+    pub glyphs: Vec<GlyphQuad>,
+}
+
+fn terrain_color(terrain: Terrain) -> [f32; 4] {
+    match terrain {
+        Terrain::Grass => [0.05, 0.09, 0.04, 1.0],
+        Terrain::Sand => [0.13, 0.11, 0.06, 1.0],
+        Terrain::Stone => [0.07, 0.07, 0.08, 1.0],
+        Terrain::Water => [0.03, 0.06, 0.13, 1.0],
+    }
+}
+
+/// Drawn in its own square viewport, quads are in NDC of that viewport and clipped to it
+#[derive(Debug)]
+pub struct Minimap {
+    /// Pixels from top-left corner of main viewport
+    pub left: u32,
+    pub top: u32,
+    pub size: u32,
+    pub quads: Vec<Quad>,
+}
+
+/// Background layer in draw order: terrain of known chunks, then grid lines one pixel wide.
+/// Only tiles and lines inside the camera rect are emitted.
+pub fn build_tiles(app_data: &AppData, viewport: (u32, u32)) -> Vec<Quad> {
+    let mut quads = vec![];
+    if viewport.0 == 0 || viewport.1 == 0 || app_data.camera.scale <= 0.0 || !app_data.layers.terrain {
+        return quads;
+    }
+    let (viewport_width, viewport_height) = (viewport.0 as f32, viewport.1 as f32);
+    let aspect_ratio = viewport_width / viewport_height;
+    let visible_area = app_data.visible_area(aspect_ratio);
+    let tile_size = Vector2F::new(World::TILE_SIZE_SIDE * app_data.camera.scale / aspect_ratio, World::TILE_SIZE_SIDE * app_data.camera.scale);
+
+    for (coord, terrain) in &app_data.terrain {
+        if !Chunk::area(*coord).intersects(&visible_area) {
+            continue;
+        }
+        let chunk_origin = Vector2F::new(coord.x as f32 * Chunk::SIZE, coord.y as f32 * Chunk::SIZE);
+        for (idx, tile) in terrain.iter().enumerate() {
+            let tile_x = (idx as i32 % Chunk::SIZE_TILES) as f32 * World::TILE_SIZE_SIDE;
+            let tile_y = (idx as i32 / Chunk::SIZE_TILES) as f32 * World::TILE_SIZE_SIDE;
+            let tile_area = Rect2F::new(chunk_origin.x + tile_x, chunk_origin.y + tile_y, World::TILE_SIZE_SIDE, World::TILE_SIZE_SIDE);
+            if tile_area.intersects(&visible_area) {
+                let position = app_data.world_to_ndc(tile_area.pos, aspect_ratio);
+                quads.push(Quad { position, size: tile_size, color: terrain_color(*tile), sprite: None });
+            }
+        }
+    }
+
+    if tile_size.y / 2.0 * viewport_height < MIN_GRID_SPACING {
+        return quads;
+    }
+    let line_width = 2.0 / viewport_width;
+    let line_height = 2.0 / viewport_height;
+    let first_column = (visible_area.pos.x / World::TILE_SIZE_SIDE).ceil() as i32;
+    let last_column = ((visible_area.pos.x + visible_area.size.x) / World::TILE_SIZE_SIDE).floor() as i32;
+    for column in first_column..=last_column {
+        let x = app_data.world_to_ndc(Vector2F::new(column as f32 * World::TILE_SIZE_SIDE, 0.0), aspect_ratio).x;
+        quads.push(Quad { position: Vector2F::new(x - line_width / 2.0, -1.0), size: Vector2F::new(line_width, 2.0), color: GRID_COLOR, sprite: None });
+    }
+    let first_row = (visible_area.pos.y / World::TILE_SIZE_SIDE).ceil() as i32;
+    let last_row = ((visible_area.pos.y + visible_area.size.y) / World::TILE_SIZE_SIDE).floor() as i32;
+    for row in first_row..=last_row {
+        let y = app_data.world_to_ndc(Vector2F::new(0.0, row as f32 * World::TILE_SIZE_SIDE), aspect_ratio).y;
+        quads.push(Quad { position: Vector2F::new(-1.0, y - line_height / 2.0), size: Vector2F::new(2.0, line_height), color: GRID_COLOR, sprite: None });
+    }
+    quads
+}
+
+/// Quads in draw order, independent of backend
+pub fn build_quads(app_data: &AppData, aspect_ratio: f32, sprites: &SpriteSheet) -> Vec<Quad> {
+    let scale_x = app_data.camera.scale / aspect_ratio;
+    let scale_y = app_data.camera.scale;
+    let mut quads = Vec::with_capacity(app_data.items.len() + app_data.entities.len());
+
+    let item_size = Vector2F::new(ItemView::SIZE.x * scale_x, ItemView::SIZE.y * scale_y);
+    let frame = |sprite: &str, facing, is_moving| {
+        app_data.layers.sprites.then(|| sprites.frame(sprite, facing, is_moving, app_data.animation_time)).flatten()
+    };
+    let item_sprite = frame(ItemView::SPRITE, MoveDirection::default(), false);
+    let [r, g, b] = ItemView::COLOR;
+    for item in &app_data.items {
+        let position = app_data.world_to_ndc(item.position, aspect_ratio);
+        quads.push(Quad { position, size: item_size, color: [r, g, b, 1.0], sprite: item_sprite });
+    }
+
+    for entity in &app_data.entities {
+        let position = app_data.world_to_ndc(entity.position, aspect_ratio);
+        let size = Vector2F::new(entity.size.x * scale_x, entity.size.y * scale_y);
+        let sprite = entity.sprite.as_ref()
+            .and_then(|sprite| frame(sprite, entity.facing, entity.is_moving));
+        quads.push(Quad { position, size, color: [entity.color[0], entity.color[1], entity.color[2], 1.0], sprite });
+
+        if Some(entity.id) == app_data.selected {
+            let (outline_x, outline_y) = (SELECTION_OUTLINE * scale_x, SELECTION_OUTLINE * scale_y);
+            let outer_position = Vector2F::new(position.x - outline_x, position.y - outline_y);
+            let outer_size = Vector2F::new(size.x + 2.0 * outline_x, size.y + 2.0 * outline_y);
+            for (edge_position, edge_size) in [
+                (outer_position, Vector2F::new(outer_size.x, outline_y)),
+                (Vector2F::new(outer_position.x, position.y + size.y), Vector2F::new(outer_size.x, outline_y)),
+                (Vector2F::new(outer_position.x, position.y), Vector2F::new(outline_x, size.y)),
+                (Vector2F::new(position.x + size.x, position.y), Vector2F::new(outline_x, size.y)),
+            ] {
+                quads.push(Quad { position: edge_position, size: edge_size, color: SELECTION_COLOR, sprite: None });
+            }
+        }
+
+        // Health bar above damaged entities
+        let health = entity.health_fraction();
+        if health < 1.0 {
+            let bar_position = Vector2F::new(position.x, position.y + size.y + HEALTH_BAR_OFFSET * scale_y);
+            let bar_height = HEALTH_BAR_HEIGHT * scale_y;
+            quads.push(Quad { position: bar_position, size: Vector2F::new(size.x, bar_height), color: [0.4, 0.0, 0.0, 1.0], sprite: None });
+            quads.push(Quad { position: bar_position, size: Vector2F::new(size.x * health, bar_height), color: [0.0, 0.8, 0.0, 1.0], sprite: None });
+        }
+    }
+    quads
+}
+
+/// Rect given in pixels from top-left corner of viewport
+fn pixel_rect_quad(left: f32, top: f32, width: f32, height: f32, viewport: (u32, u32), color: [f32; 4]) -> Quad {
+    let (viewport_width, viewport_height) = (viewport.0 as f32, viewport.1 as f32);
+    Quad {
+        position: Vector2F::new(left / viewport_width * 2.0 - 1.0, 1.0 - (top + height) / viewport_height * 2.0),
+        size: Vector2F::new(width / viewport_width * 2.0, height / viewport_height * 2.0),
+        color,
+        sprite: None,
+    }
+}
+
+/// Lines of text on a panel, `top_left` in pixels
+fn push_panel(overlay: &mut Overlay, atlas: &GlyphAtlas, lines: &[String], top_left: Vector2F, width: f32, viewport: (u32, u32)) {
+    let height = lines.len() as f32 * atlas.line_height() + 2.0 * PANEL_PADDING;
+    overlay.panels.push(pixel_rect_quad(top_left.x, top_left.y, width, height, viewport, PANEL_COLOR));
+
+    for (idx, line) in lines.iter().enumerate() {
+        let baseline = Vector2F::new(
+            top_left.x + PANEL_PADDING,
+            top_left.y + PANEL_PADDING + atlas.ascent() + idx as f32 * atlas.line_height()
+        );
+        atlas.layout(line, baseline, HUD_TEXT_COLOR, viewport, &mut overlay.glyphs);
+    }
+}
+
+fn hud_lines(app_data: &AppData) -> Vec<String> {
+    let ping = app_data.ping.map_or("-".to_string(), |ping| format!("{} ms", ping.as_millis()));
+    let player = app_data.player_id.map_or("-".to_string(), |player_id| player_id.to_string());
+    let position = app_data.player_position().map_or("-".to_string(), |position| format!("{:.1}, {:.1}", position.x, position.y));
+    vec![
+        format!("FPS {:.0}", app_data.fps),
+        format!("Ping {ping}"),
+        format!("Player {player}"),
+        format!("Position {position}"),
+    ]
+}
+
+fn inspect_lines(entity: &EntityView) -> Vec<String> {
+    let kind = if entity.is_npc { "NPC" } else { "Player" };
+    let moving = if entity.is_moving { format!("{:?}", entity.facing) } else { "-".to_string() };
+    let [r, g, b] = entity.color.map(|channel| (channel * 255.0).round() as u8);
+    vec![
+        format!("{} #{}", entity.name, entity.id),
+        format!("Kind {kind}"),
+        format!("Health {}/{}", entity.health, entity.max_health),
+        format!("Position {:.1}, {:.1}", entity.position.x, entity.position.y),
+        format!("Size {:.1} x {:.1}", entity.size.x, entity.size.y),
+        format!("Color {r}, {g}, {b}"),
+        format!("Sprite {}", entity.sprite.as_deref().unwrap_or("-")),
+        format!("Moving {moving}"),
+    ]
+}
+
+/// Entity names, HUD in top-left, selected entity in top-right and chat log in bottom-left corner
+pub fn build_overlay(app_data: &AppData, atlas: &GlyphAtlas, viewport: (u32, u32)) -> Overlay {
+    let mut overlay = Overlay::default();
+    if viewport.0 == 0 || viewport.1 == 0 {
+        return overlay;
+    }
+    let (viewport_width, viewport_height) = (viewport.0 as f32, viewport.1 as f32);
+    let aspect_ratio = viewport_width / viewport_height;
+
+    // Names centered above entity and its health bar
+    let named = if app_data.layers.names { app_data.entities.as_slice() } else { &[] };
+    for entity in named {
+        let anchor = entity.position + Vector2F::new(entity.size.x / 2.0, entity.size.y + HEALTH_BAR_OFFSET + HEALTH_BAR_HEIGHT);
+        let ndc = app_data.world_to_ndc(anchor, aspect_ratio);
+        let text_width = atlas.text_width(&entity.name);
+        let left = (ndc.x + 1.0) / 2.0 * viewport_width - text_width / 2.0;
+        let baseline = (1.0 - ndc.y) / 2.0 * viewport_height - NAME_OFFSET;
+
+        let is_visible = left + text_width >= 0.0 && left <= viewport_width && baseline >= 0.0 && baseline - atlas.line_height() <= viewport_height;
+        if is_visible {
+            let color = if entity.is_npc { NPC_NAME_COLOR } else { PLAYER_NAME_COLOR };
+            atlas.layout(&entity.name, Vector2F::new(left, baseline), color, viewport, &mut overlay.glyphs);
+        }
+    }
+
+    if !app_data.hide_debug_overlay {
+        let hud_lines = hud_lines(app_data);
+        let hud_width = hud_lines.iter().map(|line| atlas.text_width(line)).fold(0.0, f32::max) + 2.0 * PANEL_PADDING;
+        push_panel(&mut overlay, atlas, &hud_lines, Vector2F::new(PANEL_MARGIN, PANEL_MARGIN), hud_width, viewport);
+    }
+
+    if let Some(entity) = app_data.selected.and_then(|selected| app_data.entities.iter().find(|entity| entity.id == selected)) {
+        let inspect_lines = inspect_lines(entity);
+        let inspect_width = inspect_lines.iter().map(|line| atlas.text_width(line)).fold(0.0, f32::max) + 2.0 * PANEL_PADDING;
+        let top_left = Vector2F::new(viewport_width - PANEL_MARGIN - inspect_width, PANEL_MARGIN);
+        push_panel(&mut overlay, atlas, &inspect_lines, top_left, inspect_width, viewport);
+    }
+
+    if !app_data.chat_log.is_empty() || app_data.chat_draft.is_some() {
+        // Monospace font, long lines are cut to fit the panel
+        let chat_width = viewport_width * CHAT_PANEL_WIDTH - PANEL_MARGIN;
+        let max_chars = ((chat_width - 2.0 * PANEL_PADDING) / atlas.glyph(' ').advance.round()).max(0.0) as usize;
+        let mut chat_lines: Vec<String> = app_data.chat_log.iter()
+            .map(|line| line.chars().take(max_chars).collect())
+            .collect();
+        // End of long message stays visible while typing
+        if let Some(draft) = &app_data.chat_draft {
+            let draft = format!("> {draft}_");
+            chat_lines.push(draft.chars().skip(draft.chars().count().saturating_sub(max_chars)).collect());
+        }
+        let chat_height = chat_lines.len() as f32 * atlas.line_height() + 2.0 * PANEL_PADDING;
+        let top_left = Vector2F::new(PANEL_MARGIN, viewport_height - PANEL_MARGIN - chat_height);
+        push_panel(&mut overlay, atlas, &chat_lines, top_left, chat_width, viewport);
+    }
+    overlay
+}
+
+/// Part of quad inside -1..1 on both axes, sprite frames are not clipped
+fn clip_quad(quad: Quad) -> Option<Quad> {
+    let min = Vector2F::new(quad.position.x.max(-1.0), quad.position.y.max(-1.0));
+    let max = Vector2F::new((quad.position.x + quad.size.x).min(1.0), (quad.position.y + quad.size.y).min(1.0));
+    (min.x < max.x && min.y < max.y).then(|| Quad { position: min, size: max - min, ..quad })
+}
+
+/// Loaded chunks around the player with terrain, entity dots and outline of the main camera view,
+/// placed in bottom-right corner
+pub fn build_minimap(app_data: &AppData, viewport: (u32, u32)) -> Option<Minimap> {
+    let (viewport_width, viewport_height) = (viewport.0 as f32, viewport.1 as f32);
+    let size = MINIMAP_SIZE.min(viewport_width.min(viewport_height) * MINIMAP_MAX_FRACTION).floor();
+    if size < MINIMAP_MIN_SIZE || app_data.camera.scale <= 0.0 || !app_data.layers.minimap {
+        return None;
+    }
+
+    // Same area as chunks server keeps loaded around the player
+    let range = Chunk::SIZE * (2 * ChunkMap::ACTIVE_RADIUS + 1) as f32;
+    let center = app_data.player_position().unwrap_or(app_data.camera.position);
+    let area = Rect2F::new(center.x - range / 2.0, center.y - range / 2.0, range, range);
+    let to_minimap = |position: Vector2F| (position - area.pos) * (2.0 / range) + Vector2F::new(-1.0, -1.0);
+    let tile_size = Vector2F::new(World::TILE_SIZE_SIDE, World::TILE_SIZE_SIDE) * (2.0 / range);
+
+    let mut quads = vec![Quad { position: Vector2F::new(-1.0, -1.0), size: Vector2F::new(2.0, 2.0), color: PANEL_COLOR, sprite: None }];
+    for (coord, terrain) in &app_data.terrain {
+        if !Chunk::area(*coord).intersects(&area) {
+            continue;
+        }
+        for (idx, tile) in terrain.iter().enumerate() {
+            let tile_position = Chunk::area(*coord).pos + Vector2F::new(
+                (idx as i32 % Chunk::SIZE_TILES) as f32 * World::TILE_SIZE_SIDE,
+                (idx as i32 / Chunk::SIZE_TILES) as f32 * World::TILE_SIZE_SIDE
+            );
+            quads.extend(clip_quad(Quad { position: to_minimap(tile_position), size: tile_size, color: terrain_color(*tile), sprite: None }));
+        }
+    }
+
+    // Dots keep their size in pixels at any zoom, own player on top
+    let dot_size = MINIMAP_DOT_SIZE / size * 2.0;
+    let mut dots: Vec<_> = app_data.entities.iter().collect();
+    dots.sort_by_key(|entity| Some(entity.id) == app_data.player_id);
+    for entity in dots {
+        let color = match entity {
+            _ if Some(entity.id) == app_data.player_id => MINIMAP_OWN_COLOR,
+            _ if entity.is_npc => NPC_NAME_COLOR,
+            _ => PLAYER_NAME_COLOR,
+        };
+        let entity_center = to_minimap(entity.position + entity.size * 0.5);
+        let position = entity_center - Vector2F::new(dot_size / 2.0, dot_size / 2.0);
+        quads.extend(clip_quad(Quad { position, size: Vector2F::new(dot_size, dot_size), color, sprite: None }));
+    }
+
+    // Outline of what main view shows, one pixel wide
+    let visible_area = app_data.visible_area(viewport_width / viewport_height);
+    let (min, max) = (to_minimap(visible_area.pos), to_minimap(visible_area.pos + visible_area.size));
+    let line = 2.0 / size;
+    for (position, edge_size) in [
+        (min, Vector2F::new(max.x - min.x, line)),
+        (Vector2F::new(min.x, max.y - line), Vector2F::new(max.x - min.x, line)),
+        (min, Vector2F::new(line, max.y - min.y)),
+        (Vector2F::new(max.x - line, min.y), Vector2F::new(line, max.y - min.y)),
+    ] {
+        quads.extend(clip_quad(Quad { position, size: edge_size, color: MINIMAP_CAMERA_COLOR, sprite: None }));
+    }
+
+    Some(Minimap {
+        left: (viewport_width - PANEL_MARGIN - size) as u32,
+        top: (viewport_height - PANEL_MARGIN - size) as u32,
+        size: size as u32,
+        quads,
+    })
+}
+
+#[test]
+fn test_scene_overlay() {
+    let atlas = GlyphAtlas::new().unwrap();
+    let mut app_data = AppData { player_id: Some(1), camera: super::camera::Camera::new(Vector2F::zero(), 0.05), ..Default::default() };
+    app_data.entities.push(EntityView { position: Vector2F::zero(), size: Vector2F::new(4.8, 4.8), color: [1.0; 3], health: 1, max_health: 1, id: 1, name: "Tuna".to_string(), ..Default::default() });
+    app_data.ping = Some(std::time::Duration::from_millis(12));
+
+    assert_eq!(hud_lines(&app_data), vec!["FPS 0", "Ping 12 ms", "Player 1", "Position 0.0, 0.0"]);
+    let overlay = build_overlay(&app_data, &atlas, (200, 100));
+    assert_eq!(overlay.panels.len(), 1);
+    let hud_glyphs: usize = hud_lines(&app_data).iter().map(|line| line.chars().filter(|c| *c != ' ').count()).sum();
+    assert_eq!(overlay.glyphs.len(), "Tuna".len() + hud_glyphs);
+
+    // Name is above the entity, which starts at the center of the viewport
+    assert!(overlay.glyphs[0].position.y > app_data.entities[0].size.y * app_data.camera.scale);
+
+    // Long chat line is cut at the right edge of the panel, in the middle of the viewport
+    let glyphs_without_chat = overlay.glyphs.len();
+    app_data.push_chat("x".repeat(500));
+    let overlay = build_overlay(&app_data, &atlas, (200, 100));
+    assert_eq!(overlay.panels.len(), 2);
+    let chat_glyphs = &overlay.glyphs[glyphs_without_chat..];
+    assert!(!chat_glyphs.is_empty() && chat_glyphs.len() < 500);
+    assert!(chat_glyphs.iter().all(|glyph| glyph.position.x + glyph.size.x <= 0.0));
+
+    // Typed message is the last line, HUD can be hidden
+    app_data.chat_draft = Some("hi".to_string());
+    app_data.hide_debug_overlay = true;
+    let overlay = build_overlay(&app_data, &atlas, (200, 100));
+    assert_eq!(overlay.panels.len(), 1);
+    assert_eq!(overlay.glyphs.len(), "Tuna".len() + chat_glyphs.len() + "> hi_".len() - 1);
+}
+
+#[test]
+fn test_scene_selected_entity() {
+    use crate::client_requests::EntityCheckData;
+
+    let atlas = GlyphAtlas::new().unwrap();
+    let sprites = SpriteSheet::new().unwrap();
+    let mut app_data = AppData { player_id: Some(1), camera: super::camera::Camera::new(Vector2F::zero(), 0.05), ..Default::default() };
+    let check = EntityCheckData {
+        position: Vector2F::new(10.0, 0.0),
+        size: Vector2F::new(4.8, 4.8),
+        color: [255, 0, 0],
+        id: 2,
+        name: "Tuna".to_string(),
+        is_npc: true,
+        health: 3,
+        max_health: 10,
+        sprite: None,
+        facing: None,
+    };
+    app_data.update_world(vec![check], vec![]);
+    let unselected = (build_quads(&app_data, 2.0, &sprites).len(), build_overlay(&app_data, &atlas, (400, 200)).panels.len());
+
+    app_data.selected = app_data.entity_at(Vector2F::new(12.0, 2.0));
+    assert_eq!(app_data.selected, Some(2));
+    assert_eq!(build_quads(&app_data, 2.0, &sprites).len(), unselected.0 + 4);
+    let overlay = build_overlay(&app_data, &atlas, (400, 200));
+    assert_eq!(overlay.panels.len(), unselected.1 + 1);
+    assert!(overlay.panels.last().unwrap().position.x > 0.0);
+    let lines = inspect_lines(&app_data.entities[0]);
+    assert!(lines.contains(&"Health 3/10".to_string()));
+    assert!(lines.contains(&"Color 255, 0, 0".to_string()));
+
+    // Selection is dropped with the entity
+    app_data.update_world(vec![], vec![]);
+    assert_eq!(app_data.selected, None);
+}
+
+#[test]
+fn test_scene_tiles_culled_to_camera() {
+    use crate::{client_requests::ChunkData, game::chunk::ChunkCoord};
+
+    let chunk = |x, y| ChunkData { coord: ChunkCoord::new(x, y), terrain: vec![Terrain::Sand; (Chunk::SIZE_TILES * Chunk::SIZE_TILES) as usize] };
+    let mut app_data = AppData { camera: super::camera::Camera::new(Vector2F::zero(), 0.05), ..Default::default() };
+    app_data.update_terrain(vec![chunk(0, 0), chunk(5, 5)], vec![]);
+
+    // Viewport shows x in -40..40 and y in -20..20, top row of chunk (0, 0) and far chunk are outside
+    let quads = build_tiles(&app_data, (200, 100));
+    let tiles = quads.iter().filter(|quad| quad.color == terrain_color(Terrain::Sand)).count();
+    assert_eq!(tiles, 4 * Chunk::SIZE_TILES as usize);
+    let vertical_lines = quads.iter().filter(|quad| quad.color == GRID_COLOR && quad.size.y == 2.0).count();
+    assert_eq!((vertical_lines, quads.len() - tiles - vertical_lines), (17, 9));
+
+    // Zoomed out so far that grid would be a flat color
+    app_data.camera.scale = 0.005;
+    assert!(build_tiles(&app_data, (200, 100)).iter().all(|quad| quad.color != GRID_COLOR));
+
+    app_data.update_terrain(vec![], vec![ChunkCoord::new(0, 0), ChunkCoord::new(5, 5)]);
+    assert!(app_data.terrain.is_empty());
+}
+
+#[test]
+fn test_scene_minimap() {
+    let mut app_data = AppData { player_id: Some(1), ..Default::default() };
+    for (id, x, is_npc) in [(1, 0.0, false), (2, 20.0, true), (3, -20.0, false), (4, 1000.0, true)] {
+        app_data.entities.push(EntityView { position: Vector2F::new(x, 0.0), size: Vector2F::new(4.8, 4.8), id, name: format!("Entity{id}"), is_npc, ..Default::default() });
+    }
+
+    // Corner of the window, smaller than default size on small window
+    let minimap = build_minimap(&app_data, (400, 300)).unwrap();
+    assert_eq!((minimap.left, minimap.top, minimap.size), (304, 204, 90));
+    assert!(build_minimap(&app_data, (100, 100)).is_none());
+
+    // Far entity is outside of minimap, own player is drawn last
+    let count = |color| minimap.quads.iter().filter(|quad| quad.color == color).count();
+    assert_eq!((count(MINIMAP_OWN_COLOR), count(NPC_NAME_COLOR), count(PLAYER_NAME_COLOR)), (1, 1, 1));
+    let own_dot = minimap.quads.iter().rposition(|quad| quad.color == MINIMAP_OWN_COLOR).unwrap();
+    let camera_outline = minimap.quads.iter().position(|quad| quad.color == MINIMAP_CAMERA_COLOR).unwrap();
+    assert_eq!(own_dot + 1, camera_outline);
+    assert_eq!(count(MINIMAP_CAMERA_COLOR), 4);
+
+    // Zoomed out camera shows more than minimap, outline is clipped to its edges
+    app_data.camera.scale = super::camera::Camera::MIN_SCALE;
+    let minimap = build_minimap(&app_data, (400, 300)).unwrap();
+    assert!(minimap.quads.iter().all(|quad| quad.position.x >= -1.0 && quad.position.y >= -1.0
+        && quad.position.x + quad.size.x <= 1.0 && quad.position.y + quad.size.y <= 1.0));
+}
@@ -1,39 +1,46 @@
-// Vertex input structure matching the Vertex struct in Rust.
+// Corner of unit quad, matches Vertex struct in Rust.
 struct VertexInput {
-    @location(0) position: vec4<f32>,
+    @location(0) position: vec2<f32>,
 };
 
-// The uniform structure with our color.
-struct Uniforms {
-    color: vec4<f32>,
+// Per instance rect in NDC, matches Instance struct in Rust.
+// Zero uv_size means solid color rect without sprite.
+struct InstanceInput {
+    @location(1) position: vec2<f32>,
+    @location(2) size: vec2<f32>,
+    @location(3) color: vec4<f32>,
+    @location(4) uv_position: vec2<f32>,
+    @location(5) uv_size: vec2<f32>,
 };
 
-// Bind the uniform to group 0, binding 0.
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) textured: f32,
+};
+
+// Sprite sheet, sRGB with straight alpha.
 @group(0) @binding(0)
-var<uniform> uniforms: Uniforms;
+var sprite_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var sprite_sampler: sampler;
 
 @vertex
-fn vs_main(input: VertexInput) -> @builtin(position) vec4<f32> {
-    // Pass through the vertex position.
-    return input.position;
+fn vs_main(vertex: VertexInput, instance: InstanceInput) -> VertexOutput {
+    // Scale unit quad to instance size and move it to instance position.
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(instance.position + vertex.position * instance.size, 0.0, 1.0);
+    out.color = instance.color;
+    // Sprite rows go from the top, quad corners from the bottom.
+    out.uv = instance.uv_position + vec2<f32>(vertex.position.x, 1.0 - vertex.position.y) * instance.uv_size;
+    out.textured = select(0.0, 1.0, instance.uv_size.x > 0.0);
+    return out;
 }
 
 @fragment
-fn fs_main() -> @location(0) vec4<f32> {
-    // Output a solid red color.
-    // return vec4<f32>(0.14, 0.14, 0.14, 1.0);
-    // Use the uniform color for output.
-    return uniforms.color;
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Sampled always, texture lookup has to be in uniform control flow.
+    let texel = textureSample(sprite_texture, sprite_sampler, in.uv);
+    return in.color * select(vec4<f32>(1.0), texel, in.textured > 0.5);
 }
-
-// @vertex
-// fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
-//     let x = f32(i32(in_vertex_index) - 1) / 2.0;
-//     let y = f32(i32(in_vertex_index & 1u) * 2 - 1);
-//     return vec4<f32>(x, y, 0.0, 1.0);
-// }
-
-// @fragment
-// fn fs_main() -> @location(0) vec4<f32> {
-//     return vec4<f32>(1.0, 0.0, 0.0, 1.0);
-// }
@@ -0,0 +1,472 @@
+use std::{
+    io::BufWriter,
+    path::Path
+};
+
+use crate::game::common::Vector2F;
+
+use super::{
+    scene::{self, Minimap, Quad},
+    sprites::SpriteSheet,
+    text::{GlyphAtlas, GlyphQuad},
+    AppData, Renderer
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum SoftwareRendererError {
+    #[error("IoError, reason='{0}'")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Could not encode PNG, reason='{0}'")]
+    EncodingError(#[from] png::EncodingError),
+
+    #[error("Could not decode PNG, reason='{0}'")]
+    DecodingError(#[from] png::DecodingError),
+
+    #[error("Unsupported PNG format, only 8 bit RGBA is read")]
+    UnsupportedFormat,
+}
+
+/// CPU backend drawing into RGBA image, used where no GPU is available
+pub struct SoftwareRenderer {
+    width: u32,
+    height: u32,
+    /// Row by row from the top, sRGB encoded like GPU surface
+    pixels: Vec<u8>,
+    atlas: GlyphAtlas,
+    sprites: SpriteSheet,
+}
+
+/// Image read from PNG file, e.g. reference image in tests
+pub struct RgbaImage {
+    width: u32,
+    height: u32,
+    /// Row by row from the top
+    pixels: Vec<u8>,
+}
+
+/// Same transfer function as `*Srgb` texture formats
+fn linear_to_srgb(channel: f32) -> u8 {
+    let channel = channel.clamp(0.0, 1.0);
+    let encoded = if channel <= 0.003_130_8 {
+        channel * 12.92
+    } else {
+        1.055 * channel.powf(1.0 / 2.4) - 0.055
+    };
+    (encoded * 255.0).round() as u8
+}
+
+fn srgb_to_linear(channel: u8) -> f32 {
+    let channel = channel as f32 / 255.0;
+    if channel <= 0.040_45 {
+        channel / 12.92
+    } else {
+        ((channel + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+impl SoftwareRenderer {
+    /// Embedded font and sprites
+    pub fn new(width: u32, height: u32) -> Self {
+        let atlas = GlyphAtlas::new().expect("Embedded font should load");
+        let sprites = SpriteSheet::new().expect("Embedded sprites should load");
+        Self::with_assets(width, height, atlas, sprites)
+    }
+
+    pub fn with_assets(width: u32, height: u32, atlas: GlyphAtlas, sprites: SpriteSheet) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize * 4],
+            atlas,
+            sprites,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Column and row counted from the top-left corner
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let idx = (y as usize * self.width as usize + x as usize) * 4;
+        [self.pixels[idx], self.pixels[idx + 1], self.pixels[idx + 2], self.pixels[idx + 3]]
+    }
+
+    fn clear(&mut self, color: [f32; 4]) {
+        let color = color.map(linear_to_srgb);
+        self.pixels.chunks_exact_mut(4).for_each(|pixel| pixel.copy_from_slice(&color));
+    }
+
+    /// Columns and rows of pixels with center inside the rect, like GPU rasterization
+    fn covered_pixels(viewport: (u32, u32), position: Vector2F, size: Vector2F) -> (std::ops::Range<usize>, std::ops::Range<usize>) {
+        let (width, height) = (viewport.0 as f32, viewport.1 as f32);
+        let to_column = |ndc_x: f32| ((ndc_x + 1.0) / 2.0 * width - 0.5).ceil().clamp(0.0, width) as usize;
+        let to_row = |ndc_y: f32| ((1.0 - ndc_y) / 2.0 * height - 0.5).ceil().clamp(0.0, height) as usize;
+
+        (
+            to_column(position.x)..to_column(position.x + size.x),
+            to_row(position.y + size.y)..to_row(position.y)
+        )
+    }
+
+    fn fill_quad(&mut self, quad: &Quad) {
+        if quad.sprite.is_some() {
+            return self.blend_sprite(quad);
+        }
+        let (columns, rows) = Self::covered_pixels((self.width, self.height), quad.position, quad.size);
+        let color = quad.color.map(linear_to_srgb);
+        let is_translucent = quad.color[3] < 1.0;
+
+        for row in rows {
+            let row_start = row * self.width as usize * 4;
+            let row_pixels = self.pixels[row_start + columns.start * 4..row_start + columns.end * 4].chunks_exact_mut(4);
+            if is_translucent {
+                row_pixels.for_each(|pixel| Self::blend_pixel(pixel, [quad.color[0], quad.color[1], quad.color[2]], quad.color[3]));
+            } else {
+                row_pixels.for_each(|pixel| pixel.copy_from_slice(&color));
+            }
+        }
+    }
+
+    /// Quads are given in NDC of minimap viewport, mapped to the whole image here
+    fn fill_minimap(&mut self, minimap: &Minimap) {
+        let (width, height) = (self.width as f32, self.height as f32);
+        let scale = Vector2F::new(minimap.size as f32 / width, minimap.size as f32 / height);
+        let offset = Vector2F::new(
+            (minimap.left as f32 + minimap.size as f32 / 2.0) / width * 2.0 - 1.0,
+            1.0 - (minimap.top as f32 + minimap.size as f32 / 2.0) / height * 2.0
+        );
+        for quad in &minimap.quads {
+            self.fill_quad(&Quad {
+                position: Vector2F::new(quad.position.x * scale.x + offset.x, quad.position.y * scale.y + offset.y),
+                size: Vector2F::new(quad.size.x * scale.x, quad.size.y * scale.y),
+                ..*quad
+            });
+        }
+    }
+
+    /// Calls `shade` with covered pixel and nearest texel of `uv_position`..`uv_position + uv_size`
+    /// Takes pixels instead of `self`, so `shade` can borrow textures
+    fn for_each_texel<F: FnMut(&mut [u8], u32, u32)>(
+        pixels: &mut [u8],
+        viewport: (u32, u32),
+        (position, size): (Vector2F, Vector2F),
+        (uv_position, uv_size): ([f32; 2], [f32; 2]),
+        (texture_width, texture_height): (u32, u32),
+        mut shade: F
+    ) {
+        let (columns, rows) = Self::covered_pixels(viewport, position, size);
+        let left = (position.x + 1.0) / 2.0 * viewport.0 as f32;
+        let top = (1.0 - position.y - size.y) / 2.0 * viewport.1 as f32;
+        let width = size.x / 2.0 * viewport.0 as f32;
+        let height = size.y / 2.0 * viewport.1 as f32;
+
+        for row in rows {
+            let v = uv_position[1] + (row as f32 + 0.5 - top) / height * uv_size[1];
+            let texel_y = ((v * texture_height as f32) as u32).min(texture_height - 1);
+            for column in columns.clone() {
+                let u = uv_position[0] + (column as f32 + 0.5 - left) / width * uv_size[0];
+                let texel_x = ((u * texture_width as f32) as u32).min(texture_width - 1);
+                let idx = (row * viewport.0 as usize + column) * 4;
+                shade(&mut pixels[idx..idx + 4], texel_x, texel_y);
+            }
+        }
+    }
+
+    /// Straight alpha blending in linear space, like sRGB render target
+    fn blend_pixel(pixel: &mut [u8], color: [f32; 3], alpha: f32) {
+        if alpha == 0.0 {
+            return;
+        }
+        for channel in 0..3 {
+            let destination = srgb_to_linear(pixel[channel]);
+            pixel[channel] = linear_to_srgb(color[channel] * alpha + destination * (1.0 - alpha));
+        }
+    }
+
+    fn blend_glyph(&mut self, glyph: &GlyphQuad) {
+        let atlas = &self.atlas;
+        let texture = (glyph.uv_position, glyph.uv_size);
+        Self::for_each_texel(&mut self.pixels, (self.width, self.height), (glyph.position, glyph.size), texture, (atlas.width(), atlas.height()), |pixel, x, y| {
+            let alpha = atlas.coverage_at(x, y) as f32 / 255.0 * glyph.color[3];
+            Self::blend_pixel(pixel, [glyph.color[0], glyph.color[1], glyph.color[2]], alpha);
+        });
+    }
+
+    /// Sprite texel multiplied by quad color
+    fn blend_sprite(&mut self, quad: &Quad) {
+        let Some(frame) = quad.sprite else {
+            return;
+        };
+        let sprites = &self.sprites;
+        let texture = (frame.uv_position, frame.uv_size);
+        Self::for_each_texel(&mut self.pixels, (self.width, self.height), (quad.position, quad.size), texture, (sprites.width(), sprites.height()), |pixel, x, y| {
+            let texel = sprites.pixel(x, y);
+            let color = [0, 1, 2].map(|channel| srgb_to_linear(texel[channel]) * quad.color[channel]);
+            Self::blend_pixel(pixel, color, texel[3] as f32 / 255.0 * quad.color[3]);
+        });
+    }
+
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> Result<(), SoftwareRendererError> {
+        let file = std::fs::File::create(path)?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    /// Reads image saved with `save_png`
+    pub fn load_png<P: AsRef<Path>>(path: P) -> Result<RgbaImage, SoftwareRendererError> {
+        let file = std::fs::File::open(path)?;
+        let mut reader = png::Decoder::new(std::io::BufReader::new(file)).read_info()?;
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels)?;
+        if info.color_type != png::ColorType::Rgba || info.bit_depth != png::BitDepth::Eight {
+            return Err(SoftwareRendererError::UnsupportedFormat);
+        }
+        pixels.truncate(info.buffer_size());
+
+        Ok(RgbaImage {
+            width: info.width,
+            height: info.height,
+            pixels,
+        })
+    }
+}
+
+impl RgbaImage {
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+}
+
+impl Renderer for SoftwareRenderer {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.pixels = vec![0; width as usize * height as usize * 4];
+    }
+
+    fn render(&mut self, app_data: &AppData) {
+        self.clear(scene::CLEAR_COLOR);
+        if self.width == 0 || self.height == 0 {
+            return;
+        }
+        // Background layer first, entities cover it
+        for tile in scene::build_tiles(app_data, (self.width, self.height)) {
+            self.fill_quad(&tile);
+        }
+
+        let aspect_ratio = self.width as f32 / self.height as f32;
+        for quad in scene::build_quads(app_data, aspect_ratio, &self.sprites) {
+            self.fill_quad(&quad);
+        }
+
+        let overlay = scene::build_overlay(app_data, &self.atlas, (self.width, self.height));
+        for panel in &overlay.panels {
+            self.fill_quad(panel);
+        }
+        for glyph in &overlay.glyphs {
+            self.blend_glyph(glyph);
+        }
+
+        if let Some(minimap) = scene::build_minimap(app_data, (self.width, self.height)) {
+            self.fill_minimap(&minimap);
+        }
+    }
+}
+
+/// Entities and item of the first renderer only, later layers are switched on by their own tests
+#[cfg(test)]
+fn test_app_data(player_position: crate::game::common::Vector2F, scale: f32) -> AppData {
+    use crate::{
+        client_requests::{ChunkData, EntityCheckData, GroundItemData, ItemData, SpriteData},
+        game::{chunk::{Chunk, ChunkCoord}, common::Vector2F}
+    };
+
+    let entity = |id, name: &str, position, color, health| EntityCheckData {
+        position,
+        size: Vector2F::new(4.8, 4.8),
+        color,
+        id,
+        name: name.to_string(),
+        is_npc: id != 1,
+        health,
+        max_health: 10,
+        sprite: Some(if id == 1 { 0 } else { 1 }),
+        // NPC walks to the left
+        facing: (id != 1).then_some(crate::client_requests::MoveDirection::Left),
+    };
+    let mut app_data = AppData {
+        player_id: Some(1),
+        camera: super::camera::Camera::new(Vector2F::zero(), scale),
+        hide_debug_overlay: true,
+        layers: super::RenderLayers { terrain: false, sprites: false, names: false, minimap: false },
+        ..Default::default()
+    };
+    app_data.update_sprites(vec![
+        SpriteData { id: 0, name: "player".to_string() },
+        SpriteData { id: 1, name: "npc".to_string() },
+    ]);
+    app_data.update_world(
+        vec![
+            entity(1, "Player", player_position, [40, 120, 255], 10),
+            entity(2, "Tuna", Vector2F::new(110.0, 100.0), [255, 60, 60], 4),
+        ],
+        vec![GroundItemData { position: Vector2F::new(95.0, 95.0), item: ItemData { item_id: 1, name: "Coin".to_string(), count: 1 } }],
+    );
+    // Terrain around the player, rest of the view shows grid only
+    let chunks = (3..=4)
+        .flat_map(|x| (3..=4).map(move |y| ChunkData::from(&Chunk::generate(ChunkCoord::new(x, y)))))
+        .collect();
+    app_data.update_terrain(chunks, vec![]);
+    app_data
+}
+
+/// Set `SNIPPETS_UPDATE_GOLDEN=1` to write reference image again
+#[cfg(test)]
+fn assert_matches_golden(renderer: &SoftwareRenderer, name: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{name}.png"));
+    if std::env::var_os("SNIPPETS_UPDATE_GOLDEN").is_some() {
+        renderer.save_png(&path).unwrap();
+        return;
+    }
+
+    let golden = SoftwareRenderer::load_png(&path)
+        .unwrap_or_else(|e| panic!("Could not load '{}', reason='{e}'", path.display()));
+    if (golden.width(), golden.height()) != (renderer.width(), renderer.height()) || golden.pixels() != renderer.pixels() {
+        let actual_path = std::env::temp_dir().join(format!("{name}_actual.png"));
+        renderer.save_png(&actual_path).unwrap();
+        panic!("Render differs from '{}', actual image saved to '{}'", path.display(), actual_path.display());
+    }
+}
+
+#[test]
+fn test_software_renderer_camera_follows_player() {
+    use crate::game::common::Vector2F;
+
+    let mut renderer = SoftwareRenderer::new(96, 64);
+    renderer.render(&test_app_data(Vector2F::new(100.0, 100.0), 0.05));
+    assert_matches_golden(&renderer, "camera_follow");
+
+    // Player stays in the center, rest of the world moves
+    let player_pixel = renderer.pixel(50, 30);
+    let npc_pixel = renderer.pixel(68, 30);
+    renderer.render(&test_app_data(Vector2F::new(105.0, 100.0), 0.05));
+    assert_eq!(renderer.pixel(50, 30), player_pixel);
+    assert_ne!(renderer.pixel(68, 30), npc_pixel);
+    assert_matches_golden(&renderer, "camera_follow_moved");
+}
+
+#[test]
+fn test_software_renderer_zoom() {
+    use crate::game::common::Vector2F;
+
+    let mut renderer = SoftwareRenderer::new(96, 64);
+    renderer.render(&test_app_data(Vector2F::new(100.0, 100.0), 0.02));
+    assert_matches_golden(&renderer, "zoomed_out");
+
+    renderer.resize(64, 64);
+    renderer.render(&test_app_data(Vector2F::new(100.0, 100.0), 0.1));
+    assert_matches_golden(&renderer, "zoomed_in");
+}
+
+#[test]
+fn test_software_renderer_hud_and_chat() {
+    use crate::game::common::Vector2F;
+
+    let mut app_data = test_app_data(Vector2F::new(100.0, 100.0), 0.05);
+    app_data.layers.names = true;
+    app_data.hide_debug_overlay = false;
+    app_data.fps = 60.0;
+    app_data.ping = Some(std::time::Duration::from_millis(23));
+    app_data.push_chat("[Global] Tuna: Hello there!".to_string());
+    app_data.push_chat("[Whisper] Player: A message too long to fit in the chat panel".to_string());
+
+    let mut renderer = SoftwareRenderer::new(320, 240);
+    renderer.render(&app_data);
+    assert_matches_golden(&renderer, "hud_and_chat");
+}
+
+#[test]
+fn test_software_renderer_sprite_animation() {
+    use crate::game::common::Vector2F;
+
+    let mut app_data = test_app_data(Vector2F::new(100.0, 100.0), 0.05);
+    app_data.layers.sprites = true;
+    let mut renderer = SoftwareRenderer::new(320, 240);
+    renderer.render(&app_data);
+    assert_matches_golden(&renderer, "sprites");
+    let first_frame = renderer.pixels().to_vec();
+
+    // Moving NPC changes frame, idle player does not
+    app_data.animation_time = std::time::Duration::from_millis(150);
+    renderer.render(&app_data);
+    let npc_changed = (96..122).any(|y| (220..250).any(|x| {
+        let idx = (y * 320 + x) * 4;
+        renderer.pixels()[idx..idx + 4] != first_frame[idx..idx + 4]
+    }));
+    assert!(npc_changed);
+    let player_changed = (96..122).any(|y| (160..190).any(|x| {
+        let idx = (y * 320 + x) * 4;
+        renderer.pixels()[idx..idx + 4] != first_frame[idx..idx + 4]
+    }));
+    assert!(!player_changed);
+}
+
+#[test]
+fn test_software_renderer_terrain() {
+    use crate::game::common::Vector2F;
+
+    let mut app_data = test_app_data(Vector2F::new(100.0, 100.0), 0.05);
+    app_data.layers.terrain = true;
+    let mut renderer = SoftwareRenderer::new(320, 240);
+    renderer.render(&app_data);
+    assert_matches_golden(&renderer, "terrain");
+}
+
+#[test]
+fn test_software_renderer_minimap() {
+    use crate::game::common::Vector2F;
+
+    let mut app_data = test_app_data(Vector2F::new(100.0, 100.0), 0.05);
+    app_data.layers.minimap = true;
+    let mut renderer = SoftwareRenderer::new(320, 240);
+    renderer.render(&app_data);
+    assert_matches_golden(&renderer, "minimap");
+}
+
+#[test]
+fn test_software_renderer_png_roundtrip() {
+    let mut renderer = SoftwareRenderer::new(8, 4);
+    renderer.render(&AppData { camera: super::camera::Camera::new(crate::game::common::Vector2F::zero(), 0.05), ..Default::default() });
+    assert_eq!(renderer.pixel(3, 2), [linear_to_srgb(0.1), linear_to_srgb(0.1), linear_to_srgb(0.1), 255]);
+
+    let path = std::env::temp_dir().join(format!("snippets_render_{}.png", std::process::id()));
+    renderer.save_png(&path).unwrap();
+    let loaded = SoftwareRenderer::load_png(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!((loaded.width(), loaded.height()), (8, 4));
+    assert_eq!(loaded.pixels(), renderer.pixels());
+}
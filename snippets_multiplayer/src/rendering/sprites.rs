@@ -0,0 +1,189 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Duration
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::client_requests::MoveDirection;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SpriteError {
+    #[error("IoError, reason='{0}'")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Could not parse sprite metadata, reason='{0}'")]
+    ParseError(#[from] serde_json::Error),
+
+    #[error("Could not decode sprite image, reason='{0}'")]
+    ImageError(#[from] png::DecodingError),
+
+    #[error("Unsupported sprite image format, only 8 bit RGBA is read")]
+    UnsupportedFormat,
+
+    #[error("Frames of sprite '{0}' are outside of the image")]
+    InvalidSprite(String),
+}
+
+/// Frames of one sprite in the image. Directional sprites take 4 rows: down, up, left, right.
+/// First column is idle frame, next `moving_frames` columns are walking animation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpriteDefinition {
+    pub name: String,
+    pub row: u32,
+    #[serde(default)]
+    pub directional: bool,
+    #[serde(default)]
+    pub moving_frames: u32,
+}
+
+/// Content of sprite metadata file, image path is relative to it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpriteMetadata {
+    pub image: PathBuf,
+    /// Frames are squares of this many pixels
+    pub frame_size: u32,
+    pub frame_duration_ms: u64,
+    pub sprites: Vec<SpriteDefinition>,
+}
+
+/// Part of sprite image in texture coordinates, `uv_position` is top-left corner
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SpriteFrame {
+    pub uv_position: [f32; 2],
+    pub uv_size: [f32; 2],
+}
+
+/// Sprite image with its frames, looked up by sprite name
+#[derive(Clone)]
+pub struct SpriteSheet {
+    width: u32,
+    height: u32,
+    /// Row by row from the top, sRGB encoded with straight alpha
+    pixels: Vec<u8>,
+    frame_size: u32,
+    frame_duration: Duration,
+    sprites: HashMap<String, SpriteDefinition>,
+}
+
+impl SpriteSheet {
+    const DEFAULT_METADATA: &str = include_str!("../../assets/sprites.json");
+    const DEFAULT_IMAGE: &[u8] = include_bytes!("../../assets/sprites.png");
+
+    /// Embedded sprites
+    pub fn new() -> Result<Self, SpriteError> {
+        Self::from_parts(Self::DEFAULT_METADATA, Self::DEFAULT_IMAGE)
+    }
+
+    pub fn from_parts(metadata_json: &str, png: &[u8]) -> Result<Self, SpriteError> {
+        let metadata: SpriteMetadata = serde_json::from_str(metadata_json)?;
+
+        let mut reader = png::Decoder::new(std::io::Cursor::new(png)).read_info()?;
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels)?;
+        if info.color_type != png::ColorType::Rgba || info.bit_depth != png::BitDepth::Eight {
+            return Err(SpriteError::UnsupportedFormat);
+        }
+        pixels.truncate(info.buffer_size());
+
+        for sprite in &metadata.sprites {
+            let rows = if sprite.directional { 4 } else { 1 };
+            let fits = (sprite.row + rows) * metadata.frame_size <= info.height
+                && (1 + sprite.moving_frames) * metadata.frame_size <= info.width;
+            if !fits || metadata.frame_size == 0 {
+                return Err(SpriteError::InvalidSprite(sprite.name.clone()));
+            }
+        }
+
+        Ok(Self {
+            width: info.width,
+            height: info.height,
+            pixels,
+            frame_size: metadata.frame_size,
+            frame_duration: Duration::from_millis(metadata.frame_duration_ms.max(1)),
+            sprites: metadata.sprites.into_iter().map(|sprite| (sprite.name.clone(), sprite)).collect(),
+        })
+    }
+
+    /// Reads metadata file and image it points to
+    pub fn load<P: AsRef<Path>>(metadata_path: P) -> Result<Self, SpriteError> {
+        let metadata_path = metadata_path.as_ref();
+        let metadata_json = std::fs::read_to_string(metadata_path)?;
+        let metadata: SpriteMetadata = serde_json::from_str(&metadata_json)?;
+        let image_path = metadata_path.parent().unwrap_or(Path::new(".")).join(metadata.image);
+        Self::from_parts(&metadata_json, &std::fs::read(image_path)?)
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let idx = ((y * self.width + x) * 4) as usize;
+        [self.pixels[idx], self.pixels[idx + 1], self.pixels[idx + 2], self.pixels[idx + 3]]
+    }
+
+    /// Idle frame or walking animation frame at given time, None for unknown sprite
+    pub fn frame(&self, sprite: &str, facing: MoveDirection, is_moving: bool, time: Duration) -> Option<SpriteFrame> {
+        let definition = self.sprites.get(sprite)?;
+        let row = match (definition.directional, facing) {
+            (false, _) | (true, MoveDirection::Down) => definition.row,
+            (true, MoveDirection::Up) => definition.row + 1,
+            (true, MoveDirection::Left) => definition.row + 2,
+            (true, MoveDirection::Right) => definition.row + 3,
+        };
+        let column = if is_moving && definition.moving_frames > 0 {
+            let frame_idx = time.as_millis() / self.frame_duration.as_millis();
+            1 + (frame_idx % definition.moving_frames as u128) as u32
+        } else {
+            0
+        };
+
+        Some(SpriteFrame {
+            uv_position: [(column * self.frame_size) as f32 / self.width as f32, (row * self.frame_size) as f32 / self.height as f32],
+            uv_size: [self.frame_size as f32 / self.width as f32, self.frame_size as f32 / self.height as f32],
+        })
+    }
+}
+
+#[test]
+fn test_sprites_frames() {
+    let sheet = SpriteSheet::new().unwrap();
+    let frame_size = 16.0;
+    let uv_row = |frame: SpriteFrame| (frame.uv_position[1] * sheet.height() as f32 / frame_size).round() as u32;
+    let uv_column = |frame: SpriteFrame| (frame.uv_position[0] * sheet.width() as f32 / frame_size).round() as u32;
+
+    let idle = sheet.frame("player", MoveDirection::Left, false, Duration::from_secs(7)).unwrap();
+    assert_eq!((uv_row(idle), uv_column(idle)), (2, 0));
+
+    // Walking animation cycles through moving frames
+    let columns: Vec<_> = [0, 150, 300, 450].into_iter()
+        .map(|ms| uv_column(sheet.frame("npc", MoveDirection::Right, true, Duration::from_millis(ms)).unwrap()))
+        .collect();
+    assert_eq!(columns, vec![1, 2, 1, 2]);
+    assert_eq!(uv_row(sheet.frame("npc", MoveDirection::Right, true, Duration::ZERO).unwrap()), 7);
+
+    // Items look the same in every direction
+    assert_eq!(sheet.frame("item", MoveDirection::Up, false, Duration::ZERO), sheet.frame("item", MoveDirection::Down, true, Duration::ZERO));
+    assert!(sheet.frame("dragon", MoveDirection::Down, false, Duration::ZERO).is_none());
+}
+
+#[test]
+fn test_sprites_invalid_metadata() {
+    let metadata = r#"{ "image": "sprites.png", "frame_size": 16, "frame_duration_ms": 100, "sprites": [{ "name": "huge", "row": 40 }] }"#;
+    assert!(matches!(SpriteSheet::from_parts(metadata, SpriteSheet::DEFAULT_IMAGE), Err(SpriteError::InvalidSprite(name)) if name == "huge"));
+    assert!(matches!(SpriteSheet::from_parts("{}", SpriteSheet::DEFAULT_IMAGE), Err(SpriteError::ParseError(_))));
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/sprites.json");
+    assert_eq!(SpriteSheet::load(path).unwrap().pixels(), SpriteSheet::new().unwrap().pixels());
+}
@@ -0,0 +1,182 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, List, ListItem, Widget}
+};
+
+use crate::game::{common::Vector2F, world::World};
+
+use super::{AppData, EntityView, ItemView};
+
+/// Terminal cells are about twice as tall as wide
+const CELL_ASPECT: f32 = 2.0;
+
+/// World around the player drawn with characters, same camera model as GPU renderer
+pub struct WorldWidget<'a> {
+    app_data: &'a AppData,
+}
+
+/// Side panel with entities visible in the world view
+pub struct EntityListWidget<'a> {
+    app_data: &'a AppData,
+}
+
+impl<'a> WorldWidget<'a> {
+    pub fn new(app_data: &'a AppData) -> Self {
+        Self {
+            app_data
+        }
+    }
+
+    fn aspect_ratio(area: Rect) -> f32 {
+        area.width as f32 / (area.height as f32 * CELL_ASPECT)
+    }
+
+    /// Cell column and row, may be outside of area
+    fn ndc_to_cell(ndc: Vector2F, area: Rect) -> (i32, i32) {
+        (
+            ((ndc.x + 1.0) / 2.0 * area.width as f32).floor() as i32,
+            ((1.0 - ndc.y) / 2.0 * area.height as f32).floor() as i32
+        )
+    }
+
+    fn cell_to_ndc(column: u16, row: u16, area: Rect) -> Vector2F {
+        Vector2F::new(
+            (column as f32 + 0.5) / area.width as f32 * 2.0 - 1.0,
+            1.0 - (row as f32 + 0.5) / area.height as f32 * 2.0
+        )
+    }
+
+    fn color_style(color: [f32; 3]) -> Style {
+        let [r, g, b] = color.map(|channel| (channel.clamp(0.0, 1.0) * 255.0) as u8);
+        Style::default().fg(Color::Rgb(r, g, b))
+    }
+
+    fn symbol(&self, entity: &EntityView) -> (char, Style) {
+        let style = Self::color_style(entity.color);
+        if Some(entity.id) == self.app_data.player_id {
+            return ('@', style.add_modifier(Modifier::BOLD));
+        }
+        if entity.is_npc { ('n', style) } else { ('P', style) }
+    }
+
+    /// Cells covered by world rect, at least one even when zoomed out
+    fn fill_cells(&self, position: Vector2F, size: Vector2F, (symbol, style): (char, Style), area: Rect, buf: &mut Buffer) {
+        let aspect_ratio = Self::aspect_ratio(area);
+        let (left, bottom) = Self::ndc_to_cell(self.app_data.world_to_ndc(position, aspect_ratio), area);
+        let (right, top) = Self::ndc_to_cell(self.app_data.world_to_ndc(position + size, aspect_ratio), area);
+        for row in top..bottom.max(top + 1) {
+            for column in left..right.max(left + 1) {
+                if (0..area.width as i32).contains(&column) && (0..area.height as i32).contains(&row) {
+                    buf[(area.x + column as u16, area.y + row as u16)].set_char(symbol).set_style(style);
+                }
+            }
+        }
+    }
+
+    fn render_grid(&self, area: Rect, buf: &mut Buffer) {
+        let aspect_ratio = Self::aspect_ratio(area);
+        let grid_style = Style::default().fg(Color::DarkGray);
+        let half_cell = Vector2F::new(1.0 / area.width as f32, 1.0 / area.height as f32);
+
+        for row in 0..area.height {
+            for column in 0..area.width {
+                // Dot where tile corner falls into the cell
+                let center = Self::cell_to_ndc(column, row, area);
+                let min = self.app_data.ndc_to_world(center - half_cell, aspect_ratio);
+                let max = self.app_data.ndc_to_world(center + half_cell, aspect_ratio);
+                let has_corner = |min: f32, max: f32| (min / World::TILE_SIZE_SIDE).floor() != (max / World::TILE_SIZE_SIDE).floor();
+                if has_corner(min.x, max.x) && has_corner(min.y, max.y) {
+                    buf[(area.x + column, area.y + row)].set_char('·').set_style(grid_style);
+                }
+            }
+        }
+    }
+}
+
+impl Widget for WorldWidget<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.is_empty() || self.app_data.camera.scale <= 0.0 {
+            return;
+        }
+        self.render_grid(area, buf);
+
+        let item_style = Self::color_style(ItemView::COLOR);
+        for item in &self.app_data.items {
+            self.fill_cells(item.position, ItemView::SIZE, ('*', item_style), area, buf);
+        }
+        for entity in &self.app_data.entities {
+            self.fill_cells(entity.position, entity.size, self.symbol(entity), area, buf);
+        }
+    }
+}
+
+impl<'a> EntityListWidget<'a> {
+    pub fn new(app_data: &'a AppData) -> Self {
+        Self {
+            app_data
+        }
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        let mut entities: Vec<_> = self.app_data.entities.iter().collect();
+        entities.sort_by_key(|entity| entity.id);
+        entities.into_iter()
+            .map(|entity| {
+                let marker = if Some(entity.id) == self.app_data.player_id { '@' } else { ' ' };
+                let kind = if entity.is_npc { "npc" } else { "player" };
+                format!("{marker}{:>4} {} ({kind})", entity.id, entity.name)
+            })
+            .collect()
+    }
+}
+
+impl Widget for EntityListWidget<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let items: Vec<ListItem> = self.lines().into_iter().map(|line| ListItem::new(Line::from(line))).collect();
+        let title = format!(" Entities ({}) ", items.len());
+        List::new(items)
+            .block(Block::bordered().title(title))
+            .render(area, buf);
+    }
+}
+
+#[cfg(test)]
+fn test_app_data() -> AppData {
+    let mut app_data = AppData {
+        player_id: Some(1),
+        camera: super::camera::Camera::new(Vector2F::new(10.0, 10.0), 0.05),
+        ..Default::default()
+    };
+    for (id, position, is_npc) in [(1, Vector2F::new(10.0, 10.0), false), (2, Vector2F::new(15.0, 10.0), true)] {
+        app_data.entities.push(EntityView { position, size: Vector2F::new(4.8, 4.8), color: [1.0, 0.0, 0.0], health: 1, max_health: 1, id, name: format!("Entity{id}"), is_npc, ..Default::default() });
+    }
+    app_data
+}
+
+#[test]
+fn test_terminal_world_view_draws_player_at_center() {
+    let app_data = test_app_data();
+    let area = Rect::new(0, 0, 40, 20);
+    let mut buf = Buffer::empty(area);
+    WorldWidget::new(&app_data).render(area, &mut buf);
+
+    // Player spans from the center to the right and up
+    assert_eq!(buf[(20, 9)].symbol(), "@");
+    assert_ne!(buf[(19, 10)].symbol(), "@");
+    // NPC is next to the player, one tile to the right
+    let npc_row: String = (0..area.width).map(|column| buf[(column, 9)].symbol().to_string()).collect();
+    assert!(npc_row.contains('n'), "'{npc_row}'");
+    assert!(npc_row.find('@') < npc_row.find('n'));
+}
+
+#[test]
+fn test_terminal_entity_list() {
+    let app_data = test_app_data();
+    assert_eq!(EntityListWidget::new(&app_data).lines(), vec![
+        "@   1 Entity1 (player)".to_string(),
+        "    2 Entity2 (npc)".to_string(),
+    ]);
+}
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+
+use crate::game::common::Vector2F;
+
+/// DejaVu Sans Mono, license in `assets/DejaVuSansMono-LICENSE.txt`
+const EMBEDDED_FONT: &[u8] = include_bytes!("../../assets/DejaVuSansMono.ttf");
+
+#[derive(Debug, thiserror::Error)]
+pub enum TextError {
+    #[error("Could not load font, reason='{0}'")]
+    FontError(&'static str),
+}
+
+/// Printable ASCII, other characters are drawn as `FALLBACK_CHAR`
+const FIRST_CHAR: char = ' ';
+const LAST_CHAR: char = '~';
+const FALLBACK_CHAR: char = '?';
+
+/// Glyph bitmap in atlas and its placement relative to pen position on baseline
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Glyph {
+    pub atlas_x: u32,
+    pub atlas_y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Left edge of bitmap from pen position
+    pub offset_x: f32,
+    /// Bottom edge of bitmap above baseline, negative for descenders
+    pub offset_y: f32,
+    pub advance: f32,
+}
+
+/// Single channel coverage texture with all glyphs rasterized at one size
+pub struct GlyphAtlas {
+    width: u32,
+    height: u32,
+    /// Row by row from the top
+    coverage: Vec<u8>,
+    glyphs: HashMap<char, Glyph>,
+    ascent: f32,
+    line_height: f32,
+}
+
+/// Textured rect in NDC, `uv_position` is top-left corner of glyph in atlas
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GlyphQuad {
+    pub position: Vector2F,
+    pub size: Vector2F,
+    pub uv_position: [f32; 2],
+    pub uv_size: [f32; 2],
+    pub color: [f32; 4],
+}
+
+impl GlyphAtlas {
+    pub const DEFAULT_FONT_SIZE: f32 = 14.0;
+    const WIDTH: u32 = 256;
+    /// Gap between glyphs, so sampling never bleeds into neighbour
+    const PADDING: u32 = 1;
+
+    /// Embedded font at default size
+    pub fn new() -> Result<Self, TextError> {
+        Self::from_font_bytes(EMBEDDED_FONT, Self::DEFAULT_FONT_SIZE)
+    }
+
+    pub fn from_font_bytes(font_bytes: &[u8], font_size: f32) -> Result<Self, TextError> {
+        let font = fontdue::Font::from_bytes(font_bytes, fontdue::FontSettings::default()).map_err(TextError::FontError)?;
+        let line_metrics = font.horizontal_line_metrics(font_size).ok_or(TextError::FontError("Font has no horizontal metrics"))?;
+
+        // Shelf packing, glyphs go left to right in rows as tall as the tallest glyph
+        let mut bitmaps = vec![];
+        let mut glyphs = HashMap::new();
+        let (mut pen_x, mut pen_y, mut row_height) = (Self::PADDING, Self::PADDING, 0);
+        for character in FIRST_CHAR..=LAST_CHAR {
+            let (metrics, bitmap) = font.rasterize(character, font_size);
+            let (width, height) = (metrics.width as u32, metrics.height as u32);
+            if pen_x + width + Self::PADDING > Self::WIDTH {
+                pen_x = Self::PADDING;
+                pen_y += row_height + Self::PADDING;
+                row_height = 0;
+            }
+
+            glyphs.insert(character, Glyph {
+                atlas_x: pen_x,
+                atlas_y: pen_y,
+                width,
+                height,
+                offset_x: metrics.xmin as f32,
+                offset_y: metrics.ymin as f32,
+                advance: metrics.advance_width,
+            });
+            bitmaps.push((pen_x, pen_y, width, bitmap));
+            pen_x += width + Self::PADDING;
+            row_height = row_height.max(height);
+        }
+
+        let height = (pen_y + row_height + Self::PADDING).next_power_of_two();
+        let mut coverage = vec![0; (Self::WIDTH * height) as usize];
+        for (x, y, width, bitmap) in bitmaps {
+            if width == 0 {
+                continue;
+            }
+            for (row, bitmap_row) in bitmap.chunks_exact(width as usize).enumerate() {
+                let start = ((y + row as u32) * Self::WIDTH + x) as usize;
+                coverage[start..start + width as usize].copy_from_slice(bitmap_row);
+            }
+        }
+
+        Ok(Self {
+            width: Self::WIDTH,
+            height,
+            coverage,
+            glyphs,
+            ascent: line_metrics.ascent,
+            line_height: line_metrics.new_line_size,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn coverage(&self) -> &[u8] {
+        &self.coverage
+    }
+
+    pub fn coverage_at(&self, x: u32, y: u32) -> u8 {
+        self.coverage[(y * self.width + x) as usize]
+    }
+
+    pub fn ascent(&self) -> f32 {
+        self.ascent
+    }
+
+    pub fn line_height(&self) -> f32 {
+        self.line_height
+    }
+
+    pub fn glyph(&self, character: char) -> &Glyph {
+        self.glyphs.get(&character)
+            .or_else(|| self.glyphs.get(&FALLBACK_CHAR))
+            .expect("Fallback glyph should be in atlas")
+    }
+
+    /// Width in pixels, pen positions are rounded like in `layout`
+    pub fn text_width(&self, text: &str) -> f32 {
+        text.chars().map(|character| self.glyph(character).advance.round()).sum()
+    }
+
+    /// Appends quads of `text` starting at `baseline`, given in pixels from top-left corner of viewport
+    pub fn layout(&self, text: &str, baseline: Vector2F, color: [f32; 4], viewport: (u32, u32), quads: &mut Vec<GlyphQuad>) {
+        let (viewport_width, viewport_height) = (viewport.0 as f32, viewport.1 as f32);
+        let mut pen = Vector2F::new(baseline.x.round(), baseline.y.round());
+
+        for character in text.chars() {
+            let glyph = self.glyph(character);
+            if glyph.width > 0 && glyph.height > 0 {
+                let left = pen.x + glyph.offset_x;
+                let bottom = pen.y - glyph.offset_y;
+                quads.push(GlyphQuad {
+                    position: Vector2F::new(left / viewport_width * 2.0 - 1.0, 1.0 - bottom / viewport_height * 2.0),
+                    size: Vector2F::new(glyph.width as f32 / viewport_width * 2.0, glyph.height as f32 / viewport_height * 2.0),
+                    uv_position: [glyph.atlas_x as f32 / self.width as f32, glyph.atlas_y as f32 / self.height as f32],
+                    uv_size: [glyph.width as f32 / self.width as f32, glyph.height as f32 / self.height as f32],
+                    color,
+                });
+            }
+            pen.x += glyph.advance.round();
+        }
+    }
+}
+
+#[test]
+fn test_text_glyph_atlas() {
+    let atlas = GlyphAtlas::new().unwrap();
+    assert!(atlas.height().is_power_of_two());
+    assert_eq!(atlas.coverage().len(), (atlas.width() * atlas.height()) as usize);
+
+    // Monospace font, unknown characters fall back to '?'
+    assert_eq!(atlas.glyph('i').advance, atlas.glyph('W').advance);
+    assert_eq!(atlas.glyph('ł'), atlas.glyph('?'));
+    assert_eq!(atlas.text_width("abc"), 3.0 * atlas.glyph('a').advance.round());
+
+    let glyph = atlas.glyph('W');
+    let covered = (0..glyph.height)
+        .flat_map(|y| (0..glyph.width).map(move |x| (x, y)))
+        .filter(|(x, y)| atlas.coverage_at(glyph.atlas_x + x, glyph.atlas_y + y) > 0)
+        .count();
+    assert!(covered > 0);
+    assert_eq!(atlas.glyph(' ').width, 0);
+}
+
+#[test]
+fn test_text_layout() {
+    let atlas = GlyphAtlas::new().unwrap();
+    let mut quads = vec![];
+    atlas.layout("A b", Vector2F::new(10.0, 20.0), [1.0; 4], (100, 50), &mut quads);
+
+    // Space has no bitmap
+    assert_eq!(quads.len(), 2);
+    let glyph = atlas.glyph('A');
+    let left = 10.0 + glyph.offset_x;
+    assert!((quads[0].position.x - (left / 100.0 * 2.0 - 1.0)).abs() < 1e-6);
+    assert!((quads[0].size.y - glyph.height as f32 / 50.0 * 2.0).abs() < 1e-6);
+    assert!(quads[1].position.x > quads[0].position.x);
+}
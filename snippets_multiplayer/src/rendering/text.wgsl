@@ -0,0 +1,41 @@
+// Corner of unit quad, matches Vertex struct in Rust.
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+};
+
+// Per glyph rect in NDC and its place in atlas, matches GlyphInstance struct in Rust.
+struct InstanceInput {
+    @location(1) position: vec2<f32>,
+    @location(2) size: vec2<f32>,
+    @location(3) uv_position: vec2<f32>,
+    @location(4) uv_size: vec2<f32>,
+    @location(5) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+// Single channel coverage of all glyphs.
+@group(0) @binding(0)
+var atlas_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var atlas_sampler: sampler;
+
+@vertex
+fn vs_main(vertex: VertexInput, instance: InstanceInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(instance.position + vertex.position * instance.size, 0.0, 1.0);
+    // Atlas rows go from the top, quad corners from the bottom.
+    out.uv = instance.uv_position + vec2<f32>(vertex.position.x, 1.0 - vertex.position.y) * instance.uv_size;
+    out.color = instance.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let coverage = textureSample(atlas_texture, atlas_sampler, in.uv).r;
+    return vec4<f32>(in.color.rgb, in.color.a * coverage);
+}
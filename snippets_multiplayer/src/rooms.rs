@@ -0,0 +1,360 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant}
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game::world::{World, WorldSettings},
+    interest::InterestArea,
+    metrics::SharedMetrics,
+    session_registry::{SessionRegistry, SharedSessionRegistry},
+    world_handle::{WorldHandle, WorldTaskHandler}
+};
+
+pub type RoomId = u32;
+pub type SharedRoomRegistry = Arc<Mutex<RoomRegistry>>;
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum RoomError {
+    #[error("Room id={0} does not exist")]
+    RoomNotExist(RoomId),
+
+    #[error("Room limit {0} reached")]
+    TooManyRooms(usize),
+
+    #[error("Room name must have 1..={0} characters")]
+    InvalidName(usize),
+
+    #[error("Tick rate must be in 1..={0} ticks per second")]
+    InvalidTickRate(u32),
+
+    #[error("Room registry is poisoned")]
+    RegistryPoisoned,
+
+    #[error(transparent)]
+    WorldHandleError(#[from] crate::world_handle::WorldHandleError),
+
+    #[error("World error {0:?}")]
+    WorldError(crate::game::world::WorldError),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomInfo {
+    pub id: RoomId,
+    pub name: String,
+    pub players: usize,
+}
+
+/// Applied to every room created by registry
+#[derive(Debug, Copy, Clone)]
+pub struct RoomSettings {
+    pub interest_area: InterestArea,
+    pub tick_interval: Duration,
+    pub world_settings: WorldSettings,
+    /// Rooms without players for this long are closed, except the main room
+    pub idle_timeout: Duration,
+}
+
+/// Independent world with own clients, ticking on its own task
+pub struct Room {
+    pub id: RoomId,
+    pub name: String,
+    pub world: WorldHandle,
+    pub sessions: SharedSessionRegistry,
+    world_task: WorldTaskHandler,
+    tick_interval_sender: tokio::sync::watch::Sender<Duration>,
+    metrics: SharedMetrics,
+    /// Set when room was found without players, new rooms start empty
+    empty_since: Option<Instant>,
+}
+
+pub struct RoomRegistry {
+    rooms: HashMap<RoomId, Room>,
+    next_room_id: RoomId,
+    settings: RoomSettings,
+    metrics: SharedMetrics,
+}
+
+impl Default for RoomSettings {
+    fn default() -> Self {
+        Self {
+            interest_area: InterestArea::default(),
+            tick_interval: Room::DEFAULT_TICK_INTERVAL,
+            world_settings: WorldSettings::default(),
+            idle_timeout: Room::DEFAULT_IDLE_TIMEOUT,
+        }
+    }
+}
+
+impl Room {
+    // const DEFAULT_TICK_INTERVAL: Duration = Duration::from_millis(250); // Slow for testing purpose
+    pub const DEFAULT_TICK_INTERVAL: Duration = Duration::from_millis(32);
+    pub const MAX_TICK_RATE: u32 = 200;
+    pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+    /// Must be called within tokio runtime
+    fn spawn(id: RoomId, name: String, settings: &RoomSettings, metrics: SharedMetrics) -> Self {
+        let (tick_interval_sender, tick_interval_receiver) = tokio::sync::watch::channel(settings.tick_interval);
+        let tick_metrics = metrics.clone();
+        let world = World::new().with_settings(settings.world_settings);
+
+        let (world, world_task) = WorldHandle::spawn(world, tick_interval_receiver, metrics.clone(), move |world| {
+            let players = world.iter_entities().filter(|e| e.is_player()).count();
+            let npcs = world.iter_entities().count() - players;
+            tick_metrics.world_entities.set(format!("room=\"{id}\",kind=\"player\""), players as i64);
+            tick_metrics.world_entities.set(format!("room=\"{id}\",kind=\"npc\""), npcs as i64);
+        });
+
+        Self {
+            id,
+            name,
+            world,
+            sessions: SessionRegistry::with_default_interest_area(settings.interest_area).into_shared(),
+            world_task,
+            tick_interval_sender,
+            metrics,
+            empty_since: Some(Instant::now()),
+        }
+    }
+
+    pub fn tick_interval(&self) -> Duration {
+        *self.tick_interval_sender.borrow()
+    }
+
+    /// Takes effect from the next tick
+    pub fn set_tick_rate(&self, ticks_per_second: u32) -> Result<Duration, RoomError> {
+        let tick_interval = Self::tick_interval_from_rate(ticks_per_second)?;
+        self.tick_interval_sender.send_replace(tick_interval);
+        Ok(tick_interval)
+    }
+
+    pub fn tick_interval_from_rate(ticks_per_second: u32) -> Result<Duration, RoomError> {
+        if !(1..=Self::MAX_TICK_RATE).contains(&ticks_per_second) {
+            return Err(RoomError::InvalidTickRate(Self::MAX_TICK_RATE));
+        }
+        Ok(Duration::from_secs(1) / ticks_per_second)
+    }
+
+    pub fn info(&self) -> RoomInfo {
+        RoomInfo {
+            id: self.id,
+            name: self.name.clone(),
+            players: self.sessions.lock().map_or(0, |sessions| sessions.len()),
+        }
+    }
+
+    /// Stop ticking, returns final state of the world.
+    /// Gauges of the room are removed once no tick can set them again.
+    pub async fn shutdown(self) -> Result<World, tokio::task::JoinError> {
+        log::debug!("Room {} shutting down...", self.id);
+        let world = self.world_task.shutdown().await;
+        self.metrics.world_entities.remove_prefixed(&format!("room=\"{}\"", self.id));
+        world
+    }
+}
+
+impl RoomRegistry {
+    pub const MAIN_ROOM_ID: RoomId = 0;
+    pub const MAX_ROOMS: usize = 32;
+    pub const MAX_ROOM_NAME_LENGTH: usize = 32;
+
+    /// Registry with main room, where clients land after connecting
+    pub fn new(interest_area: InterestArea) -> Self {
+        Self::with_settings(RoomSettings {
+            interest_area,
+            ..Default::default()
+        })
+    }
+
+    pub fn with_settings(settings: RoomSettings) -> Self {
+        let mut registry = Self {
+            rooms: HashMap::new(),
+            next_room_id: Self::MAIN_ROOM_ID,
+            settings,
+            metrics: crate::metrics::Metrics::new().into_shared(),
+        };
+        registry.create_room("main").expect("Main room should be created");
+        registry
+    }
+
+    pub fn into_shared(self) -> SharedRoomRegistry {
+        Arc::new(Mutex::new(self))
+    }
+
+    /// Shared by all rooms and their clients
+    pub fn metrics(&self) -> SharedMetrics {
+        self.metrics.clone()
+    }
+
+    pub fn create_room(&mut self, name: &str) -> Result<RoomId, RoomError> {
+        let name = name.trim();
+        if name.is_empty() || name.chars().count() > Self::MAX_ROOM_NAME_LENGTH {
+            return Err(RoomError::InvalidName(Self::MAX_ROOM_NAME_LENGTH));
+        }
+        if self.rooms.len() >= Self::MAX_ROOMS {
+            return Err(RoomError::TooManyRooms(Self::MAX_ROOMS));
+        }
+
+        let room_id = self.next_room_id;
+        self.next_room_id += 1;
+        self.rooms.insert(room_id, Room::spawn(room_id, name.to_string(), &self.settings, self.metrics.clone()));
+        log::info!("Room {room_id} '{name}' created");
+        Ok(room_id)
+    }
+
+    /// Recreates room saved before restart under its previous id, existing room is reused
+    pub fn restore_room(&mut self, room_id: RoomId, name: &str) -> Result<&Room, RoomError> {
+        if !self.rooms.contains_key(&room_id) {
+            if self.rooms.len() >= Self::MAX_ROOMS {
+                return Err(RoomError::TooManyRooms(Self::MAX_ROOMS));
+            }
+            self.next_room_id = self.next_room_id.max(room_id + 1);
+            self.rooms.insert(room_id, Room::spawn(room_id, name.to_string(), &self.settings, self.metrics.clone()));
+            log::info!("Room {room_id} '{name}' restored");
+        }
+        self.get(room_id)
+    }
+
+    pub fn get(&self, room_id: RoomId) -> Result<&Room, RoomError> {
+        self.rooms.get(&room_id).ok_or(RoomError::RoomNotExist(room_id))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Room> {
+        self.rooms.values()
+    }
+
+    /// Rooms sorted by id
+    pub fn list(&self) -> Vec<RoomInfo> {
+        let mut rooms: Vec<RoomInfo> = self.rooms.values().map(|room| room.info()).collect();
+        rooms.sort_by_key(|room| room.id);
+        rooms
+    }
+
+    /// Main room is never removed. Caller should await `Room::shutdown`.
+    pub fn remove_room(&mut self, room_id: RoomId) -> Option<Room> {
+        if room_id == Self::MAIN_ROOM_ID {
+            return None;
+        }
+        self.rooms.remove(&room_id)
+    }
+
+    /// Room is removed when nobody plays there anymore. Caller should await `Room::shutdown`.
+    pub fn remove_room_if_empty(&mut self, room_id: RoomId) -> Option<Room> {
+        let is_empty = self.rooms.get(&room_id)?.sessions.lock().is_ok_and(|sessions| sessions.is_empty());
+        if is_empty {
+            self.remove_room(room_id)
+        } else {
+            None
+        }
+    }
+
+    /// Rooms created or restored but never joined are closed too. Caller should await `Room::shutdown`.
+    pub fn remove_idle_rooms(&mut self) -> Vec<Room> {
+        let now = Instant::now();
+        let mut idle = vec![];
+        for room in self.rooms.values_mut() {
+            let is_empty = room.sessions.lock().is_ok_and(|sessions| sessions.is_empty());
+            room.empty_since = if is_empty { room.empty_since.or(Some(now)) } else { None };
+            if room.empty_since.is_some_and(|empty_since| now - empty_since >= self.settings.idle_timeout) {
+                idle.push(room.id);
+            }
+        }
+        idle.into_iter().filter_map(|room_id| self.remove_room(room_id)).collect()
+    }
+
+    pub fn drain(&mut self) -> Vec<Room> {
+        self.rooms.drain().map(|(_, room)| room).collect()
+    }
+}
+
+#[tokio::test]
+async fn test_rooms_create_and_list() {
+    let mut rooms = RoomRegistry::new(InterestArea::default());
+    let room_id = rooms.create_room(" Arena ").unwrap();
+
+    assert_eq!(rooms.list(), vec![
+        RoomInfo { id: RoomRegistry::MAIN_ROOM_ID, name: "main".to_string(), players: 0 },
+        RoomInfo { id: room_id, name: "Arena".to_string(), players: 0 },
+    ]);
+    assert_eq!(rooms.create_room("  ").err(), Some(RoomError::InvalidName(RoomRegistry::MAX_ROOM_NAME_LENGTH)));
+    assert_eq!(rooms.get(123).err(), Some(RoomError::RoomNotExist(123)));
+
+    assert!(rooms.remove_room(RoomRegistry::MAIN_ROOM_ID).is_none());
+    // Removed room keeps ticking until shut down, its gauges go only then
+    let room = rooms.remove_room(room_id).unwrap();
+    tokio::time::sleep(Room::DEFAULT_TICK_INTERVAL * 3).await;
+    assert!(rooms.metrics().render().contains(&format!("room=\"{room_id}\"")));
+    room.shutdown().await.unwrap();
+    assert!(!rooms.metrics().render().contains(&format!("room=\"{room_id}\"")));
+
+    // Rooms with players and the main room stay
+    let room_id = rooms.create_room("Arena").unwrap();
+    let (channels, _receivers) = crate::session_registry::SessionChannels::new();
+    rooms.get(room_id).unwrap().sessions.lock().unwrap().register(1, "127.0.0.1:0".parse().unwrap(), channels);
+    assert!(rooms.remove_room_if_empty(room_id).is_none());
+    rooms.get(room_id).unwrap().sessions.lock().unwrap().unregister(1);
+    rooms.remove_room_if_empty(room_id).unwrap().shutdown().await.unwrap();
+    assert!(rooms.remove_room_if_empty(RoomRegistry::MAIN_ROOM_ID).is_none());
+
+    for room in rooms.drain() {
+        room.shutdown().await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_rooms_idle_are_removed() {
+    let mut rooms = RoomRegistry::with_settings(RoomSettings { idle_timeout: Duration::ZERO, ..Default::default() });
+    let never_joined = rooms.create_room("Arena").unwrap();
+    let joined = rooms.create_room("Dojo").unwrap();
+    let (channels, _receivers) = crate::session_registry::SessionChannels::new();
+    rooms.get(joined).unwrap().sessions.lock().unwrap().register(1, "127.0.0.1:0".parse().unwrap(), channels);
+
+    // Main room and room with player stay
+    let removed = rooms.remove_idle_rooms();
+    assert_eq!(removed.iter().map(|room| room.id).collect::<Vec<_>>(), vec![never_joined]);
+    for room in removed {
+        room.shutdown().await.unwrap();
+    }
+    assert_eq!(rooms.list().len(), 2);
+
+    for room in rooms.drain() {
+        room.shutdown().await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_rooms_tick_independently() {
+    use crate::game::common::Vector2F;
+
+    let mut rooms = RoomRegistry::new(InterestArea::default());
+    let room_id = rooms.create_room("Arena").unwrap();
+    let arena_world = rooms.get(room_id).unwrap().world.clone();
+    let player_id = arena_world.execute(|world| {
+        let player_id = world.create_entity_player("Player", Vector2F::new(0.0, 0.0), Vector2F::new(4.8, 4.8)).unwrap();
+        world.try_start_move_entity_to(player_id, Vector2F::new(5.0, 0.0)).unwrap();
+        player_id
+    }).await.unwrap();
+
+    tokio::time::sleep(Room::DEFAULT_TICK_INTERVAL * 20).await;
+    assert_eq!(arena_world.snapshot().get_entity_by_id(player_id).unwrap().position, Vector2F::new(5.0, 0.0));
+    assert_eq!(rooms.get(RoomRegistry::MAIN_ROOM_ID).unwrap().world.snapshot().iter_entities().count(), 0);
+
+    for room in rooms.drain() {
+        room.shutdown().await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_rooms_change_tick_rate() {
+    let rooms = RoomRegistry::new(InterestArea::default());
+    let room = rooms.get(RoomRegistry::MAIN_ROOM_ID).unwrap();
+    assert_eq!(room.tick_interval(), Room::DEFAULT_TICK_INTERVAL);
+
+    assert_eq!(room.set_tick_rate(50), Ok(Duration::from_millis(20)));
+    assert_eq!(room.tick_interval(), Duration::from_millis(20));
+    assert_eq!(room.set_tick_rate(0), Err(RoomError::InvalidTickRate(Room::MAX_TICK_RATE)));
+    assert_eq!(room.set_tick_rate(Room::MAX_TICK_RATE + 1), Err(RoomError::InvalidTickRate(Room::MAX_TICK_RATE)));
+}
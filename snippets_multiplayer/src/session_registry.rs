@@ -0,0 +1,169 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex}
+};
+
+use crate::{
+    chat::FloodGuard,
+    client_requests::ClientResponse,
+    game::world::EntityId,
+    interest::{InterestArea, InterestState}
+};
+
+pub type SharedSessionRegistry = Arc<Mutex<SessionRegistry>>;
+
+/// Senders reaching the task of one client, kept when client moves between rooms
+#[derive(Debug, Clone)]
+pub struct SessionChannels {
+    push_sender: tokio::sync::mpsc::Sender<ClientResponse>,
+    /// Kick reason, delivered even when push queue is full
+    close_sender: tokio::sync::watch::Sender<Option<String>>,
+}
+
+/// Receiving ends, owned by the task of the client
+pub struct SessionReceivers {
+    pub pushed: tokio::sync::mpsc::Receiver<ClientResponse>,
+    /// Session sends `Kicked` with the reason and closes once it is set
+    pub closed: tokio::sync::watch::Receiver<Option<String>>,
+}
+
+/// Connected client, able to receive messages pushed by server
+#[derive(Debug)]
+pub struct SessionEntry {
+    pub address: std::net::SocketAddr,
+    channels: SessionChannels,
+    pub chat_flood_guard: FloodGuard,
+    pub interest: InterestState,
+}
+
+impl SessionChannels {
+    pub fn new() -> (Self, SessionReceivers) {
+        let (push_sender, pushed) = tokio::sync::mpsc::channel(SessionRegistry::PUSH_QUEUE_SIZE);
+        let (close_sender, closed) = tokio::sync::watch::channel(None);
+        (Self { push_sender, close_sender }, SessionReceivers { pushed, closed })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SessionRegistry {
+    sessions: HashMap<EntityId, SessionEntry>,
+    default_interest_area: InterestArea,
+}
+
+impl SessionRegistry {
+    /// Pushed messages waiting for a slow client, newer ones are dropped when full
+    pub const PUSH_QUEUE_SIZE: usize = 64;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_default_interest_area(default_interest_area: InterestArea) -> Self {
+        Self {
+            sessions: HashMap::new(),
+            default_interest_area: default_interest_area.clamped()
+        }
+    }
+
+    pub fn into_shared(self) -> SharedSessionRegistry {
+        Arc::new(Mutex::new(self))
+    }
+
+    pub fn register(
+        &mut self,
+        player_id: EntityId,
+        address: std::net::SocketAddr,
+        channels: SessionChannels
+    ) {
+        self.sessions.insert(player_id, SessionEntry {
+            address,
+            channels,
+            chat_flood_guard: FloodGuard::default(),
+            interest: InterestState::new(self.default_interest_area)
+        });
+    }
+
+    pub fn unregister(&mut self, player_id: EntityId) -> Option<SessionEntry> {
+        self.sessions.remove(&player_id)
+    }
+
+    pub fn get_mut(&mut self, player_id: EntityId) -> Option<&mut SessionEntry> {
+        self.sessions.get_mut(&player_id)
+    }
+
+    /// Queue message to be sent to client. Returns false if client is gone
+    /// or lags behind and message was dropped.
+    pub fn push(&self, player_id: EntityId, response: ClientResponse) -> bool {
+        let Some(session) = self.sessions.get(&player_id) else {
+            return false;
+        };
+        match session.channels.push_sender.try_send(response) {
+            Ok(()) => true,
+            Err(tokio::sync::mpsc::error::TrySendError::Full(_)) => {
+                log::warn!("Client {} lags behind, pushed message dropped", session.address);
+                false
+            },
+            Err(tokio::sync::mpsc::error::TrySendError::Closed(_)) => false,
+        }
+    }
+
+    /// Close session with reason, not blocked by pushed messages waiting for client.
+    /// Returns false if client is gone.
+    pub fn kick(&self, player_id: EntityId, reason: String) -> bool {
+        self.sessions.get(&player_id)
+            .is_some_and(|session| session.channels.close_sender.send(Some(reason)).is_ok())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (EntityId, &SessionEntry)> {
+        self.sessions.iter().map(|(player_id, session)| (*player_id, session))
+    }
+
+    pub fn player_ids(&self) -> impl Iterator<Item = EntityId> + '_ {
+        self.sessions.keys().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+}
+
+#[test]
+fn test_session_registry_drops_pushes_to_lagging_client() {
+    let mut sessions = SessionRegistry::new();
+    let (channels, receivers) = SessionChannels::new();
+    sessions.register(1, "127.0.0.1:0".parse().unwrap(), channels);
+    let mut receiver = receivers.pushed;
+
+    let push = |sessions: &SessionRegistry| sessions.push(1, ClientResponse::Queued { position: 1 });
+    assert!((0..SessionRegistry::PUSH_QUEUE_SIZE).all(|_| push(&sessions)));
+    assert!(!push(&sessions));
+    assert_eq!(receiver.len(), SessionRegistry::PUSH_QUEUE_SIZE);
+
+    // Client catching up makes room again
+    receiver.try_recv().unwrap();
+    assert!(push(&sessions));
+
+    drop(receiver);
+    assert!(!push(&sessions));
+    assert!(!sessions.push(2, ClientResponse::Queued { position: 1 }));
+}
+
+#[test]
+fn test_session_registry_kicks_lagging_client() {
+    let mut sessions = SessionRegistry::new();
+    let (channels, mut receivers) = SessionChannels::new();
+    sessions.register(1, "127.0.0.1:0".parse().unwrap(), channels);
+    while sessions.push(1, ClientResponse::Queued { position: 1 }) {}
+
+    assert!(sessions.kick(1, "Bye".to_string()));
+    assert!(receivers.closed.has_changed().unwrap());
+    assert_eq!(receivers.closed.borrow_and_update().as_deref(), Some("Bye"));
+    assert!(!sessions.kick(2, "Bye".to_string()));
+
+    drop(receivers);
+    assert!(!sessions.kick(1, "Bye".to_string()));
+}
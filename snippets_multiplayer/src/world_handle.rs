@@ -0,0 +1,310 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant}
+};
+
+use crate::{
+    game::world::World,
+    metrics::SharedMetrics
+};
+
+/// Closure executed by the tick task with exclusive access to the world
+pub type WorldCommand = Box<dyn FnOnce(&mut World) + Send>;
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum WorldHandleError {
+    #[error("World task is not running")]
+    WorldTaskStopped,
+    #[error("World command queue is full")]
+    CommandQueueFull,
+}
+
+/// Access to world owned by the tick task. Writes go through bounded command queue,
+/// reads use snapshot published after every tick.
+#[derive(Clone)]
+pub struct WorldHandle {
+    command_sender: tokio::sync::mpsc::Sender<WorldCommand>,
+    snapshot_receiver: tokio::sync::watch::Receiver<Arc<World>>,
+    metrics: SharedMetrics,
+}
+
+pub struct WorldTaskHandler {
+    world_task_handler: tokio::task::JoinHandle<World>,
+    shutdown_sender: tokio::sync::oneshot::Sender<()>,
+}
+
+impl WorldHandle {
+    /// Commands waiting for the tick task, senders are slowed down or refused above it
+    pub const COMMAND_QUEUE_SIZE: usize = 1024;
+
+    /// Must be called within tokio runtime. Tick interval can be changed while running.
+    pub fn spawn(
+        mut world: World,
+        mut tick_interval_receiver: tokio::sync::watch::Receiver<Duration>,
+        metrics: SharedMetrics,
+        on_tick: impl Fn(&World) + Send + 'static
+    ) -> (Self, WorldTaskHandler) {
+        let (command_sender, mut command_receiver) = tokio::sync::mpsc::channel::<WorldCommand>(Self::COMMAND_QUEUE_SIZE);
+        let (snapshot_sender, snapshot_receiver) = tokio::sync::watch::channel(Arc::new(world.clone()));
+        let (shutdown_sender, mut shutdown_receiver) = tokio::sync::oneshot::channel();
+        let task_metrics = metrics.clone();
+
+        let world_task_handler = tokio::spawn(async move {
+            let mut next_tick = tokio::time::Instant::now() + *tick_interval_receiver.borrow_and_update();
+
+            loop {
+                tokio::select! {
+                    _ = &mut shutdown_receiver => {
+                        break;
+                    },
+                    Some(command) = command_receiver.recv() => {
+                        command(&mut world);
+                    },
+                    _ = tokio::time::sleep_until(next_tick) => {
+                        let tick_start = Instant::now();
+                        let chunk_events = world.tick();
+                        task_metrics.tick_duration_seconds.observe_duration(tick_start.elapsed());
+
+                        // Nobody reads snapshots once all handles are dropped
+                        if snapshot_sender.receiver_count() > 0 {
+                            snapshot_sender.send_replace(Arc::new(world.clone()));
+                        }
+                        for event in chunk_events {
+                            log::debug!("Chunk {event:?}");
+                        }
+                        on_tick(&world);
+
+                        // Keep steady rate, unless tick took longer than interval
+                        next_tick = (next_tick + *tick_interval_receiver.borrow_and_update()).max(tokio::time::Instant::now());
+                    },
+                }
+            }
+
+            // Commands already queued are still applied, so no request is lost silently
+            command_receiver.close();
+            while let Ok(command) = command_receiver.try_recv() {
+                command(&mut world);
+            }
+            world
+        });
+
+        let world_handle = Self {
+            command_sender,
+            snapshot_receiver,
+            metrics,
+        };
+        let world_task_handler = WorldTaskHandler {
+            world_task_handler,
+            shutdown_sender,
+        };
+        (world_handle, world_task_handler)
+    }
+
+    /// Run command on the tick task and wait for its result, waits for free place in full queue
+    pub async fn execute<R, F>(&self, command: F) -> Result<R, WorldHandleError>
+    where
+        F: FnOnce(&mut World) -> R + Send + 'static,
+        R: Send + 'static
+    {
+        let (result_sender, result_receiver) = tokio::sync::oneshot::channel();
+        let started = Instant::now();
+
+        let command: WorldCommand = Box::new(move |world| {
+            let _ = result_sender.send(command(world));
+        });
+        self.command_sender.send(command).await.map_err(|_| WorldHandleError::WorldTaskStopped)?;
+        let result = result_receiver.await.map_err(|_| WorldHandleError::WorldTaskStopped);
+
+        self.metrics.world_command_latency_seconds.observe_duration(started.elapsed());
+        result
+    }
+
+    /// Queue command without waiting, usable outside of async context. Refused when queue is full
+    pub fn send<F>(&self, command: F) -> Result<(), WorldHandleError>
+    where
+        F: FnOnce(&mut World) + Send + 'static
+    {
+        self.command_sender.try_send(Box::new(command)).map_err(|e| match e {
+            tokio::sync::mpsc::error::TrySendError::Full(_) => WorldHandleError::CommandQueueFull,
+            tokio::sync::mpsc::error::TrySendError::Closed(_) => WorldHandleError::WorldTaskStopped,
+        })
+    }
+
+    /// World as of the last tick
+    pub fn snapshot(&self) -> Arc<World> {
+        self.snapshot_receiver.borrow().clone()
+    }
+
+    /// Resolves after next tick
+    pub async fn next_snapshot(&mut self) -> Result<Arc<World>, WorldHandleError> {
+        self.snapshot_receiver.changed().await.map_err(|_| WorldHandleError::WorldTaskStopped)?;
+        Ok(self.snapshot_receiver.borrow_and_update().clone())
+    }
+
+    /// Wait until published snapshot reflects e.g. just executed command
+    pub async fn wait_for_snapshot<P>(&mut self, predicate: P) -> Result<Arc<World>, WorldHandleError>
+    where
+        P: Fn(&World) -> bool
+    {
+        let mut snapshot = self.snapshot();
+        while !predicate(&snapshot) {
+            snapshot = self.next_snapshot().await?;
+        }
+        Ok(snapshot)
+    }
+}
+
+impl WorldTaskHandler {
+    /// Stop ticking and hand back the final world state
+    pub async fn shutdown(self) -> Result<World, tokio::task::JoinError> {
+        let _ = self.shutdown_sender.send(());
+        self.world_task_handler.await
+    }
+}
+
+#[cfg(test)]
+fn test_world_handle_spawn(world: World, tick_interval: Duration) -> (WorldHandle, WorldTaskHandler) {
+    let (_, tick_interval_receiver) = tokio::sync::watch::channel(tick_interval);
+    WorldHandle::spawn(world, tick_interval_receiver, crate::metrics::Metrics::new().into_shared(), |_| {})
+}
+
+#[tokio::test]
+async fn test_world_handle_commands_and_snapshots() {
+    use crate::game::common::Vector2F;
+
+    let (mut world_handle, world_task) = test_world_handle_spawn(World::new(), Duration::from_millis(10));
+
+    let player_id = world_handle.execute(|world| {
+        world.create_entity_player("Player", Vector2F::new(0.0, 0.0), Vector2F::new(4.8, 4.8)).unwrap()
+    }).await.unwrap();
+    // Snapshot follows after tick
+    let snapshot = world_handle.next_snapshot().await.unwrap();
+    assert_eq!(snapshot.get_entity_by_id(player_id).unwrap().name, "Player");
+
+    world_handle.send(move |world| {
+        world.get_entity_by_id_mut(player_id).unwrap().name = "Renamed".to_string();
+    }).unwrap();
+    assert_eq!(snapshot.get_entity_by_id(player_id).unwrap().name, "Player", "Snapshot is immutable");
+
+    let world = world_task.shutdown().await.unwrap();
+    assert_eq!(world.get_entity_by_id(player_id).unwrap().name, "Renamed");
+    assert_eq!(world_handle.execute(|_| ()).await, Err(WorldHandleError::WorldTaskStopped));
+}
+
+#[tokio::test]
+async fn test_world_handle_full_command_queue() {
+    let (world_handle, world_task) = test_world_handle_spawn(World::new(), Duration::from_millis(10));
+
+    // Tick task does not run until this test yields, so nothing is taken from the queue
+    for _ in 0..WorldHandle::COMMAND_QUEUE_SIZE {
+        world_handle.send(|_| ()).unwrap();
+    }
+    assert_eq!(world_handle.send(|_| ()), Err(WorldHandleError::CommandQueueFull));
+    // Awaiting sender waits for free place instead
+    assert_eq!(world_handle.execute(|_| 5).await, Ok(5));
+
+    world_task.shutdown().await.unwrap();
+}
+
+/// Compares former `Arc<Mutex<World>>` design with command queue under the same load:
+/// many clients moving and reading the world while it ticks.
+/// Run with `cargo test --release -- --ignored --nocapture load_compared_to_mutex`
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+#[ignore = "load comparison, prints throughput"]
+async fn test_world_handle_load_compared_to_mutex() {
+    use std::sync::Mutex;
+    use crate::game::common::Vector2F;
+
+    const CLIENTS: usize = 32;
+    const REQUESTS_PER_CLIENT: usize = 300;
+    const TICK_INTERVAL: Duration = Duration::from_millis(5);
+
+    fn populated_world() -> (World, Vec<u32>) {
+        let mut world = World::new();
+        for idx in 0..200 {
+            world.create_entity_npc("Bot", Vector2F::new((idx % 20) as f32 * 10.0, (idx / 20) as f32 * 10.0), Vector2F::new(4.8, 4.8)).unwrap();
+        }
+        let players = (0..CLIENTS)
+            .map(|idx| world.create_entity_player("Player", Vector2F::new(idx as f32 * 10.0, -50.0), Vector2F::new(4.8, 4.8)).unwrap())
+            .collect();
+        (world, players)
+    }
+
+    fn visible_count(world: &World, player_id: u32) -> usize {
+        let position = world.get_entity_by_id(player_id).map_or(Vector2F::zero(), |e| e.position);
+        let area = crate::game::common::Rect2F::new(position.x - 80.0, position.y - 50.0, 160.0, 100.0);
+        world.query_entities_in_area(&area).count()
+    }
+
+    // Former design, every request locks the world
+    let (world, players) = populated_world();
+    let world = Arc::new(Mutex::new(world));
+    let ticking_world = world.clone();
+    let tick_task = tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(TICK_INTERVAL).await;
+            ticking_world.lock().unwrap().tick();
+        }
+    });
+    let started = Instant::now();
+    let clients: Vec<_> = players.iter().copied().map(|player_id| {
+        let world = world.clone();
+        tokio::spawn(async move {
+            for request_idx in 0..REQUESTS_PER_CLIENT {
+                if request_idx % 2 == 0 {
+                    let mut world_guard = world.lock().unwrap();
+                    let position = world_guard.get_entity_by_id(player_id).unwrap().position;
+                    let _ = world_guard.try_start_move_entity_to(player_id, position + Vector2F::new(5.0, 0.0));
+                } else {
+                    std::hint::black_box(visible_count(&world.lock().unwrap(), player_id));
+                }
+                tokio::task::yield_now().await;
+            }
+        })
+    }).collect();
+    for client in clients {
+        client.await.unwrap();
+    }
+    let mutex_elapsed = started.elapsed();
+    tick_task.abort();
+
+    // Command queue, reads from snapshot
+    let (world, players) = populated_world();
+    let (world_handle, world_task) = test_world_handle_spawn(world, TICK_INTERVAL);
+    let started = Instant::now();
+    let clients: Vec<_> = players.iter().copied().map(|player_id| {
+        let world_handle = world_handle.clone();
+        tokio::spawn(async move {
+            // Move is refused while previous one is in progress, but every command must run
+            let (mut moves_executed, mut moves_started) = (0, 0);
+            for request_idx in 0..REQUESTS_PER_CLIENT {
+                if request_idx % 2 == 0 {
+                    let started = world_handle.execute(move |world| {
+                        let position = world.get_entity_by_id(player_id).unwrap().position;
+                        world.try_start_move_entity_to(player_id, position + Vector2F::new(5.0, 0.0)).is_ok()
+                    }).await.unwrap();
+                    moves_executed += 1;
+                    moves_started += started as usize;
+                } else {
+                    std::hint::black_box(visible_count(&world_handle.snapshot(), player_id));
+                }
+                tokio::task::yield_now().await;
+            }
+            (moves_executed, moves_started)
+        })
+    }).collect();
+    let mut moves_executed = 0;
+    for client in clients {
+        let (executed, started) = client.await.unwrap();
+        assert!(started > 0, "Every player started moving");
+        moves_executed += executed;
+    }
+    let queue_elapsed = started.elapsed();
+    let world = world_task.shutdown().await.expect("World task did not stop cleanly");
+    assert_eq!(moves_executed, CLIENTS * REQUESTS_PER_CLIENT / 2, "Every move command was executed");
+    assert!(players.iter().all(|&player_id| world.get_entity_by_id(player_id).is_some()));
+
+    let requests = (CLIENTS * REQUESTS_PER_CLIENT) as f64;
+    println!("Mutex<World>:  {:>10.0} requests/s", requests / mutex_elapsed.as_secs_f64());
+    println!("Command queue: {:>10.0} requests/s", requests / queue_elapsed.as_secs_f64());
+}
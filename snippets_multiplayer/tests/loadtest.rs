@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+use snippets_multiplayer::{
+    game::map::MapDefinition, 
+    loadtest::{run_load_test, BehaviourProfile, LoadTestConfig}, 
+    multiplayer_server::MultiplayerServer
+};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_loadtest_against_local_server() {
+    let server = MultiplayerServer::bind_any_local().await.unwrap()
+        .with_map(MapDefinition::default());
+    let server_address = server.get_local_address().unwrap();
+    let server_handler = server.run().await.unwrap();
+
+    let report = run_load_test(LoadTestConfig {
+        address: server_address,
+        players: 20,
+        duration: Duration::from_secs(1),
+        profile: BehaviourProfile::Stress,
+        ramp_up: Duration::from_millis(200),
+    }).await;
+
+    assert_eq!(report.players_connected, 20);
+    assert_eq!(report.connect_errors, 0);
+    assert_eq!(report.errors, 0);
+    assert!(report.requests > 20);
+    // Last player starts connecting after 19/20 of ramp-up, which is not measured
+    assert!(report.ramp_up >= Duration::from_millis(190), "{report}");
+    assert!(report.elapsed >= Duration::from_secs(1), "{report}");
+    assert!(report.elapsed < Duration::from_secs(2), "Ramp-up should not be measured, {report}");
+    assert!(report.throughput() > 0.0);
+    let latency = report.latency;
+    assert!(Duration::ZERO < latency.p50 && latency.p50 <= latency.p90 && latency.p90 <= latency.p99 && latency.p99 <= latency.max, "{report}");
+
+    server_handler.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_loadtest_counts_rejected_players() {
+    let server = MultiplayerServer::bind_any_local().await.unwrap()
+        .with_max_players(2);
+    let server_address = server.get_local_address().unwrap();
+    let server_handler = server.run().await.unwrap();
+
+    let report = run_load_test(LoadTestConfig {
+        address: server_address,
+        players: 3,
+        duration: Duration::from_millis(300),
+        profile: BehaviourProfile::Observer,
+        ramp_up: Duration::from_millis(100),
+    }).await;
+
+    assert_eq!(report.players_connected, 2);
+    assert_eq!(report.connect_errors, 1);
+
+    server_handler.shutdown().await.unwrap();
+}